@@ -1,14 +1,14 @@
 use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use clearing_engine::core::currency::CurrencyCode;
+use clearing_engine::core::obligation::ObligationSet;
+use clearing_engine::core::party::PartyId;
 use clearing_engine::optimization::netting::NettingEngine;
-use clearing_engine::simulation::stress_test::{generate_random_network, NetworkConfig};
+use clearing_engine::simulation::bench_fixtures::standard_network;
+use rust_decimal::Decimal;
+use std::collections::{BTreeMap, HashMap};
 
 fn bench_netting_10_parties(c: &mut Criterion) {
-    let config = NetworkConfig {
-        party_count: 10,
-        avg_obligations_per_party: 5,
-        ..Default::default()
-    };
-    let set = generate_random_network(&config);
+    let set = standard_network(10);
 
     c.bench_function("netting_10_parties", |b| {
         b.iter(|| NettingEngine::multilateral_net(black_box(&set)))
@@ -16,12 +16,7 @@ fn bench_netting_10_parties(c: &mut Criterion) {
 }
 
 fn bench_netting_100_parties(c: &mut Criterion) {
-    let config = NetworkConfig {
-        party_count: 100,
-        avg_obligations_per_party: 10,
-        ..Default::default()
-    };
-    let set = generate_random_network(&config);
+    let set = standard_network(100);
 
     c.bench_function("netting_100_parties", |b| {
         b.iter(|| NettingEngine::multilateral_net(black_box(&set)))
@@ -29,22 +24,56 @@ fn bench_netting_100_parties(c: &mut Criterion) {
 }
 
 fn bench_netting_1000_parties(c: &mut Criterion) {
-    let config = NetworkConfig {
-        party_count: 1000,
-        avg_obligations_per_party: 10,
-        ..Default::default()
-    };
-    let set = generate_random_network(&config);
+    let set = standard_network(1000);
 
     c.bench_function("netting_1000_parties", |b| {
         b.iter(|| NettingEngine::multilateral_net(black_box(&set)))
     });
 }
 
+/// Accumulate and iterate net positions the way the ledger does, once
+/// per map type, to price keeping positions ordered.
+fn bench_position_maps(c: &mut Criterion) {
+    type Key = (PartyId, CurrencyCode);
+
+    fn positions<M>(set: &ObligationSet, mut adjust: impl FnMut(&mut M, Key, Decimal)) -> M
+    where
+        M: Default,
+    {
+        let mut map = M::default();
+        for ob in set.obligations() {
+            adjust(&mut map, (ob.debtor().clone(), ob.currency().clone()), -ob.amount());
+            adjust(&mut map, (ob.creditor().clone(), ob.currency().clone()), ob.amount());
+        }
+        map
+    }
+
+    let set = standard_network(1000);
+    let mut group = c.benchmark_group("position_map_1000_parties");
+    group.bench_function("hash_map", |b| {
+        b.iter(|| {
+            let map = positions(black_box(&set), |m: &mut HashMap<Key, Decimal>, k, v| {
+                *m.entry(k).or_insert(Decimal::ZERO) += v
+            });
+            map.values().map(|v| v.abs()).sum::<Decimal>()
+        })
+    });
+    group.bench_function("btree_map", |b| {
+        b.iter(|| {
+            let map = positions(black_box(&set), |m: &mut BTreeMap<Key, Decimal>, k, v| {
+                *m.entry(k).or_insert(Decimal::ZERO) += v
+            });
+            map.values().map(|v| v.abs()).sum::<Decimal>()
+        })
+    });
+    group.finish();
+}
+
 criterion_group!(
     benches,
     bench_netting_10_parties,
     bench_netting_100_parties,
-    bench_netting_1000_parties
+    bench_netting_1000_parties,
+    bench_position_maps
 );
 criterion_main!(benches);
@@ -0,0 +1,136 @@
+use rust_decimal::{Decimal, RoundingStrategy};
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+use thiserror::Error;
+
+/// Errors arising from parsing a monetary amount.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum AmountError {
+    #[error("amount is empty")]
+    Empty,
+    #[error("invalid amount '{0}': expected a plain decimal number such as \"1000.50\"")]
+    Malformed(String),
+    #[error("amount '{0}' is out of range")]
+    OutOfRange(String),
+    #[error("amount '{0}' has more decimal places than can be represented exactly")]
+    Overscaled(String),
+    #[error("amount must be positive, got {0}")]
+    NonPositive(Decimal),
+}
+
+/// Strictly parse a positive monetary amount from a string.
+///
+/// Only plain decimal notation is accepted: ASCII digits with an optional
+/// single `.` separating the fractional part. Signs, exponents, `NaN`,
+/// `inf`, thousands separators and surrounding whitespace are rejected,
+/// as is any value that cannot be represented exactly as a [`Decimal`]
+/// (rather than being silently rounded).
+///
+/// # Examples
+///
+/// ```
+/// use clearing_engine::core::amount::{parse_amount, AmountError};
+/// use rust_decimal_macros::dec;
+///
+/// assert_eq!(parse_amount("1000.50").unwrap(), dec!(1000.50));
+/// assert!(matches!(parse_amount("1e6"), Err(AmountError::Malformed(_))));
+/// ```
+pub fn parse_amount(raw: &str) -> Result<Decimal, AmountError> {
+    if raw.is_empty() {
+        return Err(AmountError::Empty);
+    }
+
+    let (integer, fraction) = match raw.split_once('.') {
+        Some((i, f)) => (i, Some(f)),
+        None => (raw, None),
+    };
+    let all_digits = |s: &str| !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit());
+    if !all_digits(integer) || !fraction.is_none_or(all_digits) {
+        return Err(AmountError::Malformed(raw.to_string()));
+    }
+
+    let amount =
+        Decimal::from_str(raw).map_err(|_| AmountError::OutOfRange(raw.to_string()))?;
+
+    // Decimal rounds excess fractional digits away; refuse instead.
+    let fraction_digits = fraction.map_or(0, str::len);
+    if amount.scale() as usize != fraction_digits {
+        return Err(AmountError::Overscaled(raw.to_string()));
+    }
+
+    if amount <= Decimal::ZERO {
+        return Err(AmountError::NonPositive(amount));
+    }
+    Ok(amount)
+}
+
+/// How to round amounts that fall exactly halfway between minor units.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RoundingMode {
+    /// Round half to even (banker's rounding): `0.125` → `0.12`.
+    #[default]
+    Bankers,
+    /// Round half away from zero: `0.125` → `0.13`.
+    HalfUp,
+}
+
+/// Round `amount` to `decimals` minor-unit places using `mode`.
+pub fn round_to_minor_units(amount: Decimal, decimals: u32, mode: RoundingMode) -> Decimal {
+    let strategy = match mode {
+        RoundingMode::Bankers => RoundingStrategy::MidpointNearestEven,
+        RoundingMode::HalfUp => RoundingStrategy::MidpointAwayFromZero,
+    };
+    amount.round_dp_with_strategy(decimals, strategy)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_parse_plain_amounts() {
+        assert_eq!(parse_amount("100").unwrap(), dec!(100));
+        assert_eq!(parse_amount("100000000.25").unwrap(), dec!(100000000.25));
+        assert_eq!(parse_amount("0.000001").unwrap(), dec!(0.000001));
+    }
+
+    #[test]
+    fn test_rejects_non_plain_notation() {
+        for raw in ["1e5", "NaN", "inf", "-100", "+100", " 100", "1,000", ".5", "5.", "1.2.3"] {
+            assert!(
+                matches!(parse_amount(raw), Err(AmountError::Malformed(_))),
+                "expected '{}' to be rejected",
+                raw
+            );
+        }
+        assert_eq!(parse_amount(""), Err(AmountError::Empty));
+    }
+
+    #[test]
+    fn test_rejects_non_positive() {
+        assert_eq!(parse_amount("0"), Err(AmountError::NonPositive(Decimal::ZERO)));
+        assert!(matches!(parse_amount("0.00"), Err(AmountError::NonPositive(_))));
+    }
+
+    #[test]
+    fn test_rejects_lossy_amounts() {
+        assert!(matches!(
+            parse_amount("1.00000000000000000000000000001"),
+            Err(AmountError::Overscaled(_))
+        ));
+        assert!(matches!(
+            parse_amount("99999999999999999999999999999999"),
+            Err(AmountError::OutOfRange(_))
+        ));
+    }
+
+    #[test]
+    fn test_rounding_modes_differ_at_midpoint() {
+        assert_eq!(round_to_minor_units(dec!(0.125), 2, RoundingMode::Bankers), dec!(0.12));
+        assert_eq!(round_to_minor_units(dec!(0.125), 2, RoundingMode::HalfUp), dec!(0.13));
+        assert_eq!(round_to_minor_units(dec!(0.135), 2, RoundingMode::Bankers), dec!(0.14));
+        assert_eq!(round_to_minor_units(dec!(-2.5), 0, RoundingMode::HalfUp), dec!(-3));
+        assert_eq!(RoundingMode::default(), RoundingMode::Bankers);
+    }
+}
@@ -1,3 +1,4 @@
+use crate::core::amount::{round_to_minor_units, RoundingMode};
 use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
@@ -106,12 +107,44 @@ impl fmt::Display for CurrencyPair {
 /// ).unwrap();
 /// assert_eq!(converted, dec!(200));
 /// ```
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FxRateTable {
     /// The base currency for normalization.
     pub base_currency: CurrencyCode,
     /// Direct rates: (from, to) -> rate.
+    #[serde(with = "pair_map_serde")]
     rates: HashMap<(CurrencyCode, CurrencyCode), Decimal>,
+    /// Two-sided quotes: (from, to) -> (bid, ask), in units of `to` per `from`.
+    #[serde(default, with = "pair_map_serde")]
+    quotes: HashMap<(CurrencyCode, CurrencyCode), (Decimal, Decimal)>,
+}
+
+/// Serde for maps keyed by currency pair, as a sorted list of
+/// `[from, to, value]` entries (JSON object keys must be strings).
+pub(crate) mod pair_map_serde {
+    use super::CurrencyCode;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use std::collections::HashMap;
+
+    pub fn serialize<S: Serializer, V: Serialize>(
+        map: &HashMap<(CurrencyCode, CurrencyCode), V>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        let mut entries: Vec<(&CurrencyCode, &CurrencyCode, &V)> =
+            map.iter().map(|((from, to), value)| (from, to, value)).collect();
+        entries.sort_by(|a, b| (a.0, a.1).cmp(&(b.0, b.1)));
+        entries.serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>, V: Deserialize<'de>>(
+        deserializer: D,
+    ) -> Result<HashMap<(CurrencyCode, CurrencyCode), V>, D::Error> {
+        let entries: Vec<(CurrencyCode, CurrencyCode, V)> = Vec::deserialize(deserializer)?;
+        Ok(entries
+            .into_iter()
+            .map(|(from, to, value)| ((from, to), value))
+            .collect())
+    }
 }
 
 impl FxRateTable {
@@ -120,6 +153,7 @@ impl FxRateTable {
         Self {
             base_currency,
             rates: HashMap::new(),
+            quotes: HashMap::new(),
         }
     }
 
@@ -137,6 +171,9 @@ impl FxRateTable {
                 rate,
             });
         }
+        // A plain mid rate replaces any earlier two-sided quote
+        self.quotes.remove(&(from.clone(), to.clone()));
+        self.quotes.remove(&(to.clone(), from.clone()));
         // Store direct rate
         self.rates.insert((from.clone(), to.clone()), rate);
         // Store inverse
@@ -145,6 +182,106 @@ impl FxRateTable {
         Ok(())
     }
 
+    /// Set a mid rate with a bid/ask spread of `spread_bps` basis points.
+    ///
+    /// The spread is split evenly around `mid`: bid = mid × (1 − s/2),
+    /// ask = mid × (1 + s/2). The mid rate is also stored, so
+    /// [`FxRateTable::get_rate`] and [`FxRateTable::convert`] keep working.
+    /// Inverse quotes are derived so that bid and ask swap sides.
+    pub fn set_rate_with_spread(
+        &mut self,
+        from: CurrencyCode,
+        to: CurrencyCode,
+        mid: Decimal,
+        spread_bps: u32,
+    ) -> Result<(), FxError> {
+        let half_spread = Decimal::from(spread_bps) / Decimal::from(20_000);
+        let bid = mid * (Decimal::ONE - half_spread);
+        let ask = mid * (Decimal::ONE + half_spread);
+        if bid <= Decimal::ZERO {
+            return Err(FxError::InvalidRate {
+                from,
+                to,
+                rate: bid,
+            });
+        }
+        self.set_rate(from.clone(), to.clone(), mid)?;
+        self.quotes
+            .insert((to.clone(), from.clone()), (Decimal::ONE / ask, Decimal::ONE / bid));
+        self.quotes.insert((from, to), (bid, ask));
+        Ok(())
+    }
+
+    /// Get the (bid, ask) quote for `from` in units of `to`.
+    ///
+    /// Pairs set without a spread quote bid = ask = mid.
+    pub fn get_quote(
+        &self,
+        from: &CurrencyCode,
+        to: &CurrencyCode,
+    ) -> Result<(Decimal, Decimal), FxError> {
+        if from == to {
+            return Ok((Decimal::ONE, Decimal::ONE));
+        }
+        if let Some(&quote) = self.quotes.get(&(from.clone(), to.clone())) {
+            return Ok(quote);
+        }
+        let mid = self.get_rate(from, to)?;
+        Ok((mid, mid))
+    }
+
+    /// Cost in `to` of buying `amount` of `from` (pays the ask).
+    pub fn convert_buy(
+        &self,
+        amount: Decimal,
+        from: &CurrencyCode,
+        to: &CurrencyCode,
+    ) -> Result<Decimal, FxError> {
+        let (_, ask) = self.get_quote(from, to)?;
+        Ok(amount * ask)
+    }
+
+    /// Proceeds in `to` of selling `amount` of `from` (receives the bid).
+    pub fn convert_sell(
+        &self,
+        amount: Decimal,
+        from: &CurrencyCode,
+        to: &CurrencyCode,
+    ) -> Result<Decimal, FxError> {
+        let (bid, _) = self.get_quote(from, to)?;
+        Ok(amount * bid)
+    }
+
+    /// Convert a signed position conservatively for exposure purposes.
+    ///
+    /// Receivables (positive) are valued at the bid and payables
+    /// (negative) at the ask, so the converted position is never more
+    /// favourable than what the market would actually deliver.
+    pub fn convert_conservative(
+        &self,
+        amount: Decimal,
+        from: &CurrencyCode,
+        to: &CurrencyCode,
+    ) -> Result<Decimal, FxError> {
+        if amount >= Decimal::ZERO {
+            self.convert_sell(amount, from, to)
+        } else {
+            self.convert_buy(amount, from, to)
+        }
+    }
+
+    /// Convert and round the result to `decimals` minor-unit places.
+    pub fn convert_rounded(
+        &self,
+        amount: Decimal,
+        from: &CurrencyCode,
+        to: &CurrencyCode,
+        decimals: u32,
+        mode: RoundingMode,
+    ) -> Result<Decimal, FxError> {
+        Ok(round_to_minor_units(self.convert(amount, from, to)?, decimals, mode))
+    }
+
     /// Get the exchange rate from one currency to another.
     pub fn get_rate(&self, from: &CurrencyCode, to: &CurrencyCode) -> Result<Decimal, FxError> {
         if from == to {
@@ -160,15 +297,119 @@ impl FxRateTable {
     }
 
     /// Convert an amount from one currency to another.
+    ///
+    /// Same-currency amounts pass through unchanged, so obligations already
+    /// in the target currency never need a rate entry.
     pub fn convert(
         &self,
         amount: Decimal,
         from: &CurrencyCode,
         to: &CurrencyCode,
     ) -> Result<Decimal, FxError> {
+        if from == to {
+            return Ok(amount);
+        }
         let rate = self.get_rate(from, to)?;
         Ok(amount * rate)
     }
+
+    /// Convert an amount, triangulating through the base currency when no
+    /// direct rate is set.
+    ///
+    /// The cross rate is `rate(from, base) × rate(base, to)`. If either leg
+    /// is missing, the error names that leg rather than the requested pair.
+    pub fn convert_via_base(
+        &self,
+        amount: Decimal,
+        from: &CurrencyCode,
+        to: &CurrencyCode,
+    ) -> Result<Decimal, FxError> {
+        let rate: Decimal = self
+            .conversion_path(from, to)?
+            .iter()
+            .map(|(_, _, rate)| rate)
+            .product();
+        Ok(amount * rate)
+    }
+
+    /// The legs `convert_via_base` would use, as (from, to, rate).
+    ///
+    /// Empty for a same-currency conversion, one leg when a direct rate is
+    /// set, otherwise two legs through the base currency.
+    pub fn conversion_path(
+        &self,
+        from: &CurrencyCode,
+        to: &CurrencyCode,
+    ) -> Result<Vec<(CurrencyCode, CurrencyCode, Decimal)>, FxError> {
+        if from == to {
+            return Ok(Vec::new());
+        }
+        if let Ok(rate) = self.get_rate(from, to) {
+            return Ok(vec![(from.clone(), to.clone(), rate)]);
+        }
+        let base = &self.base_currency;
+        Ok(vec![
+            (from.clone(), base.clone(), self.get_rate(from, base)?),
+            (base.clone(), to.clone(), self.get_rate(base, to)?),
+        ])
+    }
+}
+
+/// A synthetic unit of account defined as a basket of currencies.
+///
+/// One unit is worth the sum of fixed amounts of each component currency,
+/// in the style of the IMF's SDR (e.g. 0.6 USD + 2.0 BRL).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Basket {
+    /// Code under which amounts in the unit are reported, e.g. `XDR`.
+    pub code: CurrencyCode,
+    /// Amount of each component currency in one unit.
+    pub components: Vec<(CurrencyCode, Decimal)>,
+}
+
+impl Basket {
+    pub fn new(code: CurrencyCode) -> Self {
+        Self {
+            code,
+            components: Vec::new(),
+        }
+    }
+
+    /// Add `amount` of `currency` to the definition of one unit.
+    pub fn with_component(mut self, currency: CurrencyCode, amount: Decimal) -> Self {
+        self.components.push((currency, amount));
+        self
+    }
+
+    /// Value of one unit in `currency`, at the table's current rates.
+    pub fn unit_value(&self, rates: &FxRateTable, currency: &CurrencyCode) -> Result<Decimal, FxError> {
+        self.components
+            .iter()
+            .map(|(component, amount)| rates.convert(*amount, component, currency))
+            .sum()
+    }
+
+    /// Convert `amount` of `currency` into units of the basket.
+    ///
+    /// Values are compared in the table's base currency. Errors if a rate
+    /// is missing or the basket is worth nothing.
+    pub fn to_units(
+        &self,
+        rates: &FxRateTable,
+        amount: Decimal,
+        currency: &CurrencyCode,
+    ) -> Result<Decimal, FxError> {
+        let base = &rates.base_currency;
+        let unit_value = self.unit_value(rates, base)?;
+        if unit_value <= Decimal::ZERO {
+            return Err(FxError::InvalidRate {
+                from: self.code.clone(),
+                to: base.clone(),
+                rate: unit_value,
+            });
+        }
+        Ok(rates.convert(amount, currency, base)? / unit_value)
+    }
 }
 
 #[cfg(test)]
@@ -247,6 +488,44 @@ mod tests {
         assert_eq!(rate, Decimal::ONE);
     }
 
+    #[test]
+    fn test_same_currency_passes_through_empty_table() {
+        let usd = CurrencyCode::new("USD");
+        let table = FxRateTable::new(usd.clone());
+        assert_eq!(table.convert(dec!(100.50), &usd, &usd).unwrap(), dec!(100.50));
+        assert_eq!(table.get_quote(&usd, &usd).unwrap(), (Decimal::ONE, Decimal::ONE));
+        assert_eq!(table.convert_conservative(dec!(-25), &usd, &usd).unwrap(), dec!(-25));
+    }
+
+    #[test]
+    fn test_convert_via_base_triangulates() {
+        let usd = CurrencyCode::new("USD");
+        let brl = CurrencyCode::new("BRL");
+        let inr = CurrencyCode::new("INR");
+        let mut table = FxRateTable::new(usd.clone());
+        table.set_rate(brl.clone(), usd.clone(), dec!(0.20)).unwrap();
+        table.set_rate(inr.clone(), usd.clone(), dec!(0.0125)).unwrap();
+
+        assert!(table.convert(dec!(100), &brl, &inr).is_err());
+        // 100 BRL = 20 USD = 1600 INR
+        let converted = table.convert_via_base(dec!(100), &brl, &inr).unwrap();
+        assert_eq!(converted.round_dp(10), dec!(1600));
+    }
+
+    #[test]
+    fn test_convert_via_base_names_missing_leg() {
+        let usd = CurrencyCode::new("USD");
+        let brl = CurrencyCode::new("BRL");
+        let inr = CurrencyCode::new("INR");
+        let mut table = FxRateTable::new(usd.clone());
+        table.set_rate(brl.clone(), usd.clone(), dec!(0.20)).unwrap();
+
+        match table.convert_via_base(dec!(100), &brl, &inr) {
+            Err(FxError::RateNotFound { from, to }) => assert_eq!((from, to), (usd, inr)),
+            other => panic!("expected missing USD/INR leg, got {other:?}"),
+        }
+    }
+
     #[test]
     fn test_invalid_rate() {
         let mut table = FxRateTable::new(CurrencyCode::new("USD"));
@@ -257,4 +536,60 @@ mod tests {
         );
         assert!(result.is_err());
     }
+
+    #[test]
+    fn test_spread_buy_sell_differ() {
+        let usd = CurrencyCode::new("USD");
+        let brl = CurrencyCode::new("BRL");
+        let mut table = FxRateTable::new(usd.clone());
+        table
+            .set_rate_with_spread(brl.clone(), usd.clone(), dec!(0.20), 100)
+            .unwrap();
+
+        let buy = table.convert_buy(dec!(1000), &brl, &usd).unwrap();
+        let sell = table.convert_sell(dec!(1000), &brl, &usd).unwrap();
+        assert_eq!(buy, dec!(201));
+        assert_eq!(sell, dec!(199));
+        // Difference is the full spread on the mid value: 1% of 200
+        assert_eq!(buy - sell, dec!(2));
+        assert_eq!(table.convert(dec!(1000), &brl, &usd).unwrap(), dec!(200));
+
+        // Inverse direction: selling USD for BRL never yields more than mid
+        let back = table.convert_sell(dec!(200), &usd, &brl).unwrap();
+        assert!(back < dec!(1000));
+
+        // Conservative valuation: receivables at bid, payables at ask
+        assert_eq!(table.convert_conservative(dec!(1000), &brl, &usd).unwrap(), dec!(199));
+        assert_eq!(table.convert_conservative(dec!(-1000), &brl, &usd).unwrap(), dec!(-201));
+    }
+
+    #[test]
+    fn test_spread_too_wide() {
+        let mut table = FxRateTable::new(CurrencyCode::new("USD"));
+        let result = table.set_rate_with_spread(
+            CurrencyCode::new("BRL"),
+            CurrencyCode::new("USD"),
+            dec!(0.20),
+            20_000,
+        );
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_convert_rounded_modes() {
+        let usd = CurrencyCode::new("USD");
+        let brl = CurrencyCode::new("BRL");
+        let mut table = FxRateTable::new(usd.clone());
+        table.set_rate(usd.clone(), brl.clone(), dec!(5)).unwrap();
+
+        // 0.025 USD = 0.125 BRL, exactly halfway between cents
+        let bankers = table
+            .convert_rounded(dec!(0.025), &usd, &brl, 2, RoundingMode::Bankers)
+            .unwrap();
+        let half_up = table
+            .convert_rounded(dec!(0.025), &usd, &brl, 2, RoundingMode::HalfUp)
+            .unwrap();
+        assert_eq!(bankers, dec!(0.12));
+        assert_eq!(half_up, dec!(0.13));
+    }
 }
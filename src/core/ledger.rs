@@ -3,7 +3,7 @@ use crate::core::obligation::Obligation;
 use crate::core::party::PartyId;
 use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
 /// Tracks the net position of each party in each currency.
 ///
@@ -16,8 +16,9 @@ use std::collections::HashMap;
 pub struct Ledger {
     /// (PartyId, CurrencyCode) -> net balance
     /// Positive = net creditor, Negative = net debtor
+    /// Ordered, so every iteration over positions is deterministic.
     #[serde(with = "positions_serde")]
-    positions: HashMap<(PartyId, CurrencyCode), Decimal>,
+    positions: BTreeMap<(PartyId, CurrencyCode), Decimal>,
 }
 
 mod positions_serde {
@@ -26,7 +27,7 @@ mod positions_serde {
     use serde::de::{self, MapAccess, Visitor};
 
     pub fn serialize<S: serde::Serializer>(
-        positions: &HashMap<(PartyId, CurrencyCode), Decimal>,
+        positions: &BTreeMap<(PartyId, CurrencyCode), Decimal>,
         serializer: S,
     ) -> Result<S::Ok, S::Error> {
         let mut map = serializer.serialize_map(Some(positions.len()))?;
@@ -38,15 +39,15 @@ mod positions_serde {
 
     pub fn deserialize<'de, D: serde::Deserializer<'de>>(
         deserializer: D,
-    ) -> Result<HashMap<(PartyId, CurrencyCode), Decimal>, D::Error> {
+    ) -> Result<BTreeMap<(PartyId, CurrencyCode), Decimal>, D::Error> {
         struct V;
         impl<'de> Visitor<'de> for V {
-            type Value = HashMap<(PartyId, CurrencyCode), Decimal>;
+            type Value = BTreeMap<(PartyId, CurrencyCode), Decimal>;
             fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
                 f.write_str("a map with \"party:currency\" keys")
             }
             fn visit_map<M: MapAccess<'de>>(self, mut access: M) -> Result<Self::Value, M::Error> {
-                let mut map = HashMap::new();
+                let mut map = BTreeMap::new();
                 while let Some((key, value)) = access.next_entry::<String, Decimal>()? {
                     let (party, currency) = key.split_once(':')
                         .ok_or_else(|| de::Error::custom(format!("invalid key: {key}")))?;
@@ -73,6 +74,29 @@ impl Ledger {
         *self.positions.entry(creditor_key).or_insert(Decimal::ZERO) += obligation.amount();
     }
 
+    /// Amend an already-applied obligation to `new_amount`.
+    ///
+    /// Only the difference from `old`'s amount is applied to the debtor
+    /// and creditor, leaving positions exactly as if the amended
+    /// obligation had been applied in the first place.
+    pub fn adjust_obligation(&mut self, old: &Obligation, new_amount: Decimal) {
+        let delta = new_amount - old.amount();
+        self.adjust_position(old.debtor(), old.currency(), -delta);
+        self.adjust_position(old.creditor(), old.currency(), delta);
+    }
+
+    /// Shift a party's position in one currency by `delta`.
+    ///
+    /// Unlike [`Ledger::apply_obligation`] this touches a single side, so
+    /// it can leave the ledger unbalanced; callers are responsible for
+    /// the matching entry, if any.
+    pub fn adjust_position(&mut self, party: &PartyId, currency: &CurrencyCode, delta: Decimal) {
+        *self
+            .positions
+            .entry((party.clone(), currency.clone()))
+            .or_insert(Decimal::ZERO) += delta;
+    }
+
     /// Get the net position of a party in a specific currency.
     pub fn position(&self, party: &PartyId, currency: &CurrencyCode) -> Decimal {
         self.positions
@@ -91,7 +115,7 @@ impl Ledger {
     }
 
     /// Get all non-zero positions.
-    pub fn all_positions(&self) -> &HashMap<(PartyId, CurrencyCode), Decimal> {
+    pub fn all_positions(&self) -> &BTreeMap<(PartyId, CurrencyCode), Decimal> {
         &self.positions
     }
 
@@ -106,6 +130,11 @@ impl Ledger {
 
     /// Total absolute value of all net positions (sum of |position|).
     /// This represents the total amount that actually needs to settle.
+    ///
+    /// Note: amounts in different currencies are summed as raw numbers,
+    /// so the result is unit-mixed whenever the ledger holds more than one
+    /// currency. Use [`Ledger::net_settlement_by_currency`] for figures
+    /// that are meaningful in multi-currency reporting.
     pub fn total_net_settlement(&self) -> Decimal {
         // Sum positive positions only (equivalent to sum of |negative| positions)
         self.positions
@@ -113,6 +142,18 @@ impl Ledger {
             .filter(|v| **v > Decimal::ZERO)
             .sum()
     }
+
+    /// Net settlement required in each currency (sum of positive positions).
+    pub fn net_settlement_by_currency(&self) -> HashMap<CurrencyCode, Decimal> {
+        let mut totals: HashMap<CurrencyCode, Decimal> = HashMap::new();
+        for ((_, currency), amount) in &self.positions {
+            let total = totals.entry(currency.clone()).or_insert(Decimal::ZERO);
+            if *amount > Decimal::ZERO {
+                *total += amount;
+            }
+        }
+        totals
+    }
 }
 
 #[cfg(test)]
@@ -190,4 +231,61 @@ mod tests {
         );
         assert_eq!(ledger.total_net_settlement(), Decimal::ZERO);
     }
+
+    #[test]
+    fn test_net_settlement_by_currency() {
+        let mut ledger = Ledger::new();
+        let usd = CurrencyCode::new("USD");
+        let brl = CurrencyCode::new("BRL");
+        ledger.apply_obligation(&Obligation::new(
+            PartyId::new("A"),
+            PartyId::new("B"),
+            dec!(100),
+            usd.clone(),
+        ));
+        ledger.apply_obligation(&Obligation::new(
+            PartyId::new("B"),
+            PartyId::new("A"),
+            dec!(100),
+            usd.clone(),
+        ));
+        ledger.apply_obligation(&Obligation::new(
+            PartyId::new("A"),
+            PartyId::new("B"),
+            dec!(500),
+            brl.clone(),
+        ));
+
+        let totals = ledger.net_settlement_by_currency();
+        assert_eq!(totals.len(), 2);
+        assert_eq!(totals[&usd], Decimal::ZERO);
+        assert_eq!(totals[&brl], dec!(500));
+    }
+
+    #[test]
+    fn test_adjust_obligation_matches_rebuild() {
+        let usd = CurrencyCode::new("USD");
+        let original = Obligation::new(PartyId::new("A"), PartyId::new("B"), dec!(100), usd.clone());
+        let other = Obligation::new(PartyId::new("B"), PartyId::new("C"), dec!(70), usd.clone());
+
+        let mut ledger = Ledger::new();
+        ledger.apply_obligation(&original);
+        ledger.apply_obligation(&other);
+        ledger.adjust_obligation(&original, dec!(130));
+
+        let amended = Obligation::with_id(
+            original.id(),
+            PartyId::new("A"),
+            PartyId::new("B"),
+            dec!(130),
+            usd.clone(),
+        );
+        let mut rebuilt = Ledger::new();
+        rebuilt.apply_obligation(&amended);
+        rebuilt.apply_obligation(&other);
+
+        assert_eq!(ledger.all_positions(), rebuilt.all_positions());
+        assert_eq!(ledger.position(&PartyId::new("A"), &usd), dec!(-130));
+        assert!(ledger.is_balanced());
+    }
 }
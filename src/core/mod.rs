@@ -1,4 +1,8 @@
+pub mod amount;
 pub mod currency;
+pub mod event_log;
 pub mod ledger;
 pub mod obligation;
 pub mod party;
+pub mod scaled;
+pub mod schema;
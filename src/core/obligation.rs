@@ -1,10 +1,35 @@
-use crate::core::currency::CurrencyCode;
+use crate::core::currency::{CurrencyCode, FxError, FxRateTable};
 use crate::core::party::PartyId;
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Duration, DurationRound, Utc};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
+use thiserror::Error;
 use uuid::Uuid;
 
+/// Errors arising from constructing an invalid obligation.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum ObligationError {
+    #[error("obligation amount must be positive, got {0}")]
+    NonPositiveAmount(Decimal),
+    #[error("notional fraction must be in (0, 1], got {0}")]
+    InvalidFraction(Decimal),
+    #[error("debtor and creditor are both {0}")]
+    SelfObligation(PartyId),
+}
+
+/// Priority class of an obligation, used by cycle compression.
+///
+/// Protected obligations (e.g. collateral movements) are the last to be
+/// reduced: compression prefers cycles made only of standard obligations
+/// whenever such an alternative exists.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ObligationClass {
+    #[default]
+    Standard,
+    Protected,
+}
+
 /// A directed payment obligation between two parties.
 ///
 /// Represents the fact that `debtor` owes `creditor` a specific `amount`
@@ -48,6 +73,21 @@ pub struct Obligation {
     settlement_date: Option<DateTime<Utc>>,
     /// Optional reference or memo.
     reference: Option<String>,
+    /// Optional annualized simple interest rate (e.g. 0.10 = 10% p.a.).
+    #[serde(default)]
+    rate: Option<Decimal>,
+    /// Priority class for cycle compression.
+    #[serde(default)]
+    class: ObligationClass,
+    /// Account the debtor pays from, if not its default account.
+    #[serde(default)]
+    debtor_account: Option<String>,
+    /// Account the creditor is paid into, if not its default account.
+    #[serde(default)]
+    creditor_account: Option<String>,
+    /// Clearing batch this obligation belongs to, if any.
+    #[serde(default)]
+    batch_id: Option<String>,
 }
 
 impl Obligation {
@@ -55,19 +95,35 @@ impl Obligation {
     ///
     /// # Panics
     ///
-    /// Panics if `amount` is not positive.
+    /// Panics if `amount` is not positive or `debtor` equals `creditor`;
+    /// see [`Obligation::try_new`] for a fallible constructor.
     pub fn new(
         debtor: PartyId,
         creditor: PartyId,
         amount: Decimal,
         currency: CurrencyCode,
     ) -> Self {
-        assert!(
-            amount > Decimal::ZERO,
-            "Obligation amount must be positive, got {}",
-            amount
-        );
-        Self {
+        match Self::try_new(debtor, creditor, amount, currency) {
+            Ok(obligation) => obligation,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    /// Create a new obligation, rejecting a non-positive `amount` and a
+    /// party owing itself.
+    pub fn try_new(
+        debtor: PartyId,
+        creditor: PartyId,
+        amount: Decimal,
+        currency: CurrencyCode,
+    ) -> Result<Self, ObligationError> {
+        if amount <= Decimal::ZERO {
+            return Err(ObligationError::NonPositiveAmount(amount));
+        }
+        if debtor == creditor {
+            return Err(ObligationError::SelfObligation(debtor));
+        }
+        Ok(Self {
             id: Uuid::new_v4(),
             debtor,
             creditor,
@@ -76,7 +132,30 @@ impl Obligation {
             created_at: Utc::now(),
             settlement_date: None,
             reference: None,
+            rate: None,
+            class: ObligationClass::Standard,
+            debtor_account: None,
+            creditor_account: None,
+            batch_id: None,
+        })
+    }
+
+    /// Create an obligation for `fraction` of a `notional` amount.
+    ///
+    /// Derivatives settlement often states obligations as a share of a
+    /// notional; the amount is `notional × fraction`, computed exactly.
+    /// `fraction` must lie in (0, 1].
+    pub fn from_notional(
+        debtor: PartyId,
+        creditor: PartyId,
+        notional: Decimal,
+        fraction: Decimal,
+        currency: CurrencyCode,
+    ) -> Result<Self, ObligationError> {
+        if fraction <= Decimal::ZERO || fraction > Decimal::ONE {
+            return Err(ObligationError::InvalidFraction(fraction));
         }
+        Self::try_new(debtor, creditor, notional * fraction, currency)
     }
 
     /// Create an obligation with a specific ID (useful for testing / determinism).
@@ -97,6 +176,11 @@ impl Obligation {
             created_at: Utc::now(),
             settlement_date: None,
             reference: None,
+            rate: None,
+            class: ObligationClass::Standard,
+            debtor_account: None,
+            creditor_account: None,
+            batch_id: None,
         }
     }
 
@@ -106,12 +190,48 @@ impl Obligation {
         self
     }
 
+    /// Set the creation timestamp (useful for replaying historical feeds).
+    pub fn with_created_at(mut self, created_at: DateTime<Utc>) -> Self {
+        self.created_at = created_at;
+        self
+    }
+
     /// Set a reference string.
     pub fn with_reference(mut self, reference: impl Into<String>) -> Self {
         self.reference = Some(reference.into());
         self
     }
 
+    /// Set the account the debtor pays from.
+    pub fn with_debtor_account(mut self, account: impl Into<String>) -> Self {
+        self.debtor_account = Some(account.into());
+        self
+    }
+
+    /// Set the account the creditor is paid into.
+    pub fn with_creditor_account(mut self, account: impl Into<String>) -> Self {
+        self.creditor_account = Some(account.into());
+        self
+    }
+
+    /// Assign the obligation to a named clearing batch.
+    pub fn with_batch_id(mut self, batch_id: impl Into<String>) -> Self {
+        self.batch_id = Some(batch_id.into());
+        self
+    }
+
+    /// Set the priority class.
+    pub fn with_class(mut self, class: ObligationClass) -> Self {
+        self.class = class;
+        self
+    }
+
+    /// Set an annualized simple interest rate accruing from `created_at`.
+    pub fn with_rate(mut self, rate: Decimal) -> Self {
+        self.rate = Some(rate);
+        self
+    }
+
     // --- Accessors ---
 
     pub fn id(&self) -> Uuid {
@@ -145,6 +265,88 @@ impl Obligation {
     pub fn reference(&self) -> Option<&str> {
         self.reference.as_deref()
     }
+
+    pub fn debtor_account(&self) -> Option<&str> {
+        self.debtor_account.as_deref()
+    }
+
+    pub fn creditor_account(&self) -> Option<&str> {
+        self.creditor_account.as_deref()
+    }
+
+    pub fn batch_id(&self) -> Option<&str> {
+        self.batch_id.as_deref()
+    }
+
+    pub fn rate(&self) -> Option<Decimal> {
+        self.rate
+    }
+
+    pub fn class(&self) -> ObligationClass {
+        self.class
+    }
+
+    /// True if both obligations move the same value between the same
+    /// parties on the same terms (debtor, creditor, amount, currency,
+    /// settlement date and rate). Ids, timestamps, references and class
+    /// are ignored.
+    pub fn same_economics(&self, other: &Obligation) -> bool {
+        self.debtor == other.debtor
+            && self.creditor == other.creditor
+            && self.amount == other.amount
+            && self.currency == other.currency
+            && self.settlement_date == other.settlement_date
+            && self.rate == other.rate
+    }
+
+    /// A copy for `amount` in `currency`, keeping the id, parties,
+    /// accounts, batch, class, dates and every other field.
+    ///
+    /// Used when netting paths restate amounts, so the restated set still
+    /// carries the accounts settlement instructions are addressed to.
+    pub(crate) fn restated(&self, amount: Decimal, currency: CurrencyCode) -> Obligation {
+        Obligation {
+            amount,
+            currency,
+            ..self.clone()
+        }
+    }
+
+    /// The amount including simple interest accrued up to `as_of`.
+    ///
+    /// Interest runs from `created_at` to `as_of`, but never past the
+    /// settlement date, using an Actual/365 day count. Obligations
+    /// without a rate, or with an accrual period that has not started,
+    /// return their face amount.
+    pub fn accrued_amount(&self, as_of: DateTime<Utc>) -> Decimal {
+        let Some(rate) = self.rate else {
+            return self.amount;
+        };
+        let end = match self.settlement_date {
+            Some(date) if date < as_of => date,
+            _ => as_of,
+        };
+        let seconds = (end - self.created_at).num_seconds();
+        if seconds <= 0 {
+            return self.amount;
+        }
+        let year_fraction = Decimal::from(seconds) / Decimal::from(SECONDS_PER_YEAR);
+        self.amount * (Decimal::ONE + rate * year_fraction)
+    }
+}
+
+/// Seconds in a 365-day year (Actual/365 day count).
+const SECONDS_PER_YEAR: i64 = 365 * 24 * 60 * 60;
+
+/// An id present in both sets of a [`ObligationSet::merge_by_id`] with
+/// differing economic content.
+#[derive(Debug, Clone)]
+pub struct IdConflict {
+    pub id: Uuid,
+    /// The obligation already in the set, which is kept.
+    pub existing: Obligation,
+    /// The incoming obligation, which is dropped.
+    pub incoming: Obligation,
 }
 
 /// A collection of obligations that can be submitted to the clearing engine.
@@ -164,10 +366,123 @@ impl ObligationSet {
         self.obligations.push(obligation);
     }
 
+    /// Change the amount of the obligation with the given id.
+    ///
+    /// Returns the obligation as it stood before the amendment, or `None`
+    /// if no obligation has that id. A non-positive `new_amount` is
+    /// rejected and leaves the set unchanged.
+    pub fn amend(
+        &mut self,
+        id: Uuid,
+        new_amount: Decimal,
+    ) -> Result<Option<Obligation>, ObligationError> {
+        if new_amount <= Decimal::ZERO {
+            return Err(ObligationError::NonPositiveAmount(new_amount));
+        }
+        Ok(self.obligations.iter_mut().find(|o| o.id == id).map(|ob| {
+            let previous = ob.clone();
+            ob.amount = new_amount;
+            previous
+        }))
+    }
+
+    /// Remove the obligation with the given id, returning it if present.
+    pub fn remove(&mut self, id: Uuid) -> Option<Obligation> {
+        let index = self.obligations.iter().position(|o| o.id() == id)?;
+        Some(self.obligations.remove(index))
+    }
+
     pub fn obligations(&self) -> &[Obligation] {
         &self.obligations
     }
 
+    /// Pairs of obligations that look like double bookings.
+    ///
+    /// Two obligations are flagged when they share debtor, creditor and
+    /// currency and their amounts differ by at most `tolerance`, catching
+    /// near-duplicates (e.g. off by a rounding penny) that exact
+    /// deduplication misses. Pairs are (earlier, later) in set order.
+    pub fn near_duplicates(&self, tolerance: Decimal) -> Vec<(Uuid, Uuid)> {
+        let mut groups: HashMap<(&PartyId, &PartyId, &CurrencyCode), Vec<&Obligation>> =
+            HashMap::new();
+        for ob in &self.obligations {
+            groups
+                .entry((&ob.debtor, &ob.creditor, &ob.currency))
+                .or_default()
+                .push(ob);
+        }
+        let position: HashMap<Uuid, usize> = self
+            .obligations
+            .iter()
+            .enumerate()
+            .map(|(i, o)| (o.id, i))
+            .collect();
+
+        let mut pairs = Vec::new();
+        for group in groups.values() {
+            for (i, a) in group.iter().enumerate() {
+                for b in &group[i + 1..] {
+                    if (a.amount - b.amount).abs() <= tolerance {
+                        pairs.push((a.id, b.id));
+                    }
+                }
+            }
+        }
+        pairs.sort_by_key(|(a, b)| (position[a], position[b]));
+        pairs
+    }
+
+    /// Each obligation's amount converted into `base`, without changing the set.
+    ///
+    /// Returns (id, original amount, converted amount) in set order, so
+    /// operators can review a cross-currency run before committing to a
+    /// settlement currency. Errors on the first missing rate.
+    pub fn convert_preview(
+        &self,
+        rates: &FxRateTable,
+        base: &CurrencyCode,
+    ) -> Result<Vec<(Uuid, Decimal, Decimal)>, FxError> {
+        self.obligations
+            .iter()
+            .map(|ob| Ok((ob.id, ob.amount, rates.convert(ob.amount, &ob.currency, base)?)))
+            .collect()
+    }
+
+    /// Union `other` into this set, deduplicating by id.
+    ///
+    /// Obligations with new ids are appended. An id already present with
+    /// the same economics (see [`Obligation::same_economics`]) is a
+    /// duplicate and skipped; with different economics the existing
+    /// obligation is kept and the pair is reported as a conflict.
+    pub fn merge_by_id(&mut self, other: ObligationSet) -> Vec<IdConflict> {
+        let mut index: HashMap<Uuid, usize> = self
+            .obligations
+            .iter()
+            .enumerate()
+            .map(|(i, o)| (o.id, i))
+            .collect();
+        let mut conflicts = Vec::new();
+        for incoming in other.obligations {
+            match index.get(&incoming.id) {
+                Some(&i) => {
+                    let existing = &self.obligations[i];
+                    if !existing.same_economics(&incoming) {
+                        conflicts.push(IdConflict {
+                            id: incoming.id,
+                            existing: existing.clone(),
+                            incoming,
+                        });
+                    }
+                }
+                None => {
+                    index.insert(incoming.id, self.obligations.len());
+                    self.obligations.push(incoming);
+                }
+            }
+        }
+        conflicts
+    }
+
     pub fn len(&self) -> usize {
         self.obligations.len()
     }
@@ -204,6 +519,205 @@ impl ObligationSet {
         currencies.dedup();
         currencies
     }
+
+    /// Obligations whose debtor or creditor is not in `allowed`.
+    ///
+    /// An empty result means every obligation may be admitted to a closed
+    /// clearing system with the given membership.
+    pub fn validate_parties(&self, allowed: &HashSet<PartyId>) -> Vec<Obligation> {
+        self.obligations
+            .iter()
+            .filter(|o| !allowed.contains(o.debtor()) || !allowed.contains(o.creditor()))
+            .cloned()
+            .collect()
+    }
+
+    /// Split the set into fixed-duration time buckets keyed on `created_at`.
+    ///
+    /// Buckets are aligned to multiples of `bucket` since the Unix epoch
+    /// (e.g. one-hour buckets start on the hour) and returned in
+    /// chronological order. Only non-empty buckets are returned.
+    /// Obligations whose timestamp cannot be aligned to the bucket grid
+    /// are placed in the first bucket.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `bucket` is not positive.
+    pub fn split_by_time(&self, bucket: Duration) -> Vec<(DateTime<Utc>, ObligationSet)> {
+        assert!(
+            bucket > Duration::zero(),
+            "Bucket duration must be positive, got {}",
+            bucket
+        );
+
+        let mut buckets: BTreeMap<DateTime<Utc>, ObligationSet> = BTreeMap::new();
+        let mut unaligned = Vec::new();
+
+        for ob in &self.obligations {
+            match ob.created_at().duration_trunc(bucket) {
+                Ok(start) => buckets.entry(start).or_default().add(ob.clone()),
+                Err(_) => unaligned.push(ob.clone()),
+            }
+        }
+
+        if !unaligned.is_empty() {
+            let first = buckets
+                .keys()
+                .next()
+                .copied()
+                .unwrap_or_else(|| unaligned[0].created_at());
+            let set = buckets.entry(first).or_default();
+            for ob in unaligned {
+                set.add(ob);
+            }
+        }
+
+        buckets.into_iter().collect()
+    }
+
+    /// Rewrite every `from`-denominated obligation into `to` at `rate`.
+    ///
+    /// 1 unit of `from` becomes `rate` units of `to`. Amounts are scaled
+    /// exactly in decimal arithmetic; ids, timestamps and references are
+    /// preserved. Obligations in other currencies are copied unchanged.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `rate` is not positive.
+    pub fn redenominate(
+        &self,
+        from: &CurrencyCode,
+        to: &CurrencyCode,
+        rate: Decimal,
+    ) -> ObligationSet {
+        assert!(
+            rate > Decimal::ZERO,
+            "Redenomination rate must be positive, got {}",
+            rate
+        );
+        self.obligations
+            .iter()
+            .map(|ob| {
+                if ob.currency() == from {
+                    Obligation {
+                        amount: ob.amount * rate,
+                        currency: to.clone(),
+                        ..ob.clone()
+                    }
+                } else {
+                    ob.clone()
+                }
+            })
+            .collect()
+    }
+
+    /// Sum obligations sharing (debtor, creditor, currency) into one.
+    ///
+    /// Only obligations that also agree on batch, rate, creation time and
+    /// both accounts are summed, so batch, accrual and point-in-time
+    /// netting give the same results before and after aggregation.
+    /// Unlike deduplication nothing is dropped: each group becomes a
+    /// single obligation for the summed amount, placed where the group
+    /// first appears and keeping the first member's id. Distinct
+    /// references are joined with `"; "`, the latest settlement date is
+    /// kept, and the group is protected if any member is.
+    pub fn aggregate(&self) -> ObligationSet {
+        let key = |ob: &Obligation| {
+            (
+                (ob.debtor.clone(), ob.creditor.clone(), ob.currency.clone()),
+                ob.batch_id.clone(),
+                ob.rate,
+                ob.created_at,
+                (ob.debtor_account.clone(), ob.creditor_account.clone()),
+            )
+        };
+        let mut index: HashMap<_, usize> = HashMap::new();
+        let mut merged: Vec<(Obligation, Vec<String>)> = Vec::new();
+        for ob in &self.obligations {
+            match index.get(&key(ob)) {
+                Some(&i) => {
+                    let (group, references) = &mut merged[i];
+                    group.amount += ob.amount;
+                    group.settlement_date = group.settlement_date.max(ob.settlement_date);
+                    if ob.class == ObligationClass::Protected {
+                        group.class = ObligationClass::Protected;
+                    }
+                    if let Some(reference) = &ob.reference {
+                        if !references.contains(reference) {
+                            references.push(reference.clone());
+                        }
+                    }
+                }
+                None => {
+                    index.insert(key(ob), merged.len());
+                    merged.push((ob.clone(), ob.reference.iter().cloned().collect()));
+                }
+            }
+        }
+        merged
+            .into_iter()
+            .map(|(group, references)| Obligation {
+                reference: (!references.is_empty()).then(|| references.join("; ")),
+                ..group
+            })
+            .collect()
+    }
+
+    /// Copy of this set with every party id normalized.
+    ///
+    /// See [`PartyId::normalized`]: ids differing only in casing or
+    /// surrounding whitespace collapse into a single party.
+    pub fn normalize_parties(&self) -> ObligationSet {
+        self.obligations
+            .iter()
+            .map(|ob| Obligation {
+                debtor: PartyId::normalized(ob.debtor.as_str()),
+                creditor: PartyId::normalized(ob.creditor.as_str()),
+                ..ob.clone()
+            })
+            .collect()
+    }
+
+    /// Canonical form of this set, for deterministic hashing and diffing.
+    ///
+    /// Obligations are sorted by (debtor, creditor, currency, amount), with
+    /// the remaining fields breaking ties, and amounts are normalized so
+    /// `100` and `100.00` compare equal. Each id is replaced by a v5 UUID
+    /// derived from the obligation's position and contents, so equivalent
+    /// sets canonicalize to identical serializations regardless of input
+    /// order or original ids.
+    pub fn canonicalize(&self) -> ObligationSet {
+        let mut sorted: Vec<Obligation> = self
+            .obligations
+            .iter()
+            .map(|ob| Obligation {
+                amount: ob.amount.normalize(),
+                ..ob.clone()
+            })
+            .collect();
+        sorted.sort_by(|a, b| {
+            (&a.debtor, &a.creditor, &a.currency, a.amount)
+                .cmp(&(&b.debtor, &b.creditor, &b.currency, b.amount))
+                .then_with(|| a.created_at.cmp(&b.created_at))
+                .then_with(|| a.settlement_date.cmp(&b.settlement_date))
+                .then_with(|| a.reference.cmp(&b.reference))
+                .then_with(|| a.rate.cmp(&b.rate))
+        });
+        sorted
+            .into_iter()
+            .enumerate()
+            .map(|(index, ob)| {
+                let name = format!(
+                    "{}:{}>{}:{}:{}",
+                    index, ob.debtor, ob.creditor, ob.amount, ob.currency
+                );
+                Obligation {
+                    id: Uuid::new_v5(&Uuid::NAMESPACE_OID, name.as_bytes()),
+                    ..ob
+                }
+            })
+            .collect()
+    }
 }
 
 impl FromIterator<Obligation> for ObligationSet {
@@ -214,11 +728,81 @@ impl FromIterator<Obligation> for ObligationSet {
     }
 }
 
+/// Fluent construction of an [`ObligationSet`] for tests and tooling.
+///
+/// # Examples
+///
+/// ```
+/// use clearing_engine::core::obligation::ObligationSetBuilder;
+/// use rust_decimal_macros::dec;
+///
+/// let set = ObligationSetBuilder::new()
+///     .cycle(&["A", "B", "C"], dec!(100), "USD")
+///     .obligation("A", "D", dec!(25), "USD")
+///     .build();
+/// assert_eq!(set.len(), 4);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ObligationSetBuilder {
+    set: ObligationSet,
+}
+
+impl ObligationSetBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add one obligation: `from` owes `to` `amount` in `currency`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `amount` is not positive, like [`Obligation::new`].
+    pub fn obligation(
+        mut self,
+        from: impl Into<PartyId>,
+        to: impl Into<PartyId>,
+        amount: Decimal,
+        currency: impl Into<CurrencyCode>,
+    ) -> Self {
+        self.set.add(Obligation::new(from.into(), to.into(), amount, currency.into()));
+        self
+    }
+
+    /// Add a full cycle: each party owes the next `amount`, and the last
+    /// owes the first. Fewer than two parties add nothing.
+    pub fn cycle<P: Clone + Into<PartyId>>(
+        mut self,
+        parties: &[P],
+        amount: Decimal,
+        currency: impl Into<CurrencyCode>,
+    ) -> Self {
+        if parties.len() < 2 {
+            return self;
+        }
+        let currency = currency.into();
+        for (i, from) in parties.iter().enumerate() {
+            let to = &parties[(i + 1) % parties.len()];
+            self = self.obligation(from.clone(), to.clone(), amount, currency.clone());
+        }
+        self
+    }
+
+    pub fn build(self) -> ObligationSet {
+        self.set
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use chrono::TimeZone;
     use rust_decimal_macros::dec;
 
+    fn ob(debtor: &str, creditor: &str, amount: Decimal) -> Obligation {
+        let usd = CurrencyCode::new("USD");
+        Obligation::new(PartyId::new(debtor), PartyId::new(creditor), amount, usd)
+    }
+
     fn sample_obligation() -> Obligation {
         Obligation::new(
             PartyId::new("A"),
@@ -296,4 +880,295 @@ mod tests {
         let parties = set.parties();
         assert_eq!(parties.len(), 3);
     }
+
+    #[test]
+    fn test_validate_parties_flags_unregistered() {
+        let usd = CurrencyCode::new("USD");
+        let mut set = ObligationSet::new();
+        set.add(Obligation::new(PartyId::new("A"), PartyId::new("B"), dec!(100), usd.clone()));
+        set.add(Obligation::new(PartyId::new("B"), PartyId::new("X"), dec!(50), usd));
+
+        let allowed: HashSet<PartyId> = [PartyId::new("A"), PartyId::new("B")].into();
+        let rejected = set.validate_parties(&allowed);
+        assert_eq!(rejected.len(), 1);
+        assert_eq!(rejected[0].creditor(), &PartyId::new("X"));
+    }
+
+    #[test]
+    fn test_split_by_time_hourly() {
+        let ten = Utc.with_ymd_and_hms(2024, 3, 1, 10, 0, 0).unwrap();
+        let usd = CurrencyCode::new("USD");
+        let mut set = ObligationSet::new();
+        set.add(
+            Obligation::new(PartyId::new("A"), PartyId::new("B"), dec!(100), usd.clone())
+                .with_created_at(ten + Duration::minutes(15)),
+        );
+        set.add(
+            Obligation::new(PartyId::new("B"), PartyId::new("C"), dec!(200), usd.clone())
+                .with_created_at(ten + Duration::minutes(75)),
+        );
+        set.add(
+            Obligation::new(PartyId::new("C"), PartyId::new("A"), dec!(50), usd)
+                .with_created_at(ten + Duration::minutes(45)),
+        );
+
+        let buckets = set.split_by_time(Duration::hours(1));
+        assert_eq!(buckets.len(), 2);
+        assert_eq!(buckets[0].0, ten);
+        assert_eq!(buckets[0].1.len(), 2);
+        assert_eq!(buckets[1].0, ten + Duration::hours(1));
+        assert_eq!(buckets[1].1.gross_total(), dec!(200));
+    }
+
+    #[test]
+    fn test_accrued_amount_stops_at_settlement_date() {
+        let start = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        let ob = sample_obligation()
+            .with_created_at(start)
+            .with_settlement_date(start + Duration::days(365))
+            .with_rate(dec!(0.10));
+
+        assert_eq!(ob.accrued_amount(start), dec!(1000));
+        assert_eq!(ob.accrued_amount(start + Duration::days(73)), dec!(1020));
+        assert_eq!(ob.accrued_amount(start + Duration::days(730)), dec!(1100));
+        assert_eq!(sample_obligation().accrued_amount(start), dec!(1000));
+    }
+
+    #[test]
+    fn test_redenominate_brl_to_usd() {
+        let usd = CurrencyCode::new("USD");
+        let brl = CurrencyCode::new("BRL");
+        let mut set = ObligationSet::new();
+        set.add(Obligation::new(PartyId::new("A"), PartyId::new("B"), dec!(1000), brl.clone()));
+        set.add(Obligation::new(PartyId::new("B"), PartyId::new("C"), dec!(333.33), brl.clone()));
+        set.add(Obligation::new(PartyId::new("C"), PartyId::new("A"), dec!(50), usd.clone()));
+
+        let converted = set.redenominate(&brl, &usd, dec!(0.20));
+        assert_eq!(converted.len(), 3);
+        assert_eq!(converted.currencies(), vec![usd]);
+        assert_eq!(converted.obligations()[0].amount(), dec!(200));
+        assert_eq!(converted.obligations()[0].id(), set.obligations()[0].id());
+        assert_eq!(converted.obligations()[1].amount(), dec!(66.666));
+        assert_eq!(converted.obligations()[2].amount(), dec!(50));
+    }
+
+    #[test]
+    fn test_canonicalize_is_order_independent() {
+        let at = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        let ob_at = |d: &str, c: &str, amount| ob(d, c, amount).with_created_at(at);
+        let first: ObligationSet = vec![
+            ob_at("A", "B", dec!(100)),
+            ob_at("B", "C", dec!(50)),
+            ob_at("A", "B", dec!(20)),
+        ]
+        .into_iter()
+        .collect();
+        let second: ObligationSet = vec![
+            ob_at("A", "B", dec!(20)),
+            ob_at("B", "C", dec!(50.00)),
+            ob_at("A", "B", dec!(100)),
+        ]
+        .into_iter()
+        .collect();
+
+        let a = serde_json::to_vec(&first.canonicalize()).unwrap();
+        let b = serde_json::to_vec(&second.canonicalize()).unwrap();
+        assert_eq!(a, b);
+
+        let canonical = first.canonicalize();
+        let amounts: Vec<Decimal> = canonical.obligations().iter().map(|o| o.amount()).collect();
+        assert_eq!(amounts, vec![dec!(20), dec!(100), dec!(50)]);
+    }
+
+    #[test]
+    fn test_merge_by_id_reports_conflicts() {
+        let shared = Uuid::new_v4();
+        let duplicate = Uuid::new_v4();
+        let ob = |id, amount| {
+            Obligation::with_id(id, PartyId::new("A"), PartyId::new("B"), amount, CurrencyCode::new("USD"))
+        };
+
+        let mut feed_a: ObligationSet = vec![ob(shared, dec!(100)), ob(duplicate, dec!(5))]
+            .into_iter()
+            .collect();
+        let feed_b: ObligationSet = vec![ob(shared, dec!(120)), ob(duplicate, dec!(5)), sample_obligation()]
+            .into_iter()
+            .collect();
+
+        let conflicts = feed_a.merge_by_id(feed_b);
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].id, shared);
+        assert_eq!(conflicts[0].existing.amount(), dec!(100));
+        assert_eq!(conflicts[0].incoming.amount(), dec!(120));
+
+        // The new obligation is added; the duplicate and conflict are not
+        assert_eq!(feed_a.len(), 3);
+        assert_eq!(feed_a.gross_total(), dec!(1105));
+    }
+
+    #[test]
+    fn test_convert_preview() {
+        let usd = CurrencyCode::new("USD");
+        let brl = CurrencyCode::new("BRL");
+        let mut rates = FxRateTable::new(usd.clone());
+        rates.set_rate(brl.clone(), usd.clone(), dec!(0.2)).unwrap();
+
+        let dollars = Obligation::new(PartyId::new("A"), PartyId::new("B"), dec!(100), usd.clone());
+        let reais = Obligation::new(PartyId::new("B"), PartyId::new("A"), dec!(250), brl);
+        let ids = (dollars.id(), reais.id());
+        let set: ObligationSet = vec![dollars, reais].into_iter().collect();
+
+        let preview = set.convert_preview(&rates, &usd).unwrap();
+        assert_eq!(preview, vec![(ids.0, dec!(100), dec!(100)), (ids.1, dec!(250), dec!(50))]);
+        assert!(set.convert_preview(&rates, &CurrencyCode::new("INR")).is_err());
+    }
+
+    #[test]
+    fn test_near_duplicates() {
+        let first = ob("A", "B", dec!(100.00));
+        let second = ob("A", "B", dec!(100.01));
+        let expected = (first.id(), second.id());
+        let set: ObligationSet = vec![
+            first,
+            ob("B", "A", dec!(100.00)),
+            ob("A", "B", dec!(250)),
+            second,
+        ]
+        .into_iter()
+        .collect();
+
+        assert_eq!(set.near_duplicates(dec!(0.02)), vec![expected]);
+        assert!(set.near_duplicates(dec!(0.001)).is_empty());
+    }
+
+    #[test]
+    fn test_normalize_parties_merges_case_variants() {
+        let mut set = ObligationSet::new();
+        set.add(ob("BR-Treasury", "IN-RBI", dec!(100)));
+        set.add(ob("IN-RBI", " BR-TREASURY", dec!(60)));
+        assert_eq!(set.parties().len(), 3);
+
+        let normalized = set.normalize_parties();
+        assert_eq!(normalized.parties().len(), 2);
+        assert!(normalized.parties().contains(&PartyId::new("BR-TREASURY")));
+        assert_eq!(normalized.obligations()[0].id(), set.obligations()[0].id());
+    }
+
+    #[test]
+    fn test_from_notional() {
+        let usd = CurrencyCode::new("USD");
+        let ob = |notional, fraction| {
+            Obligation::from_notional(
+                PartyId::new("A"),
+                PartyId::new("B"),
+                notional,
+                fraction,
+                usd.clone(),
+            )
+        };
+
+        assert_eq!(ob(dec!(1000000), dec!(0.25)).unwrap().amount(), dec!(250000));
+        assert_eq!(ob(dec!(1000000), Decimal::ONE).unwrap().amount(), dec!(1000000));
+        assert_eq!(
+            ob(dec!(1000000), Decimal::ZERO).unwrap_err(),
+            ObligationError::InvalidFraction(Decimal::ZERO)
+        );
+        assert!(matches!(ob(dec!(1000000), dec!(1.5)), Err(ObligationError::InvalidFraction(_))));
+        assert!(matches!(ob(dec!(-10), dec!(0.5)), Err(ObligationError::NonPositiveAmount(_))));
+    }
+
+    #[test]
+    fn test_builder_cycle_matches_manual_construction() {
+        let usd = CurrencyCode::new("USD");
+        let brazil = PartyId::new("BR-TREASURY");
+        let india = PartyId::new("IN-RBI");
+        let china = PartyId::new("CN-PBOC");
+        let mut manual = ObligationSet::new();
+        manual.add(Obligation::new(brazil.clone(), india.clone(), dec!(100_000_000), usd.clone()));
+        manual.add(Obligation::new(india.clone(), china.clone(), dec!(100_000_000), usd.clone()));
+        manual.add(Obligation::new(china.clone(), brazil.clone(), dec!(100_000_000), usd.clone()));
+
+        let built = ObligationSetBuilder::new()
+            .cycle(&[brazil, india, china], dec!(100_000_000), usd)
+            .build();
+
+        let economics = |set: &ObligationSet| -> Vec<(PartyId, PartyId, Decimal, CurrencyCode)> {
+            set.obligations()
+                .iter()
+                .map(|o| {
+                    (o.debtor().clone(), o.creditor().clone(), o.amount(), o.currency().clone())
+                })
+                .collect()
+        };
+        assert_eq!(economics(&built), economics(&manual));
+    }
+
+    #[test]
+    fn test_aggregate_sums_same_direction() {
+        let mut set = ObligationSet::new();
+        set.add(ob("A", "B", dec!(100)).with_reference("INV-1"));
+        set.add(ob("B", "A", dec!(30)));
+        set.add(ob("A", "B", dec!(50)).with_reference("INV-2"));
+        set.add(ob("A", "B", dec!(25)).with_reference("INV-1"));
+        let t0 = set.obligations()[0].created_at();
+        let set: ObligationSet =
+            set.obligations().iter().map(|ob| ob.clone().with_created_at(t0)).collect();
+
+        let aggregated = set.aggregate();
+
+        assert_eq!(aggregated.len(), 2);
+        let first = &aggregated.obligations()[0];
+        assert_eq!(first.amount(), dec!(175));
+        assert_eq!(first.id(), set.obligations()[0].id());
+        assert_eq!(first.reference(), Some("INV-1; INV-2"));
+        assert_eq!(aggregated.obligations()[1].amount(), dec!(30));
+        assert_eq!(aggregated.gross_total(), set.gross_total());
+    }
+
+    #[test]
+    fn test_aggregate_keeps_differing_members_apart() {
+        let t0 = Utc.with_ymd_and_hms(2024, 3, 1, 9, 0, 0).unwrap();
+        let member = || ob("A", "B", dec!(10)).with_created_at(t0);
+        let mut set = ObligationSet::new();
+        set.add(member());
+        set.add(member());
+        set.add(member().with_batch_id("EOD"));
+        set.add(member().with_rate(dec!(0.05)));
+        set.add(member().with_created_at(t0 + Duration::hours(1)));
+        set.add(member().with_debtor_account("A-2"));
+        set.add(member().with_creditor_account("B-2"));
+
+        let aggregated = set.aggregate();
+
+        assert_eq!(aggregated.len(), 6);
+        assert_eq!(aggregated.obligations()[0].amount(), dec!(20));
+        assert!(aggregated.obligations()[1..].iter().all(|ob| ob.amount() == dec!(10)));
+        assert_eq!(aggregated.obligations()[1].batch_id(), Some("EOD"));
+    }
+
+    #[test]
+    fn test_try_new_rejects_invalid_inputs() {
+        let usd = CurrencyCode::new("USD");
+        let (a, b) = (PartyId::new("A"), PartyId::new("B"));
+
+        assert_eq!(
+            Obligation::try_new(a.clone(), b.clone(), Decimal::ZERO, usd.clone()).unwrap_err(),
+            ObligationError::NonPositiveAmount(Decimal::ZERO)
+        );
+        assert_eq!(
+            Obligation::try_new(a.clone(), b.clone(), dec!(-5), usd.clone()).unwrap_err(),
+            ObligationError::NonPositiveAmount(dec!(-5))
+        );
+        assert_eq!(
+            Obligation::try_new(a.clone(), a.clone(), dec!(5), usd.clone()).unwrap_err(),
+            ObligationError::SelfObligation(a.clone())
+        );
+        assert!(Obligation::try_new(a, b, dec!(5), usd).is_ok());
+    }
+
+    #[test]
+    #[should_panic(expected = "debtor and creditor are both A")]
+    fn test_obligation_self_referential_panics() {
+        Obligation::new(PartyId::new("A"), PartyId::new("A"), dec!(1), CurrencyCode::new("USD"));
+    }
 }
@@ -28,6 +28,14 @@ impl PartyId {
         Self(id.into())
     }
 
+    /// Create a party identifier in canonical form: trimmed and uppercased.
+    ///
+    /// Feeds often disagree on casing and padding ("BR-Treasury " vs
+    /// "BR-TREASURY"); normalizing keeps them from becoming distinct parties.
+    pub fn normalized(id: &str) -> Self {
+        Self(id.trim().to_uppercase())
+    }
+
     /// Returns the string representation of this party ID.
     pub fn as_str(&self) -> &str {
         &self.0
@@ -65,6 +73,11 @@ mod tests {
         assert_eq!(format!("{}", p), "CN-PBOC");
     }
 
+    #[test]
+    fn test_party_normalized() {
+        assert_eq!(PartyId::normalized("  br-Treasury\t"), PartyId::new("BR-TREASURY"));
+    }
+
     #[test]
     fn test_party_ordering() {
         let a = PartyId::new("A-BANK");
@@ -0,0 +1,172 @@
+//! Scaled-integer amounts for high-precision settlement units.
+//!
+//! [`Decimal`](rust_decimal::Decimal) holds at most 28 significant digits,
+//! which is too tight for units with many minor digits (some crypto
+//! settlement units use 18 decimals). Such currencies can be given a
+//! `scale_factor`: amounts are then stored as integer counts of the
+//! smallest unit in an `i128`, netted exactly, and formatted back to
+//! decimal notation only for display.
+
+use crate::core::amount::AmountError;
+use crate::core::currency::CurrencyCode;
+use crate::core::party::PartyId;
+use crate::error::ClearingError;
+use rust_decimal::Decimal;
+use std::collections::HashMap;
+
+/// Number of decimal places stored per currency.
+#[derive(Debug, Clone, Default)]
+pub struct CurrencyScales {
+    scale_factors: HashMap<CurrencyCode, u32>,
+}
+
+impl CurrencyScales {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Store amounts in `currency` as integer multiples of `10^-decimals`.
+    pub fn set_scale_factor(&mut self, currency: CurrencyCode, decimals: u32) {
+        self.scale_factors.insert(currency, decimals);
+    }
+
+    /// Decimal places stored for `currency`; unconfigured currencies use 2.
+    pub fn scale_factor(&self, currency: &CurrencyCode) -> u32 {
+        self.scale_factors.get(currency).copied().unwrap_or(2)
+    }
+
+    /// Strictly parse a positive amount into smallest units of `currency`.
+    ///
+    /// Accepts the same plain notation as
+    /// [`parse_amount`](crate::core::amount::parse_amount), but never goes
+    /// through `Decimal`, so the only limit is the range of `i128`. More
+    /// fractional digits than the currency's scale factor are rejected.
+    pub fn parse_units(&self, raw: &str, currency: &CurrencyCode) -> Result<i128, AmountError> {
+        if raw.is_empty() {
+            return Err(AmountError::Empty);
+        }
+        let (integer, fraction) = raw.split_once('.').unwrap_or((raw, ""));
+        let all_digits = |s: &str| s.bytes().all(|b| b.is_ascii_digit());
+        if integer.is_empty()
+            || !all_digits(integer)
+            || !all_digits(fraction)
+            || (raw.contains('.') && fraction.is_empty())
+        {
+            return Err(AmountError::Malformed(raw.to_string()));
+        }
+
+        let scale = self.scale_factor(currency) as usize;
+        if fraction.len() > scale {
+            return Err(AmountError::Overscaled(raw.to_string()));
+        }
+        let digits = format!("{}{}{}", integer, fraction, "0".repeat(scale - fraction.len()));
+        let units: i128 = digits
+            .parse()
+            .map_err(|_| AmountError::OutOfRange(raw.to_string()))?;
+        if units == 0 {
+            return Err(AmountError::NonPositive(Decimal::ZERO));
+        }
+        Ok(units)
+    }
+
+    /// Format smallest units of `currency` back to decimal notation.
+    pub fn format_units(&self, units: i128, currency: &CurrencyCode) -> String {
+        let scale = self.scale_factor(currency) as usize;
+        let sign = if units < 0 { "-" } else { "" };
+        let digits = format!("{:0>width$}", units.unsigned_abs(), width = scale + 1);
+        let (integer, fraction) = digits.split_at(digits.len() - scale);
+        if fraction.is_empty() {
+            format!("{}{}", sign, integer)
+        } else {
+            format!("{}{}.{}", sign, integer, fraction)
+        }
+    }
+}
+
+/// An obligation whose amount is held in smallest units.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScaledObligation {
+    pub debtor: PartyId,
+    pub creditor: PartyId,
+    pub currency: CurrencyCode,
+    pub units: i128,
+}
+
+/// Net positions in smallest units, keyed by (party, currency).
+///
+/// Positive = net creditor, negative = net debtor, as in
+/// [`Ledger`](crate::core::ledger::Ledger).
+///
+/// # Panics
+///
+/// Panics if a position overflows `i128`; see [`try_net_scaled`].
+pub fn net_scaled(obligations: &[ScaledObligation]) -> HashMap<(PartyId, CurrencyCode), i128> {
+    match try_net_scaled(obligations) {
+        Ok(positions) => positions,
+        Err(e) => panic!("{}", e),
+    }
+}
+
+/// Net positions in smallest units, reporting `i128` overflow as
+/// [`ClearingError::Overflow`] instead of panicking.
+pub fn try_net_scaled(
+    obligations: &[ScaledObligation],
+) -> Result<HashMap<(PartyId, CurrencyCode), i128>, ClearingError> {
+    let mut positions: HashMap<(PartyId, CurrencyCode), i128> = HashMap::new();
+    let mut apply = |party: &PartyId, currency: &CurrencyCode, delta: i128| {
+        let position = positions
+            .entry((party.clone(), currency.clone()))
+            .or_insert(0);
+        *position = position.checked_add(delta).ok_or_else(|| {
+            ClearingError::Overflow(format!("scaled position of {} in {}", party, currency))
+        })?;
+        Ok::<(), ClearingError>(())
+    };
+    for ob in obligations {
+        apply(&ob.debtor, &ob.currency, -ob.units)?;
+        apply(&ob.creditor, &ob.currency, ob.units)?;
+    }
+    Ok(positions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::amount::parse_amount;
+
+    #[test]
+    fn test_nets_18_decimal_amounts_exactly() {
+        let token = CurrencyCode::new("WETH");
+        let mut scales = CurrencyScales::new();
+        scales.set_scale_factor(token.clone(), 18);
+
+        // 29 significant digits: beyond what Decimal can hold exactly
+        let large = "98765432109.123456789012345678";
+        let small = "1.000000000000000001";
+        assert!(parse_amount(large).is_err());
+
+        let ob = |d: &str, c: &str, raw: &str| ScaledObligation {
+            debtor: PartyId::new(d),
+            creditor: PartyId::new(c),
+            currency: token.clone(),
+            units: scales.parse_units(raw, &token).unwrap(),
+        };
+        let positions = net_scaled(&[ob("A", "B", large), ob("B", "A", small)]);
+
+        let b = positions[&(PartyId::new("B"), token.clone())];
+        assert_eq!(scales.format_units(b, &token), "98765432108.123456789012345677");
+        assert_eq!(positions.values().sum::<i128>(), 0);
+    }
+
+    #[test]
+    fn test_parse_and_format_units() {
+        let usd = CurrencyCode::new("USD");
+        let scales = CurrencyScales::new();
+        assert_eq!(scales.parse_units("12.5", &usd).unwrap(), 1250);
+        assert_eq!(scales.format_units(1250, &usd), "12.50");
+        assert_eq!(scales.format_units(-5, &usd), "-0.05");
+        assert!(matches!(scales.parse_units("1.001", &usd), Err(AmountError::Overscaled(_))));
+        assert!(matches!(scales.parse_units("1e3", &usd), Err(AmountError::Malformed(_))));
+        assert!(matches!(scales.parse_units("0.00", &usd), Err(AmountError::NonPositive(_))));
+    }
+}
@@ -0,0 +1,383 @@
+//! The CLI's JSON obligation schema.
+//!
+//! ```json
+//! {
+//!   "obligations": [
+//!     { "from": "BR-TREASURY", "to": "IN-RBI", "amount": "100000000", "currency": "USD",
+//!       "reference": "INV-42", "settlement_date": "2024-03-01T00:00:00Z" }
+//!   ]
+//! }
+//! ```
+//!
+//! `currency` defaults to `USD`; `reference`, `settlement_date`,
+//! `debtor_account` and `creditor_account` are optional.
+//! Amounts are strings, parsed strictly (see [`parse_amount`]).
+//!
+//! The same records can also be read one per line (NDJSON) or as CSV with a
+//! header row naming the columns; see [`ObligationSet::from_reader`].
+
+use crate::core::amount::{parse_amount, AmountError};
+use crate::core::currency::CurrencyCode;
+use crate::core::obligation::{Obligation, ObligationError, ObligationSet};
+use crate::core::party::PartyId;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::io::Read;
+use thiserror::Error;
+
+/// Errors arising from reading obligations in the CLI schema.
+#[derive(Debug, Error)]
+pub enum SchemaError {
+    #[error("invalid obligations JSON: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("obligations[{index}]: {source}")]
+    InvalidAmount {
+        index: usize,
+        #[source]
+        source: AmountError,
+    },
+    #[error("obligations[{index}]: {source}")]
+    InvalidObligation {
+        index: usize,
+        #[source]
+        source: ObligationError,
+    },
+}
+
+/// Wire formats accepted by [`ObligationSet::from_reader`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputFormat {
+    /// A single `{"obligations": [...]}` document.
+    Json,
+    /// One obligation record object per line; blank lines are skipped.
+    Ndjson,
+    /// A header row (`from,to,amount` plus any optional columns) followed
+    /// by one obligation per row.
+    Csv,
+}
+
+/// Errors arising from reading obligations from a stream.
+#[derive(Debug, Error)]
+pub enum IoError {
+    #[error("failed to read obligations: {0}")]
+    Read(#[from] std::io::Error),
+    #[error(transparent)]
+    Schema(#[from] SchemaError),
+    #[error("line {line}: {message}")]
+    Malformed { line: usize, message: String },
+}
+
+#[derive(Serialize, Deserialize)]
+struct ObligationRecord {
+    from: String,
+    to: String,
+    amount: String,
+    #[serde(default = "default_currency")]
+    currency: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    reference: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    settlement_date: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    debtor_account: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    creditor_account: Option<String>,
+}
+
+fn default_currency() -> String {
+    "USD".to_string()
+}
+
+#[derive(Serialize, Deserialize)]
+struct ObligationsDocument {
+    obligations: Vec<ObligationRecord>,
+}
+
+impl ObligationSet {
+    /// Serialize to the CLI's `{"obligations": [...]}` JSON schema.
+    pub fn to_cli_json(&self) -> String {
+        let document = ObligationsDocument {
+            obligations: self
+                .obligations()
+                .iter()
+                .map(|ob| ObligationRecord {
+                    from: ob.debtor().to_string(),
+                    to: ob.creditor().to_string(),
+                    amount: ob.amount().to_string(),
+                    currency: ob.currency().to_string(),
+                    reference: ob.reference().map(str::to_string),
+                    settlement_date: ob.settlement_date(),
+                    debtor_account: ob.debtor_account().map(str::to_string),
+                    creditor_account: ob.creditor_account().map(str::to_string),
+                })
+                .collect(),
+        };
+        serde_json::to_string_pretty(&document).expect("obligation records always serialize")
+    }
+
+    /// Parse obligations from the CLI's JSON schema.
+    ///
+    /// Amount errors carry the index of the offending obligation.
+    pub fn from_cli_json(json: &str) -> Result<ObligationSet, SchemaError> {
+        let document: ObligationsDocument = serde_json::from_str(json)?;
+        records_to_set(document.obligations)
+    }
+
+    /// Read obligations in `format` from any byte stream.
+    ///
+    /// Amount errors carry the index of the offending record; structural
+    /// errors in NDJSON and CSV input carry the 1-based line number.
+    pub fn from_reader<R: Read>(mut reader: R, format: InputFormat) -> Result<ObligationSet, IoError> {
+        let mut content = String::new();
+        reader.read_to_string(&mut content)?;
+        let records = match format {
+            InputFormat::Json => return Ok(Self::from_cli_json(&content)?),
+            InputFormat::Ndjson => ndjson_records(&content)?,
+            InputFormat::Csv => csv_records(&content)?,
+        };
+        Ok(records_to_set(records)?)
+    }
+}
+
+fn records_to_set(records: Vec<ObligationRecord>) -> Result<ObligationSet, SchemaError> {
+    let mut set = ObligationSet::new();
+    for (index, record) in records.into_iter().enumerate() {
+        let amount = parse_amount(&record.amount)
+            .map_err(|source| SchemaError::InvalidAmount { index, source })?;
+        let mut ob = Obligation::try_new(
+            PartyId::new(record.from),
+            PartyId::new(record.to),
+            amount,
+            CurrencyCode::new(record.currency),
+        )
+        .map_err(|source| SchemaError::InvalidObligation { index, source })?;
+        if let Some(reference) = record.reference {
+            ob = ob.with_reference(reference);
+        }
+        if let Some(date) = record.settlement_date {
+            ob = ob.with_settlement_date(date);
+        }
+        if let Some(account) = record.debtor_account {
+            ob = ob.with_debtor_account(account);
+        }
+        if let Some(account) = record.creditor_account {
+            ob = ob.with_creditor_account(account);
+        }
+        set.add(ob);
+    }
+    Ok(set)
+}
+
+fn ndjson_records(content: &str) -> Result<Vec<ObligationRecord>, IoError> {
+    content
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| {
+            serde_json::from_str(line).map_err(|e| IoError::Malformed {
+                line: i + 1,
+                message: e.to_string(),
+            })
+        })
+        .collect()
+}
+
+fn csv_records(content: &str) -> Result<Vec<ObligationRecord>, IoError> {
+    let mut lines = content
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty());
+    let Some((header_index, header)) = lines.next() else {
+        return Ok(Vec::new());
+    };
+    let columns: Vec<String> = split_csv_row(header, header_index + 1)?
+        .into_iter()
+        .map(|c| c.trim().to_ascii_lowercase())
+        .collect();
+    let column = |name: &str| columns.iter().position(|c| c == name);
+    let (Some(from), Some(to), Some(amount)) = (column("from"), column("to"), column("amount"))
+    else {
+        return Err(IoError::Malformed {
+            line: header_index + 1,
+            message: "CSV header must name 'from', 'to' and 'amount' columns".to_string(),
+        });
+    };
+    let (currency, reference, settlement_date) =
+        (column("currency"), column("reference"), column("settlement_date"));
+    let (debtor_account, creditor_account) = (column("debtor_account"), column("creditor_account"));
+
+    let mut records = Vec::new();
+    for (i, line) in lines {
+        let line_no = i + 1;
+        let fields = split_csv_row(line, line_no)?;
+        if fields.len() != columns.len() {
+            return Err(IoError::Malformed {
+                line: line_no,
+                message: format!("expected {} fields, found {}", columns.len(), fields.len()),
+            });
+        }
+        let optional = |idx: Option<usize>| {
+            idx.map(|idx| fields[idx].clone())
+                .filter(|value| !value.is_empty())
+        };
+        let settlement_date = optional(settlement_date)
+            .map(|raw| {
+                DateTime::parse_from_rfc3339(&raw)
+                    .map(|date| date.with_timezone(&Utc))
+                    .map_err(|e| IoError::Malformed {
+                        line: line_no,
+                        message: format!("invalid settlement_date '{}': {}", raw, e),
+                    })
+            })
+            .transpose()?;
+        records.push(ObligationRecord {
+            from: fields[from].clone(),
+            to: fields[to].clone(),
+            amount: fields[amount].clone(),
+            currency: optional(currency).unwrap_or_else(default_currency),
+            reference: optional(reference),
+            settlement_date,
+            debtor_account: optional(debtor_account),
+            creditor_account: optional(creditor_account),
+        });
+    }
+    Ok(records)
+}
+
+/// Split one CSV row, honouring double-quoted fields with `""` escapes.
+fn split_csv_row(line: &str, line_no: usize) -> Result<Vec<String>, IoError> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(std::mem::take(&mut field)),
+            _ => field.push(c),
+        }
+    }
+    if quoted {
+        return Err(IoError::Malformed {
+            line: line_no,
+            message: "unterminated quoted field".to_string(),
+        });
+    }
+    fields.push(field);
+    Ok(fields)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_cli_json_round_trip() {
+        let date = Utc.with_ymd_and_hms(2024, 3, 1, 0, 0, 0).unwrap();
+        let mut set = ObligationSet::new();
+        set.add(
+            Obligation::new(
+                PartyId::new("BR-TREASURY"),
+                PartyId::new("IN-RBI"),
+                dec!(100_000_000.50),
+                CurrencyCode::new("USD"),
+            )
+            .with_reference("INV-42")
+            .with_settlement_date(date)
+            .with_creditor_account("IN-RBI-USD-01"),
+        );
+        set.add(Obligation::new(
+            PartyId::new("IN-RBI"),
+            PartyId::new("CN-PBOC"),
+            dec!(250),
+            CurrencyCode::new("INR"),
+        ));
+
+        let json = set.to_cli_json();
+        let parsed: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed["obligations"][0]["from"], "BR-TREASURY");
+        assert_eq!(parsed["obligations"][0]["amount"], "100000000.50");
+        assert!(parsed["obligations"][1].get("reference").is_none());
+
+        let back = ObligationSet::from_cli_json(&json).unwrap();
+        assert_eq!(back.len(), 2);
+        for (a, b) in set.obligations().iter().zip(back.obligations()) {
+            assert_eq!(a.debtor(), b.debtor());
+            assert_eq!(a.creditor(), b.creditor());
+            assert_eq!(a.amount(), b.amount());
+            assert_eq!(a.currency(), b.currency());
+            assert_eq!(a.reference(), b.reference());
+            assert_eq!(a.settlement_date(), b.settlement_date());
+            assert_eq!(a.creditor_account(), b.creditor_account());
+        }
+    }
+
+    #[test]
+    fn test_cli_json_defaults_currency() {
+        let set =
+            ObligationSet::from_cli_json(r#"{"obligations":[{"from":"A","to":"B","amount":"5"}]}"#)
+                .unwrap();
+        assert_eq!(set.obligations()[0].currency(), &CurrencyCode::new("USD"));
+    }
+
+    #[test]
+    fn test_cli_json_indexes_amount_errors() {
+        let err = ObligationSet::from_cli_json(
+            r#"{"obligations":[{"from":"A","to":"B","amount":"5"},{"from":"B","to":"C","amount":"-1"}]}"#,
+        )
+        .unwrap_err();
+        assert!(matches!(err, SchemaError::InvalidAmount { index: 1, .. }));
+    }
+
+    #[test]
+    fn test_from_reader_each_format() {
+        use std::io::Cursor;
+
+        let json = r#"{"obligations":[{"from":"A","to":"B","amount":"100","currency":"EUR"},{"from":"B","to":"C","amount":"40"}]}"#;
+        let ndjson = "{\"from\":\"A\",\"to\":\"B\",\"amount\":\"100\",\"currency\":\"EUR\"}\n\n{\"from\":\"B\",\"to\":\"C\",\"amount\":\"40\"}\n";
+        let csv = "from,to,amount,currency,reference\nA,B,100,EUR,\"INV,1\"\nB,C,40,,\n";
+
+        for (input, format) in [
+            (json, InputFormat::Json),
+            (ndjson, InputFormat::Ndjson),
+            (csv, InputFormat::Csv),
+        ] {
+            let set = ObligationSet::from_reader(Cursor::new(input), format).unwrap();
+            assert_eq!(set.len(), 2, "{:?}", format);
+            let first = &set.obligations()[0];
+            assert_eq!(first.debtor(), &PartyId::new("A"));
+            assert_eq!(first.amount(), dec!(100));
+            assert_eq!(first.currency(), &CurrencyCode::new("EUR"));
+            assert_eq!(set.obligations()[1].currency(), &CurrencyCode::new("USD"));
+        }
+
+        let set = ObligationSet::from_reader(Cursor::new(csv), InputFormat::Csv).unwrap();
+        assert_eq!(set.obligations()[0].reference(), Some("INV,1"));
+    }
+
+    #[test]
+    fn test_from_reader_reports_lines() {
+        use std::io::Cursor;
+
+        let err = ObligationSet::from_reader(
+            Cursor::new("{\"from\":\"A\",\"to\":\"B\",\"amount\":\"1\"}\nnot json\n"),
+            InputFormat::Ndjson,
+        )
+        .unwrap_err();
+        assert!(matches!(err, IoError::Malformed { line: 2, .. }));
+
+        let err =
+            ObligationSet::from_reader(Cursor::new("from,to\nA,B\n"), InputFormat::Csv).unwrap_err();
+        assert!(matches!(err, IoError::Malformed { line: 1, .. }));
+
+        let err = ObligationSet::from_reader(Cursor::new("from,to,amount\nA,B,abc\n"), InputFormat::Csv)
+            .unwrap_err();
+        assert!(matches!(err, IoError::Schema(SchemaError::InvalidAmount { index: 0, .. })));
+    }
+}
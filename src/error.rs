@@ -0,0 +1,91 @@
+//! Crate-level error type.
+//!
+//! Each module reports failures with its own error enum ([`FxError`],
+//! [`ObligationError`], [`IoError`], ...). [`ClearingError`] aggregates
+//! them, so callers composing several operations can propagate any of
+//! them with `?`. Operations that would otherwise panic offer a `try_`
+//! variant returning it.
+
+use crate::core::currency::FxError;
+use crate::core::obligation::ObligationError;
+use crate::core::schema::{IoError, SchemaError};
+use thiserror::Error;
+
+/// Any error the clearing engine can report.
+#[derive(Debug, Error)]
+pub enum ClearingError {
+    #[error(transparent)]
+    Fx(#[from] FxError),
+    #[error(transparent)]
+    Obligation(#[from] ObligationError),
+    #[error(transparent)]
+    Io(#[from] IoError),
+    #[error("arithmetic overflow: {0}")]
+    Overflow(String),
+    #[error("invalid configuration: {0}")]
+    InvalidConfig(String),
+}
+
+impl From<SchemaError> for ClearingError {
+    fn from(e: SchemaError) -> Self {
+        ClearingError::Io(IoError::Schema(e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::currency::{CurrencyCode, FxRateTable};
+    use crate::core::obligation::Obligation;
+    use crate::core::party::PartyId;
+    use crate::core::scaled::{try_net_scaled, ScaledObligation};
+    use crate::simulation::stress_test::{try_generate_seeded_network, NetworkConfig};
+    use rust_decimal::Decimal;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_try_variants_return_typed_errors() {
+        let usd = CurrencyCode::new("USD");
+
+        let err: ClearingError =
+            Obligation::try_new(PartyId::new("A"), PartyId::new("B"), Decimal::ZERO, usd.clone())
+                .unwrap_err()
+                .into();
+        assert!(matches!(
+            err,
+            ClearingError::Obligation(ObligationError::NonPositiveAmount(_))
+        ));
+
+        let huge = |d: &str, c: &str| ScaledObligation {
+            debtor: PartyId::new(d),
+            creditor: PartyId::new(c),
+            currency: usd.clone(),
+            units: i128::MAX,
+        };
+        let err = try_net_scaled(&[huge("A", "B"), huge("C", "B")]).unwrap_err();
+        assert!(matches!(err, ClearingError::Overflow(_)));
+
+        let config = NetworkConfig {
+            party_count: 1,
+            ..Default::default()
+        };
+        let err = try_generate_seeded_network(&config, 7).unwrap_err();
+        assert!(matches!(err, ClearingError::InvalidConfig(_)));
+    }
+
+    #[test]
+    fn test_question_mark_aggregates_module_errors() {
+        fn total_in_usd(amount: Decimal) -> Result<Decimal, ClearingError> {
+            let usd = CurrencyCode::new("USD");
+            let brl = CurrencyCode::new("BRL");
+            let ob = Obligation::try_new(PartyId::new("A"), PartyId::new("B"), amount, brl)?;
+            Ok(FxRateTable::new(usd.clone()).convert(ob.amount(), ob.currency(), &usd)?)
+        }
+
+        assert!(matches!(total_in_usd(dec!(-1)), Err(ClearingError::Obligation(_))));
+        assert!(matches!(
+            total_in_usd(dec!(10)),
+            Err(ClearingError::Fx(FxError::RateNotFound { .. }))
+        ));
+    }
+}
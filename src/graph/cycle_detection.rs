@@ -1,12 +1,14 @@
 use crate::core::currency::CurrencyCode;
 use crate::core::party::PartyId;
 use crate::graph::payment_graph::PaymentGraph;
+use rust_decimal::prelude::FromPrimitive;
 use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
 
 /// A cycle in the payment graph — a circular flow of obligations
 /// that can potentially be compressed to reduce gross settlement.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PaymentCycle {
     /// Ordered list of parties forming the cycle.
     /// The last party has an obligation back to the first.
@@ -18,17 +20,60 @@ pub struct PaymentCycle {
     pub bottleneck: Decimal,
 }
 
+/// Strategy for how much of a cycle's bottleneck to compress.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CompressionPolicy {
+    /// Compress the full bottleneck, eliminating the cycle.
+    Full,
+    /// Compress this fraction of the bottleneck, clamped to `[0, 1]`.
+    Fraction(f64),
+    /// Compress the bottleneck, but never more than this amount.
+    CapAt(Decimal),
+}
+
 impl PaymentCycle {
     /// The number of parties (and edges) in this cycle.
     pub fn len(&self) -> usize {
         self.parties.len()
     }
 
+    pub fn is_empty(&self) -> bool {
+        self.parties.is_empty()
+    }
+
+    /// Stable identifier for this cycle, e.g. `"USD:A>B>C"`.
+    ///
+    /// Derived from the currency and the canonical (smallest) rotation of
+    /// the party ids, so the same cycle gets the same id regardless of
+    /// which node it was discovered from or which run produced it.
+    pub fn canonical_id(&self) -> String {
+        let canonical = canonical_form(&self.parties);
+        let parties: Vec<&str> = canonical.iter().map(|p| p.as_str()).collect();
+        format!("{}:{}", self.currency, parties.join(">"))
+    }
+
     /// Total gross value that would be saved by compressing this cycle.
     /// Equal to bottleneck * number_of_edges.
     pub fn potential_savings(&self) -> Decimal {
         self.bottleneck * Decimal::from(self.parties.len())
     }
+
+    /// Amount to subtract from every edge of this cycle under `policy`.
+    ///
+    /// Partial compression keeps part of the bilateral positions alive,
+    /// trading some savings for exposures counterparties want to keep.
+    /// The result is always between zero and the bottleneck.
+    pub fn recommended_compression(&self, policy: CompressionPolicy) -> Decimal {
+        let amount = match policy {
+            CompressionPolicy::Full => self.bottleneck,
+            CompressionPolicy::Fraction(fraction) => {
+                let fraction = Decimal::from_f64(fraction.clamp(0.0, 1.0)).unwrap_or(Decimal::ZERO);
+                self.bottleneck * fraction
+            }
+            CompressionPolicy::CapAt(cap) => self.bottleneck.min(cap),
+        };
+        amount.max(Decimal::ZERO)
+    }
 }
 
 /// Detect all simple cycles in the payment graph for a given currency.
@@ -42,6 +87,21 @@ impl PaymentCycle {
 /// that node. The bottleneck (minimum edge weight) determines
 /// how much liquidity can be saved by compressing each cycle.
 pub fn find_cycles(graph: &PaymentGraph, currency: &CurrencyCode) -> Vec<PaymentCycle> {
+    find_cycles_bounded(graph, currency, usize::MAX)
+}
+
+/// Detect simple cycles of at most `max_len` parties.
+///
+/// Like [`find_cycles`], but the search never extends a path beyond
+/// `max_len` parties. Enumerating every cycle in a dense strongly
+/// connected cluster is exponential, while long cycles are rarely worth
+/// compressing; bounding the length keeps the search tractable and still
+/// captures the short, valuable cycles.
+pub fn find_cycles_bounded(
+    graph: &PaymentGraph,
+    currency: &CurrencyCode,
+    max_len: usize,
+) -> Vec<PaymentCycle> {
     let adj = graph.adjacency_list(currency);
     let parties: Vec<PartyId> = {
         let mut p: Vec<_> = graph.parties().iter().cloned().collect();
@@ -53,73 +113,163 @@ pub fn find_cycles(graph: &PaymentGraph, currency: &CurrencyCode) -> Vec<Payment
 
     // For each starting node, find cycles using DFS
     for start in &parties {
-        let mut visited: HashSet<PartyId> = HashSet::new();
-        let mut path: Vec<PartyId> = Vec::new();
-        let mut path_set: HashSet<PartyId> = HashSet::new();
-
-        dfs_find_cycles(
-            start,
-            start,
-            &adj,
-            &mut visited,
-            &mut path,
-            &mut path_set,
-            currency,
-            &mut all_cycles,
-            graph,
-        );
+        dfs_find_cycles(start, &adj, currency, &mut all_cycles, graph, max_len);
     }
 
     // Deduplicate cycles (same set of nodes in same order = same cycle)
     deduplicate_cycles(&mut all_cycles);
 
     // Sort by potential savings descending
-    all_cycles.sort_by(|a, b| b.potential_savings().cmp(&a.potential_savings()));
+    all_cycles.sort_by_key(|c| std::cmp::Reverse(c.potential_savings()));
     all_cycles
 }
 
+/// Compress all payment cycles out of the graph, currency by currency.
+///
+/// Repeatedly detects cycles and subtracts each cycle's bottleneck from
+/// every edge along it until no cycles remain. Every compression removes
+/// the same amount from a party's inflows and outflows, so the returned
+/// residual graph is acyclic in each currency while preserving every
+/// party's net position.
+///
+/// Within each pass, cycles that avoid protected-class edges are
+/// compressed before cycles that touch them (largest savings first within
+/// each group), so a protected edge is only reduced when no alternative
+/// cycle can absorb the flow.
+///
+/// Each remaining edge becomes a single aggregated obligation in the
+/// residual graph; protected edges stay protected.
+pub fn compress_cycles(graph: &PaymentGraph) -> PaymentGraph {
+    compress_cycles_traced(graph).0
+}
+
+/// [`compress_cycles`], also returning every compression applied.
+///
+/// Each returned cycle's `bottleneck` is the amount actually subtracted
+/// from its edges, which may be less than the bottleneck at detection time
+/// when an earlier compression drained a shared edge. A cycle compressed
+/// in several passes appears once per pass.
+pub(crate) fn compress_cycles_traced(graph: &PaymentGraph) -> (PaymentGraph, Vec<PaymentCycle>) {
+    let mut currencies: Vec<CurrencyCode> = graph.currencies().iter().cloned().collect();
+    currencies.sort();
+    compress_currencies(graph, &currencies)
+}
+
+/// [`compress_cycles`] restricted to one currency.
+///
+/// Edges in other currencies are carried into the residual graph
+/// unchanged (aggregated, like every residual edge).
+pub fn compress_currency_cycles(graph: &PaymentGraph, currency: &CurrencyCode) -> PaymentGraph {
+    compress_currencies(graph, std::slice::from_ref(currency)).0
+}
+
+/// Compress cycles in each of `currencies`, in order.
+fn compress_currencies(
+    graph: &PaymentGraph,
+    currencies: &[CurrencyCode],
+) -> (PaymentGraph, Vec<PaymentCycle>) {
+    let mut applied = Vec::new();
+    let mut edges: HashMap<(PartyId, PartyId, CurrencyCode), Decimal> = graph
+        .edges()
+        .into_iter()
+        .map(|(d, c, cur, amt)| ((d.clone(), c.clone(), cur.clone()), amt))
+        .collect();
+
+    let protected = graph.protected_edges();
+    let mut residual = PaymentGraph::from_edge_map(&edges, protected);
+    for currency in currencies {
+        loop {
+            let mut cycles = find_cycles(&residual, currency);
+            if cycles.is_empty() {
+                break;
+            }
+            // Stable sort keeps the savings order within each group
+            cycles.sort_by_key(|cycle| {
+                let n = cycle.parties.len();
+                (0..n).any(|i| {
+                    residual.is_protected(&cycle.parties[i], &cycle.parties[(i + 1) % n], currency)
+                })
+            });
+            for cycle in &cycles {
+                let n = cycle.parties.len();
+                let hops: Vec<(PartyId, PartyId, CurrencyCode)> = (0..n)
+                    .map(|i| {
+                        (
+                            cycle.parties[i].clone(),
+                            cycle.parties[(i + 1) % n].clone(),
+                            currency.clone(),
+                        )
+                    })
+                    .collect();
+                // Earlier compressions in this pass may have drained shared edges
+                let bottleneck = hops
+                    .iter()
+                    .map(|hop| edges.get(hop).copied().unwrap_or(Decimal::ZERO))
+                    .min()
+                    .unwrap_or(Decimal::ZERO);
+                if bottleneck <= Decimal::ZERO {
+                    continue;
+                }
+                for hop in &hops {
+                    if let Some(amount) = edges.get_mut(hop) {
+                        *amount -= bottleneck;
+                    }
+                }
+                applied.push(PaymentCycle {
+                    bottleneck,
+                    ..cycle.clone()
+                });
+            }
+            edges.retain(|_, amount| *amount > Decimal::ZERO);
+            residual = PaymentGraph::from_edge_map(&edges, protected);
+        }
+    }
+    (residual, applied)
+}
+
+/// Depth-first search for every cycle through `start`.
+///
+/// Uses an explicit stack of (node, next neighbor index) frames rather than
+/// recursion, so path length is bounded by the heap instead of the call
+/// stack; frames are visited in the same order a recursive search would.
 fn dfs_find_cycles(
-    current: &PartyId,
     start: &PartyId,
     adj: &HashMap<PartyId, Vec<(PartyId, Decimal)>>,
-    visited: &mut HashSet<PartyId>,
-    path: &mut Vec<PartyId>,
-    path_set: &mut HashSet<PartyId>,
     currency: &CurrencyCode,
     cycles: &mut Vec<PaymentCycle>,
     graph: &PaymentGraph,
+    max_len: usize,
 ) {
-    path.push(current.clone());
-    path_set.insert(current.clone());
-
-    if let Some(neighbors) = adj.get(current) {
-        for (next, _amount) in neighbors {
-            if next == start && path.len() >= 2 {
-                // Found a cycle back to start
-                let cycle_parties = path.clone();
-                let bottleneck = compute_bottleneck(&cycle_parties, currency, graph);
-                if bottleneck > Decimal::ZERO {
-                    cycles.push(PaymentCycle {
-                        parties: cycle_parties,
-                        currency: currency.clone(),
-                        bottleneck,
-                    });
-                }
-            } else if !path_set.contains(next) && !visited.contains(next) && next > start {
-                // Only explore nodes "greater than" start to avoid duplicate cycles
-                dfs_find_cycles(
-                    next, start, adj, visited, path, path_set, currency, cycles, graph,
-                );
+    let mut stack: Vec<(&PartyId, usize)> = vec![(start, 0)];
+    let mut path_set: HashSet<&PartyId> = HashSet::from([start]);
+
+    while let Some((current, next_index)) = stack.last_mut() {
+        let Some((next, _amount)) = adj.get(*current).and_then(|n| n.get(*next_index)) else {
+            // All neighbors explored: backtrack
+            path_set.remove(*current);
+            stack.pop();
+            continue;
+        };
+        *next_index += 1;
+
+        if next == start && stack.len() >= 2 {
+            // Found a cycle back to start
+            let cycle_parties: Vec<PartyId> =
+                stack.iter().map(|(party, _)| (*party).clone()).collect();
+            let bottleneck = compute_bottleneck(&cycle_parties, currency, graph);
+            if bottleneck > Decimal::ZERO {
+                cycles.push(PaymentCycle {
+                    parties: cycle_parties,
+                    currency: currency.clone(),
+                    bottleneck,
+                });
             }
+        } else if stack.len() < max_len && !path_set.contains(next) && next > start {
+            // Only explore nodes "greater than" start to avoid duplicate cycles
+            path_set.insert(next);
+            stack.push((next, 0));
         }
     }
-
-    path.pop();
-    path_set.remove(current);
-    // Mark as visited only after exploring all paths from start through current
-    if current == start {
-        visited.insert(current.clone());
-    }
 }
 
 /// Compute the bottleneck (minimum edge weight) along a cycle.
@@ -172,9 +322,14 @@ fn canonical_form(parties: &[PartyId]) -> Vec<PartyId> {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::core::obligation::Obligation;
+    use crate::core::obligation::{Obligation, ObligationClass};
     use rust_decimal_macros::dec;
 
+    fn ob(debtor: &str, creditor: &str, amount: Decimal) -> Obligation {
+        let usd = CurrencyCode::new("USD");
+        Obligation::new(PartyId::new(debtor), PartyId::new(creditor), amount, usd)
+    }
+
     #[test]
     fn test_simple_cycle() {
         let mut graph = PaymentGraph::new();
@@ -251,4 +406,176 @@ mod tests {
         // Bottleneck is the smaller edge
         assert_eq!(cycles[0].bottleneck, dec!(60));
     }
+
+    #[test]
+    fn test_canonical_id_ignores_start_node() {
+        let usd = CurrencyCode::new("USD");
+        let from_a = PaymentCycle {
+            parties: vec![PartyId::new("A"), PartyId::new("B"), PartyId::new("C")],
+            currency: usd.clone(),
+            bottleneck: dec!(10),
+        };
+        let from_b = PaymentCycle {
+            parties: vec![PartyId::new("B"), PartyId::new("C"), PartyId::new("A")],
+            currency: usd.clone(),
+            bottleneck: dec!(25),
+        };
+        assert_eq!(from_a.canonical_id(), "USD:A>B>C");
+        assert_eq!(from_a.canonical_id(), from_b.canonical_id());
+
+        // Reverse direction is a different cycle
+        let reversed = PaymentCycle {
+            parties: vec![PartyId::new("A"), PartyId::new("C"), PartyId::new("B")],
+            currency: usd,
+            bottleneck: dec!(10),
+        };
+        assert_ne!(from_a.canonical_id(), reversed.canonical_id());
+    }
+
+    #[test]
+    fn test_compress_cycles_leaves_residual() {
+        let usd = CurrencyCode::new("USD");
+        let graph = PaymentGraph::from_obligations(vec![
+            Obligation::new(PartyId::new("A"), PartyId::new("B"), dec!(100), usd.clone()),
+            Obligation::new(PartyId::new("B"), PartyId::new("C"), dec!(80), usd.clone()),
+            Obligation::new(PartyId::new("C"), PartyId::new("A"), dec!(120), usd.clone()),
+        ]);
+
+        let residual = compress_cycles(&graph);
+        assert!(find_cycles(&residual, &usd).is_empty());
+        assert_eq!(residual.gross_total(), dec!(60));
+        assert_eq!(
+            residual.edge_amount(&PartyId::new("A"), &PartyId::new("B"), &usd),
+            dec!(20)
+        );
+        assert_eq!(
+            residual.edge_amount(&PartyId::new("C"), &PartyId::new("A"), &usd),
+            dec!(40)
+        );
+    }
+
+    #[test]
+    fn test_compression_prefers_unprotected_cycle() {
+        let usd = CurrencyCode::new("USD");
+        // A→B→C→E→A (savings 200) and B→C→D→B (savings 150) share B→C.
+        let build = |protect: bool| {
+            let a_to_b = ob("A", "B", dec!(60));
+            PaymentGraph::from_obligations(vec![
+                if protect {
+                    a_to_b.with_class(ObligationClass::Protected)
+                } else {
+                    a_to_b
+                },
+                ob("B", "C", dec!(50)),
+                ob("C", "E", dec!(60)),
+                ob("E", "A", dec!(60)),
+                ob("C", "D", dec!(50)),
+                ob("D", "B", dec!(50)),
+            ])
+        };
+        let (a, b) = (PartyId::new("A"), PartyId::new("B"));
+
+        // Unprotected: the larger cycle wins and reduces A→B
+        let plain = compress_cycles(&build(false));
+        assert_eq!(plain.edge_amount(&a, &b, &usd), dec!(10));
+
+        // Protected: the alternative cycle absorbs B→C instead
+        let residual = compress_cycles(&build(true));
+        assert_eq!(residual.edge_amount(&a, &b, &usd), dec!(60));
+        assert!(residual.is_protected(&a, &b, &usd));
+        assert_eq!(residual.edge_amount(&PartyId::new("D"), &b, &usd), Decimal::ZERO);
+        assert!(find_cycles(&residual, &usd).is_empty());
+    }
+
+    #[test]
+    fn test_recommended_compression_policies() {
+        let cycle = PaymentCycle {
+            parties: vec![PartyId::new("A"), PartyId::new("B"), PartyId::new("C")],
+            currency: CurrencyCode::new("USD"),
+            bottleneck: dec!(80),
+        };
+
+        assert_eq!(cycle.recommended_compression(CompressionPolicy::Full), dec!(80));
+
+        assert_eq!(cycle.recommended_compression(CompressionPolicy::Fraction(0.25)), dec!(20));
+        assert_eq!(cycle.recommended_compression(CompressionPolicy::Fraction(1.5)), dec!(80));
+        assert_eq!(cycle.recommended_compression(CompressionPolicy::Fraction(-1.0)), Decimal::ZERO);
+
+        assert_eq!(cycle.recommended_compression(CompressionPolicy::CapAt(dec!(50))), dec!(50));
+        assert_eq!(cycle.recommended_compression(CompressionPolicy::CapAt(dec!(500))), dec!(80));
+        assert_eq!(cycle.recommended_compression(CompressionPolicy::CapAt(dec!(-5))), Decimal::ZERO);
+    }
+
+    #[test]
+    fn test_bounded_search_skips_long_cycles() {
+        let usd = CurrencyCode::new("USD");
+        let graph = PaymentGraph::from_obligations(vec![
+            // Length-5 cycle
+            ob("A", "B", dec!(10)),
+            ob("B", "C", dec!(10)),
+            ob("C", "D", dec!(10)),
+            ob("D", "E", dec!(10)),
+            ob("E", "A", dec!(10)),
+            // Length-3 cycle
+            ob("X", "Y", dec!(10)),
+            ob("Y", "Z", dec!(10)),
+            ob("Z", "X", dec!(10)),
+        ]);
+
+        assert_eq!(find_cycles(&graph, &usd).len(), 2);
+        let short = find_cycles_bounded(&graph, &usd, 3);
+        assert_eq!(short.len(), 1);
+        assert_eq!(short[0].canonical_id(), "USD:X>Y>Z");
+        assert_eq!(find_cycles_bounded(&graph, &usd, 5).len(), 2);
+    }
+
+    fn complete_graph(n: usize, currency: &CurrencyCode) -> PaymentGraph {
+        let party = |i: usize| PartyId::new(format!("P{:02}", i));
+        let mut obligations = Vec::new();
+        for i in 0..n {
+            for j in (0..n).filter(|&j| j != i) {
+                let amount = Decimal::from(10 + i * n + j);
+                obligations.push(Obligation::new(party(i), party(j), amount, currency.clone()));
+            }
+        }
+        PaymentGraph::from_obligations(obligations)
+    }
+
+    #[test]
+    fn test_bounded_search_on_complete_graph() {
+        let usd = CurrencyCode::new("USD");
+
+        // K10: C(10,2) two-party cycles and 2 × C(10,3) three-party cycles
+        let short = find_cycles_bounded(&complete_graph(10, &usd), &usd, 3);
+        assert_eq!(short.len(), 45 + 240);
+        assert!(short.iter().all(|c| c.len() <= 3));
+
+        // Bounded by the party count, the search is exhaustive. K6 keeps the
+        // full enumeration small: 15 + 40 + 90 + 144 + 120 cycles.
+        let graph = complete_graph(6, &usd);
+        let ids = |cycles: Vec<PaymentCycle>| -> Vec<String> {
+            cycles.iter().map(|c| c.canonical_id()).collect()
+        };
+        let all = ids(find_cycles(&graph, &usd));
+        assert_eq!(all.len(), 409);
+        assert_eq!(ids(find_cycles_bounded(&graph, &usd, 6)), all);
+    }
+
+    #[test]
+    fn test_long_cycle_does_not_overflow_stack() {
+        let usd = CurrencyCode::new("USD");
+        let n = 500;
+        let party = |i: usize| PartyId::new(format!("P{:03}", i % n));
+        let graph = PaymentGraph::from_obligations(
+            (0..n)
+                .map(|i| Obligation::new(party(i), party(i + 1), dec!(10), usd.clone()))
+                .collect(),
+        );
+
+        let cycles = find_cycles(&graph, &usd);
+        assert_eq!(cycles.len(), 1);
+        assert_eq!(cycles[0].len(), n);
+        assert_eq!(cycles[0].parties[0], party(0));
+        assert_eq!(cycles[0].bottleneck, dec!(10));
+    }
 }
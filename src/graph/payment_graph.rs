@@ -1,9 +1,55 @@
-use crate::core::currency::CurrencyCode;
-use crate::core::obligation::{Obligation, ObligationSet};
+use crate::core::currency::{CurrencyCode, FxError, FxRateTable};
+use crate::core::obligation::{Obligation, ObligationClass, ObligationSet};
 use crate::core::party::PartyId;
+use crate::graph::cycle_detection::{find_cycles, find_cycles_bounded};
+use crate::graph::scc::{find_sccs, StronglyConnectedComponent};
 use crate::optimization::netting::{NettingEngine, NettingResult};
 use rust_decimal::Decimal;
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use thiserror::Error;
+
+/// An obligation was rejected because it would push its debtor's total
+/// outgoing exposure in a currency past the configured limit.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("{party} would owe {exposure} {currency}, exceeding its limit of {limit}")]
+pub struct LimitExceeded {
+    pub party: PartyId,
+    pub currency: CurrencyCode,
+    pub limit: Decimal,
+    /// Total outgoing exposure had the obligation been accepted.
+    pub exposure: Decimal,
+}
+
+/// An aggregated edge: (debtor, creditor, currency).
+type EdgeKey = (PartyId, PartyId, CurrencyCode);
+
+/// What changed between two snapshots of a payment graph.
+///
+/// Parties are sorted by id and edges by (debtor, creditor, currency).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GraphDiff {
+    pub added_parties: Vec<PartyId>,
+    pub removed_parties: Vec<PartyId>,
+    /// Edges only in the newer graph, with their amount.
+    pub added_edges: Vec<(PartyId, PartyId, CurrencyCode, Decimal)>,
+    /// Edges only in the older graph, with their amount.
+    pub removed_edges: Vec<(PartyId, PartyId, CurrencyCode, Decimal)>,
+    /// Edges in both whose amount changed, as (.., old amount, new amount).
+    pub changed_edges: Vec<(PartyId, PartyId, CurrencyCode, Decimal, Decimal)>,
+    /// Newer gross minus older gross, for every currency in either graph.
+    pub gross_change: BTreeMap<CurrencyCode, Decimal>,
+}
+
+impl GraphDiff {
+    /// Returns true if no party or edge changed.
+    pub fn is_empty(&self) -> bool {
+        self.added_parties.is_empty()
+            && self.removed_parties.is_empty()
+            && self.added_edges.is_empty()
+            && self.removed_edges.is_empty()
+            && self.changed_edges.is_empty()
+    }
+}
 
 /// A directed graph of payment obligations between parties.
 ///
@@ -40,6 +86,8 @@ pub struct PaymentGraph {
     parties: HashSet<PartyId>,
     /// All known currencies
     currencies: HashSet<CurrencyCode>,
+    /// Edges carrying at least one protected-class obligation
+    protected: HashSet<(PartyId, PartyId, CurrencyCode)>,
 }
 
 impl PaymentGraph {
@@ -49,6 +97,7 @@ impl PaymentGraph {
             edges: HashMap::new(),
             parties: HashSet::new(),
             currencies: HashSet::new(),
+            protected: HashSet::new(),
         }
     }
 
@@ -59,6 +108,9 @@ impl PaymentGraph {
             obligation.creditor().clone(),
             obligation.currency().clone(),
         );
+        if obligation.class() == ObligationClass::Protected {
+            self.protected.insert(key.clone());
+        }
         *self.edges.entry(key).or_insert(Decimal::ZERO) += obligation.amount();
 
         self.parties.insert(obligation.debtor().clone());
@@ -67,6 +119,38 @@ impl PaymentGraph {
         self.obligations.add(obligation);
     }
 
+    /// Add an obligation only if it keeps the debtor within its exposure limit.
+    ///
+    /// `limits` maps (party, currency) to the maximum total the party may
+    /// owe in that currency across all its outgoing edges. Parties without
+    /// an entry are unlimited. A rejected obligation leaves the graph
+    /// unchanged.
+    pub fn add_obligation_checked(
+        &mut self,
+        obligation: Obligation,
+        limits: &HashMap<(PartyId, CurrencyCode), Decimal>,
+    ) -> Result<(), LimitExceeded> {
+        let key = (obligation.debtor().clone(), obligation.currency().clone());
+        if let Some(&limit) = limits.get(&key) {
+            let current: Decimal = self
+                .outgoing(obligation.debtor(), obligation.currency())
+                .iter()
+                .map(|(_, amount)| *amount)
+                .sum();
+            let exposure = current + obligation.amount();
+            if exposure > limit {
+                return Err(LimitExceeded {
+                    party: key.0,
+                    currency: key.1,
+                    limit,
+                    exposure,
+                });
+            }
+        }
+        self.add_obligation(obligation);
+        Ok(())
+    }
+
     /// Load obligations from a set.
     pub fn from_obligations(obligations: Vec<Obligation>) -> Self {
         let mut graph = Self::new();
@@ -76,6 +160,36 @@ impl PaymentGraph {
         graph
     }
 
+    /// Build a graph with one obligation per aggregated edge.
+    ///
+    /// Edges are inserted in sorted order; non-positive amounts are skipped.
+    /// Edges listed in `protected` become protected-class obligations.
+    pub(crate) fn from_edge_map(
+        edges: &HashMap<(PartyId, PartyId, CurrencyCode), Decimal>,
+        protected: &HashSet<(PartyId, PartyId, CurrencyCode)>,
+    ) -> Self {
+        let mut sorted: Vec<_> = edges
+            .iter()
+            .filter(|(_, amount)| **amount > Decimal::ZERO)
+            .collect();
+        sorted.sort_by(|a, b| a.0.cmp(b.0));
+        Self::from_obligations(
+            sorted
+                .into_iter()
+                .map(|(key, &amount)| {
+                    let (debtor, creditor, currency) = key;
+                    let ob =
+                        Obligation::new(debtor.clone(), creditor.clone(), amount, currency.clone());
+                    if protected.contains(key) {
+                        ob.with_class(ObligationClass::Protected)
+                    } else {
+                        ob
+                    }
+                })
+                .collect(),
+        )
+    }
+
     /// Number of unique parties in the graph.
     pub fn party_count(&self) -> usize {
         self.parties.len()
@@ -119,6 +233,22 @@ impl PaymentGraph {
             .unwrap_or(Decimal::ZERO)
     }
 
+    /// Returns true if the aggregated edge carries a protected-class obligation.
+    pub fn is_protected(
+        &self,
+        debtor: &PartyId,
+        creditor: &PartyId,
+        currency: &CurrencyCode,
+    ) -> bool {
+        self.protected
+            .contains(&(debtor.clone(), creditor.clone(), currency.clone()))
+    }
+
+    /// All protected edges as (debtor, creditor, currency).
+    pub(crate) fn protected_edges(&self) -> &HashSet<(PartyId, PartyId, CurrencyCode)> {
+        &self.protected
+    }
+
     /// Get all edges as (debtor, creditor, currency, amount).
     pub fn edges(&self) -> Vec<(&PartyId, &PartyId, &CurrencyCode, Decimal)> {
         self.edges
@@ -127,6 +257,27 @@ impl PaymentGraph {
             .collect()
     }
 
+    /// Total gross exposure between `a` and `b`, in both directions and
+    /// across all currencies, converted into `base`.
+    ///
+    /// Fails with [`FxError::RateNotFound`] if any currency traded between
+    /// the pair has no rate into `base`.
+    pub fn pair_exposure(
+        &self,
+        a: &PartyId,
+        b: &PartyId,
+        rates: &FxRateTable,
+        base: &CurrencyCode,
+    ) -> Result<Decimal, FxError> {
+        let mut total = Decimal::ZERO;
+        for ((debtor, creditor, currency), &amount) in &self.edges {
+            if (debtor == a && creditor == b) || (debtor == b && creditor == a) {
+                total += rates.convert(amount, currency, base)?;
+            }
+        }
+        Ok(total)
+    }
+
     /// Get outgoing edges from a party in a given currency.
     pub fn outgoing(
         &self,
@@ -153,6 +304,102 @@ impl PaymentGraph {
             .collect()
     }
 
+    /// Systemic importance of each party active in `currency`.
+    ///
+    /// Degree centrality (distinct counterparties over the other active
+    /// parties) weighted by the party's share of the currency's gross flow
+    /// (inflows plus outflows over twice the gross, so shares sum to one).
+    /// A hub that is both well connected and carries large volume scores
+    /// highest; scores lie in `[0, 1]`.
+    pub fn systemic_score(&self, currency: &CurrencyCode) -> HashMap<PartyId, Decimal> {
+        let mut volume: HashMap<&PartyId, Decimal> = HashMap::new();
+        let mut counterparties: HashMap<&PartyId, HashSet<&PartyId>> = HashMap::new();
+        let mut gross = Decimal::ZERO;
+        for ((debtor, creditor, cur), &amount) in &self.edges {
+            if cur != currency {
+                continue;
+            }
+            gross += amount;
+            *volume.entry(debtor).or_insert(Decimal::ZERO) += amount;
+            *volume.entry(creditor).or_insert(Decimal::ZERO) += amount;
+            counterparties.entry(debtor).or_default().insert(creditor);
+            counterparties.entry(creditor).or_default().insert(debtor);
+        }
+
+        let others = Decimal::from(volume.len().saturating_sub(1).max(1));
+        volume
+            .into_iter()
+            .map(|(party, flow)| {
+                let centrality = Decimal::from(counterparties[party].len()) / others;
+                let share = flow / (gross * Decimal::TWO);
+                (party.clone(), centrality * share)
+            })
+            .collect()
+    }
+
+    /// All parties in index order for [`PaymentGraph::edge_list`] (sorted by id).
+    pub fn node_labels(&self) -> Vec<PartyId> {
+        let mut labels: Vec<PartyId> = self.parties.iter().cloned().collect();
+        labels.sort();
+        labels
+    }
+
+    /// Edges in `currency` as `(debtor_index, creditor_index, weight)`.
+    ///
+    /// Indices refer to positions in [`PaymentGraph::node_labels`], the
+    /// interchange format expected by petgraph, networkx and similar
+    /// tools. Edges are sorted by index.
+    ///
+    /// Weights are `f64` and therefore approximate: amounts beyond ~15
+    /// significant digits lose precision, so use them for analysis and
+    /// visualization only, never to settle.
+    pub fn edge_list(&self, currency: &CurrencyCode) -> Vec<(usize, usize, f64)> {
+        let index: HashMap<PartyId, usize> = self
+            .node_labels()
+            .into_iter()
+            .enumerate()
+            .map(|(i, party)| (party, i))
+            .collect();
+        let mut list: Vec<(usize, usize, f64)> = self
+            .edges
+            .iter()
+            .filter(|((_, _, cur), _)| cur == currency)
+            .map(|((debtor, creditor, _), amount)| {
+                let weight = amount.to_string().parse::<f64>().unwrap_or(0.0);
+                (index[debtor], index[creditor], weight)
+            })
+            .collect();
+        list.sort_by_key(|&(from, to, _)| (from, to));
+        list
+    }
+
+    /// Parties that pay but never receive in `currency` (pure debtors), sorted.
+    ///
+    /// Only parties with at least one edge in `currency` are considered.
+    pub fn sources(&self, currency: &CurrencyCode) -> Vec<PartyId> {
+        self.one_sided_parties(currency, true)
+    }
+
+    /// Parties that receive but never pay in `currency` (pure creditors), sorted.
+    pub fn sinks(&self, currency: &CurrencyCode) -> Vec<PartyId> {
+        self.one_sided_parties(currency, false)
+    }
+
+    fn one_sided_parties(&self, currency: &CurrencyCode, paying: bool) -> Vec<PartyId> {
+        let mut payers: HashSet<&PartyId> = HashSet::new();
+        let mut payees: HashSet<&PartyId> = HashSet::new();
+        for (debtor, creditor, cur) in self.edges.keys() {
+            if cur == currency {
+                payers.insert(debtor);
+                payees.insert(creditor);
+            }
+        }
+        let (side, other) = if paying { (payers, payees) } else { (payees, payers) };
+        let mut parties: Vec<PartyId> = side.difference(&other).map(|&p| p.clone()).collect();
+        parties.sort();
+        parties
+    }
+
     /// Compute net positions using the netting engine.
     pub fn compute_net_positions(&self) -> NettingResult {
         NettingEngine::multilateral_net(&self.obligations)
@@ -163,6 +410,272 @@ impl PaymentGraph {
         &self.obligations
     }
 
+    /// Net every edge against its reverse, per currency.
+    ///
+    /// Mutual edges A→B and B→A collapse into a single edge in the
+    /// direction of the larger amount, carrying the difference; exactly
+    /// offsetting pairs disappear. Net positions are unchanged, making
+    /// this a cheap preprocessing step before multilateral analysis.
+    pub fn bilateral_compress(&self) -> PaymentGraph {
+        let (mutual, mut edges) = self.net_mutual_pairs(|_| true);
+        for (key, &amount) in &self.edges {
+            if !mutual.contains(key) {
+                edges.insert(key.clone(), amount);
+            }
+        }
+        Self::from_edge_map(&edges, &self.protected)
+    }
+
+    /// Edges that have a reverse edge in a currency accepted by `include`,
+    /// and what each such pair nets to.
+    ///
+    /// The netted map holds one entry per non-offsetting pair, in the
+    /// direction of the larger amount; exactly offsetting pairs are absent.
+    fn net_mutual_pairs(
+        &self,
+        include: impl Fn(&CurrencyCode) -> bool,
+    ) -> (HashSet<EdgeKey>, HashMap<EdgeKey, Decimal>) {
+        let mut mutual = HashSet::new();
+        let mut netted = HashMap::new();
+        for ((debtor, creditor, currency), &amount) in &self.edges {
+            let reverse = self.edge_amount(creditor, debtor, currency);
+            if !include(currency) || reverse.is_zero() {
+                continue;
+            }
+            let key = (debtor.clone(), creditor.clone(), currency.clone());
+            if amount > reverse {
+                netted.insert(key.clone(), amount - reverse);
+            }
+            mutual.insert(key);
+        }
+        (mutual, netted)
+    }
+
+    /// Changes from this graph to `other`, e.g. yesterday's network to today's.
+    pub fn compare(&self, other: &PaymentGraph) -> GraphDiff {
+        let sorted_difference = |a: &HashSet<PartyId>, b: &HashSet<PartyId>| {
+            let mut parties: Vec<PartyId> = a.difference(b).cloned().collect();
+            parties.sort();
+            parties
+        };
+        let mut diff = GraphDiff {
+            added_parties: sorted_difference(&other.parties, &self.parties),
+            removed_parties: sorted_difference(&self.parties, &other.parties),
+            ..GraphDiff::default()
+        };
+
+        let old: BTreeMap<_, Decimal> = self.edges.iter().map(|(k, v)| (k, *v)).collect();
+        let new: BTreeMap<_, Decimal> = other.edges.iter().map(|(k, v)| (k, *v)).collect();
+        for (key, &amount) in &old {
+            let (debtor, creditor, currency) = (*key).clone();
+            match new.get(key) {
+                None => diff.removed_edges.push((debtor, creditor, currency, amount)),
+                Some(&updated) if updated != amount => {
+                    diff.changed_edges.push((debtor, creditor, currency, amount, updated))
+                }
+                Some(_) => {}
+            }
+            *diff.gross_change.entry(key.2.clone()).or_default() -= amount;
+        }
+        for (key, &amount) in &new {
+            if !old.contains_key(key) {
+                let (debtor, creditor, currency) = (*key).clone();
+                diff.added_edges.push((debtor, creditor, currency, amount));
+            }
+            *diff.gross_change.entry(key.2.clone()).or_default() += amount;
+        }
+        diff
+    }
+
+    /// The part of this graph lying entirely within `parties`.
+    ///
+    /// Only obligations whose debtor and creditor are both in the set are
+    /// kept; connections to outside parties are dropped. Every member of
+    /// `parties` known to this graph appears, even without internal edges.
+    pub fn subgraph(&self, parties: &HashSet<PartyId>) -> PaymentGraph {
+        let mut sub = PaymentGraph::new();
+        for ob in self.obligations.obligations() {
+            if parties.contains(ob.debtor()) && parties.contains(ob.creditor()) {
+                sub.add_obligation(ob.clone());
+            }
+        }
+        sub.parties.extend(parties.intersection(&self.parties).cloned());
+        sub
+    }
+
+    /// Netting summary for each strongly connected component in `currency`.
+    ///
+    /// Each component is paired with the multilateral netting result of the
+    /// obligations running between its own members, giving a per-cluster
+    /// view of netting efficiency. Singleton components have nothing to net
+    /// internally and are skipped unless `include_singletons` is set.
+    pub fn components_with_summary(
+        &self,
+        currency: &CurrencyCode,
+        include_singletons: bool,
+    ) -> Vec<(StronglyConnectedComponent, NettingResult)> {
+        let sccs: Vec<StronglyConnectedComponent> = find_sccs(self, currency)
+            .into_iter()
+            .filter(|scc| include_singletons || scc.is_nettable())
+            .collect();
+        let component_of: HashMap<&PartyId, usize> = sccs
+            .iter()
+            .enumerate()
+            .flat_map(|(i, scc)| scc.parties.iter().map(move |party| (party, i)))
+            .collect();
+
+        // One pass over the obligations, bucketing those internal to a component
+        let mut internal: Vec<ObligationSet> = vec![ObligationSet::new(); sccs.len()];
+        for ob in self.obligations.obligations() {
+            if ob.currency() != currency {
+                continue;
+            }
+            let debtor = component_of.get(ob.debtor());
+            let creditor = component_of.get(ob.creditor());
+            if let Some(&component) = debtor.filter(|_| debtor == creditor) {
+                internal[component].add(ob.clone());
+            }
+        }
+
+        sccs.into_iter()
+            .zip(internal)
+            .map(|(scc, set)| {
+                let summary = NettingEngine::multilateral_net(&set);
+                (scc, summary)
+            })
+            .collect()
+    }
+
+    /// Number of simple cycles in `currency`, keyed by cycle length.
+    ///
+    /// No cycle can be longer than the strongly connected component that
+    /// contains it, so the search is bounded by the largest component and
+    /// skipped entirely for acyclic currencies.
+    pub fn cycle_length_distribution(&self, currency: &CurrencyCode) -> BTreeMap<usize, usize> {
+        let mut distribution = BTreeMap::new();
+        let max_len = find_sccs(self, currency)
+            .iter()
+            .filter(|scc| scc.is_nettable())
+            .map(|scc| scc.len())
+            .max();
+        if let Some(max_len) = max_len {
+            for cycle in find_cycles_bounded(self, currency, max_len) {
+                *distribution.entry(cycle.len()).or_insert(0) += 1;
+            }
+        }
+        distribution
+    }
+
+    /// Render the condensation of `currency` as a Graphviz DOT digraph.
+    ///
+    /// Each strongly connected component among the parties trading in
+    /// `currency` becomes one node, labelled with its members and the
+    /// savings from netting its internal obligations. Edges between
+    /// components carry the aggregated amount flowing from one to the
+    /// other. Components are numbered in order of their first member.
+    pub fn condensation_to_dot(&self, currency: &CurrencyCode) -> String {
+        let active: HashSet<&PartyId> = self
+            .edges
+            .keys()
+            .filter(|(_, _, cur)| cur == currency)
+            .flat_map(|(debtor, creditor, _)| [debtor, creditor])
+            .collect();
+        let mut components: Vec<(Vec<PartyId>, Decimal)> = self
+            .components_with_summary(currency, true)
+            .into_iter()
+            .filter(|(scc, _)| scc.parties.iter().any(|p| active.contains(p)))
+            .map(|(scc, summary)| {
+                let mut parties = scc.parties;
+                parties.sort();
+                (parties, summary.savings())
+            })
+            .collect();
+        components.sort();
+
+        let mut component_of: HashMap<&PartyId, usize> = HashMap::new();
+        for (i, (parties, _)) in components.iter().enumerate() {
+            for party in parties {
+                component_of.insert(party, i);
+            }
+        }
+        let mut between: BTreeMap<(usize, usize), Decimal> = BTreeMap::new();
+        for ((debtor, creditor, cur), &amount) in &self.edges {
+            if cur != currency {
+                continue;
+            }
+            let (from, to) = (component_of[debtor], component_of[creditor]);
+            if from != to {
+                *between.entry((from, to)).or_insert(Decimal::ZERO) += amount;
+            }
+        }
+
+        let escape = |s: &str| s.replace('\\', "\\\\").replace('"', "\\\"");
+        let mut dot = format!("digraph \"{}\" {{\n", escape(currency.as_str()));
+        for (i, (parties, savings)) in components.iter().enumerate() {
+            let members: Vec<String> = parties.iter().map(|p| escape(&p.to_string())).collect();
+            dot.push_str(&format!(
+                "    scc{} [label=\"{}\\nsavings: {}\"];\n",
+                i,
+                members.join(", "),
+                savings
+            ));
+        }
+        for ((from, to), amount) in &between {
+            dot.push_str(&format!("    scc{} -> scc{} [label=\"{}\"];\n", from, to, amount));
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// Parties ranked by how many cycles their outgoing edge bottlenecks.
+    ///
+    /// For each simple cycle in `currency`, the debtor of the smallest
+    /// edge (every one, if tied) is counted; adding liquidity there would
+    /// unlock the most netting. Sorted by count descending, then party id;
+    /// parties that bottleneck no cycle are omitted.
+    pub fn bottleneck_parties(&self, currency: &CurrencyCode) -> Vec<(PartyId, usize)> {
+        let mut counts: HashMap<PartyId, usize> = HashMap::new();
+        for cycle in find_cycles(self, currency) {
+            let n = cycle.parties.len();
+            for (i, debtor) in cycle.parties.iter().enumerate() {
+                let creditor = &cycle.parties[(i + 1) % n];
+                if self.edge_amount(debtor, creditor, currency) == cycle.bottleneck {
+                    *counts.entry(debtor.clone()).or_insert(0) += 1;
+                }
+            }
+        }
+        let mut ranked: Vec<(PartyId, usize)> = counts.into_iter().collect();
+        ranked.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        ranked
+    }
+
+    /// Net every mutual pair in `currency` in place.
+    ///
+    /// Like [`PaymentGraph::bilateral_compress`] restricted to one
+    /// currency: the obligations making up A→B and B→A are replaced by a
+    /// single aggregated edge in the direction of the larger amount, and
+    /// exactly offsetting pairs disappear. Every other obligation is kept
+    /// as it was, and so is every known party. Net positions are
+    /// unchanged, and fewer edges make cycle detection cheaper.
+    pub fn prune_reverse_dominated(&mut self, currency: &CurrencyCode) {
+        let (mutual, netted) = self.net_mutual_pairs(|cur| cur == currency);
+        if mutual.is_empty() {
+            return;
+        }
+        let mut pruned = PaymentGraph::new();
+        for ob in self.obligations.obligations() {
+            let key = (ob.debtor().clone(), ob.creditor().clone(), ob.currency().clone());
+            if !mutual.contains(&key) {
+                pruned.add_obligation(ob.clone());
+            }
+        }
+        for ob in Self::from_edge_map(&netted, &self.protected).obligations.obligations() {
+            pruned.add_obligation(ob.clone());
+        }
+        pruned.parties.extend(self.parties.iter().cloned());
+        *self = pruned;
+    }
+
     /// Build an adjacency list for a specific currency.
     /// Returns: party -> [(counterparty, amount)]
     pub fn adjacency_list(
@@ -195,6 +708,14 @@ mod tests {
     use super::*;
     use rust_decimal_macros::dec;
 
+    fn ob(debtor: &str, creditor: &str, amount: Decimal) -> Obligation {
+        ob_in(debtor, creditor, amount, &CurrencyCode::new("USD"))
+    }
+
+    fn ob_in(debtor: &str, creditor: &str, amount: Decimal, currency: &CurrencyCode) -> Obligation {
+        Obligation::new(PartyId::new(debtor), PartyId::new(creditor), amount, currency.clone())
+    }
+
     #[test]
     fn test_graph_basic() {
         let mut graph = PaymentGraph::new();
@@ -259,4 +780,345 @@ mod tests {
             dec!(500)
         );
     }
+
+    #[test]
+    fn test_add_obligation_checked_enforces_limit() {
+        let usd = CurrencyCode::new("USD");
+        let a = PartyId::new("A");
+        let limits: HashMap<(PartyId, CurrencyCode), Decimal> =
+            [((a.clone(), usd.clone()), dec!(250))].into();
+
+        let mut graph = PaymentGraph::new();
+        graph
+            .add_obligation_checked(
+                Obligation::new(a.clone(), PartyId::new("B"), dec!(100), usd.clone()),
+                &limits,
+            )
+            .unwrap();
+        graph
+            .add_obligation_checked(
+                Obligation::new(a.clone(), PartyId::new("C"), dec!(100), usd.clone()),
+                &limits,
+            )
+            .unwrap();
+        let err = graph
+            .add_obligation_checked(
+                Obligation::new(a.clone(), PartyId::new("B"), dec!(100), usd.clone()),
+                &limits,
+            )
+            .unwrap_err();
+
+        assert_eq!(err.exposure, dec!(300));
+        assert_eq!(err.limit, dec!(250));
+        assert_eq!(graph.obligation_count(), 2);
+
+        // Other parties are unconstrained
+        graph
+            .add_obligation_checked(
+                Obligation::new(PartyId::new("B"), a, dec!(1_000), usd),
+                &limits,
+            )
+            .unwrap();
+    }
+
+    #[test]
+    fn test_pair_exposure_in_base() {
+        let usd = CurrencyCode::new("USD");
+        let brl = CurrencyCode::new("BRL");
+        let a = PartyId::new("A");
+        let b = PartyId::new("B");
+        let graph = PaymentGraph::from_obligations(vec![
+            Obligation::new(a.clone(), b.clone(), dec!(100), usd.clone()),
+            Obligation::new(b.clone(), a.clone(), dec!(500), brl.clone()),
+            Obligation::new(a.clone(), PartyId::new("C"), dec!(999), usd.clone()),
+        ]);
+
+        let mut rates = FxRateTable::new(usd.clone());
+        rates.set_rate(brl.clone(), usd.clone(), dec!(0.20)).unwrap();
+        assert_eq!(graph.pair_exposure(&a, &b, &rates, &usd).unwrap(), dec!(200));
+        assert_eq!(graph.pair_exposure(&b, &a, &rates, &usd).unwrap(), dec!(200));
+
+        let empty = FxRateTable::new(usd.clone());
+        assert!(matches!(
+            graph.pair_exposure(&a, &b, &empty, &usd),
+            Err(FxError::RateNotFound { .. })
+        ));
+    }
+
+    #[test]
+    fn test_bilateral_compress_collapses_mutual_edges() {
+        let usd = CurrencyCode::new("USD");
+        let a = PartyId::new("A");
+        let b = PartyId::new("B");
+        let c = PartyId::new("C");
+        let graph = PaymentGraph::from_obligations(vec![
+            Obligation::new(a.clone(), b.clone(), dec!(100), usd.clone()),
+            Obligation::new(b.clone(), a.clone(), dec!(60), usd.clone()),
+            Obligation::new(b.clone(), c.clone(), dec!(30), usd.clone()),
+            Obligation::new(c.clone(), b.clone(), dec!(30), usd.clone()),
+        ]);
+
+        let compressed = graph.bilateral_compress();
+        assert_eq!(compressed.edges().len(), 1);
+        assert_eq!(compressed.edge_amount(&a, &b, &usd), dec!(40));
+        assert_eq!(compressed.edge_amount(&b, &a, &usd), Decimal::ZERO);
+
+        let before = graph.compute_net_positions();
+        let after = compressed.compute_net_positions();
+        for party in [&a, &b, &c] {
+            assert_eq!(before.net_position(party, &usd), after.net_position(party, &usd));
+        }
+    }
+
+    #[test]
+    fn test_components_with_summary() {
+        let usd = CurrencyCode::new("USD");
+        let graph = PaymentGraph::from_obligations(vec![
+            ob("A", "B", dec!(100)),
+            ob("B", "A", dec!(100)),
+            ob("C", "D", dec!(50)),
+            ob("D", "C", dec!(30)),
+            ob("D", "E", dec!(10)),
+        ]);
+
+        let mut summaries = graph.components_with_summary(&usd, false);
+        assert_eq!(summaries.len(), 2);
+        summaries.sort_by_key(|(scc, _)| scc.parties.iter().min().cloned());
+
+        let (ab, ab_net) = &summaries[0];
+        assert_eq!(ab.len(), 2);
+        assert_eq!(ab_net.gross_total(), dec!(200));
+        assert_eq!(ab_net.net_total(), Decimal::ZERO);
+
+        // D→E leaves the component and is excluded from its summary
+        let (_, cd_net) = &summaries[1];
+        assert_eq!(cd_net.gross_total(), dec!(80));
+        assert_eq!(cd_net.net_total(), dec!(20));
+
+        assert_eq!(graph.components_with_summary(&usd, true).len(), 3);
+    }
+
+    #[test]
+    fn test_sources_and_sinks() {
+        let usd = CurrencyCode::new("USD");
+        let graph = PaymentGraph::from_obligations(vec![
+            Obligation::new(PartyId::new("A"), PartyId::new("B"), dec!(100), usd.clone()),
+            Obligation::new(PartyId::new("B"), PartyId::new("C"), dec!(80), usd.clone()),
+            Obligation::new(PartyId::new("C"), PartyId::new("D"), dec!(60), usd.clone()),
+            Obligation::new(PartyId::new("D"), PartyId::new("A"), dec!(5), CurrencyCode::new("EUR")),
+        ]);
+
+        assert_eq!(graph.sources(&usd), vec![PartyId::new("A")]);
+        assert_eq!(graph.sinks(&usd), vec![PartyId::new("D")]);
+        assert!(graph.sources(&CurrencyCode::new("BRL")).is_empty());
+    }
+
+    #[test]
+    fn test_edge_list_round_trip() {
+        let usd = CurrencyCode::new("USD");
+        let graph = PaymentGraph::from_obligations(vec![
+            Obligation::new(PartyId::new("C"), PartyId::new("A"), dec!(25.5), usd.clone()),
+            Obligation::new(PartyId::new("A"), PartyId::new("B"), dec!(100), usd.clone()),
+            Obligation::new(PartyId::new("B"), PartyId::new("C"), dec!(7), CurrencyCode::new("EUR")),
+        ]);
+
+        let labels = graph.node_labels();
+        let names: Vec<&str> = labels.iter().map(|p| p.as_str()).collect();
+        assert_eq!(names, vec!["A", "B", "C"]);
+
+        let edges = graph.edge_list(&usd);
+        assert_eq!(edges, vec![(0, 1, 100.0), (2, 0, 25.5)]);
+        for (from, to, weight) in edges {
+            let amount = graph.edge_amount(&labels[from], &labels[to], &usd);
+            assert_eq!(amount.to_string().parse::<f64>().unwrap(), weight);
+        }
+    }
+
+    #[test]
+    fn test_systemic_score_ranks_hub_highest() {
+        let usd = CurrencyCode::new("USD");
+        let graph = PaymentGraph::from_obligations(vec![
+            ob("A", "HUB", dec!(500)),
+            ob("B", "HUB", dec!(400)),
+            ob("HUB", "C", dec!(600)),
+            ob("HUB", "D", dec!(300)),
+            ob("C", "D", dec!(10)),
+        ]);
+
+        let scores = graph.systemic_score(&usd);
+        assert_eq!(scores.len(), 5);
+        let hub = scores[&PartyId::new("HUB")];
+        assert!(scores.iter().all(|(p, s)| p.as_str() == "HUB" || *s < hub));
+        // Four of four counterparties, 1800 of 3620 two-sided flow
+        assert_eq!(hub, dec!(1800) / dec!(3620));
+    }
+
+    #[test]
+    fn test_prune_reverse_dominated() {
+        let usd = CurrencyCode::new("USD");
+        let eur = CurrencyCode::new("EUR");
+        let mut graph = PaymentGraph::from_obligations(vec![
+            ob_in("A", "B", dec!(100), &usd),
+            ob_in("B", "A", dec!(60), &usd),
+            ob_in("B", "C", dec!(50), &usd),
+            ob_in("C", "B", dec!(50), &usd),
+            ob_in("C", "A", dec!(20), &usd).with_reference("INV-7"),
+            ob_in("A", "C", dec!(5), &eur),
+            ob_in("C", "A", dec!(5), &eur),
+        ]);
+        let untouched: Vec<Obligation> = graph
+            .obligations()
+            .obligations()
+            .iter()
+            .filter(|ob| ob.currency() == &eur || ob.reference().is_some())
+            .cloned()
+            .collect();
+        let before = graph.compute_net_positions();
+        assert_eq!(graph.edges().len(), 7);
+
+        graph.prune_reverse_dominated(&usd);
+
+        // A→B 40 and C→A 20 remain in USD; EUR is untouched
+        assert_eq!(graph.edges().len(), 4);
+        assert_eq!(graph.edge_amount(&PartyId::new("A"), &PartyId::new("B"), &usd), dec!(40));
+        assert_eq!(graph.edge_amount(&PartyId::new("B"), &PartyId::new("A"), &usd), Decimal::ZERO);
+        // Obligations outside the mutual USD pairs keep their ids and fields
+        for ob in &untouched {
+            let kept = graph.obligations().obligations().iter().find(|k| k.id() == ob.id());
+            assert_eq!(kept.map(|k| k.reference()), Some(ob.reference()));
+        }
+        assert_eq!(graph.party_count(), 3);
+        let after = graph.compute_net_positions();
+        for party in ["A", "B", "C"] {
+            for cur in [&usd, &eur] {
+                let party = PartyId::new(party);
+                assert_eq!(before.net_position(&party, cur), after.net_position(&party, cur));
+            }
+        }
+    }
+
+    #[test]
+    fn test_cycle_length_distribution() {
+        let usd = CurrencyCode::new("USD");
+        let graph = PaymentGraph::from_obligations(vec![
+            ob("A", "B", dec!(100)),
+            ob("B", "A", dec!(40)),
+            ob("C", "D", dec!(30)),
+            ob("D", "E", dec!(30)),
+            ob("E", "C", dec!(30)),
+        ]);
+
+        let distribution = graph.cycle_length_distribution(&usd);
+        assert_eq!(distribution, BTreeMap::from([(2, 1), (3, 1)]));
+        assert!(graph.cycle_length_distribution(&CurrencyCode::new("EUR")).is_empty());
+    }
+
+    #[test]
+    fn test_condensation_to_dot() {
+        let usd = CurrencyCode::new("USD");
+        let graph = PaymentGraph::from_obligations(vec![
+            ob("A", "B", dec!(100)),
+            ob("B", "C", dec!(100)),
+            ob("C", "A", dec!(100)),
+            ob("C", "D", dec!(40)),
+            ob("A", "D", dec!(10)),
+        ]);
+
+        let dot = graph.condensation_to_dot(&usd);
+
+        assert!(dot.starts_with("digraph \"USD\" {"));
+        assert_eq!(dot.matches("savings: ").count(), 2);
+        assert!(dot.contains("scc0 [label=\"A, B, C\\nsavings: 300\"];"));
+        assert!(dot.contains("scc1 [label=\"D\\nsavings: 0\"];"));
+        assert!(dot.contains("scc0 -> scc1 [label=\"50\"];"));
+    }
+
+    #[test]
+    fn test_compare_categorizes_changes() {
+        let usd = CurrencyCode::new("USD");
+        let yesterday = PaymentGraph::from_obligations(vec![
+            ob("A", "B", dec!(100)),
+            ob("B", "C", dec!(40)),
+            ob("C", "A", dec!(10)),
+        ]);
+        // C has left the network; B now also pays A, and A owes B more
+        let today = PaymentGraph::from_obligations(vec![
+            ob("A", "B", dec!(120)),
+            ob("B", "A", dec!(30)),
+        ]);
+
+        let diff = yesterday.compare(&today);
+        assert!(diff.added_parties.is_empty());
+        assert_eq!(diff.removed_parties, vec![PartyId::new("C")]);
+        assert_eq!(
+            diff.added_edges,
+            vec![(PartyId::new("B"), PartyId::new("A"), usd.clone(), dec!(30))]
+        );
+        assert_eq!(
+            diff.removed_edges,
+            vec![
+                (PartyId::new("B"), PartyId::new("C"), usd.clone(), dec!(40)),
+                (PartyId::new("C"), PartyId::new("A"), usd.clone(), dec!(10)),
+            ]
+        );
+        assert_eq!(
+            diff.changed_edges,
+            vec![(PartyId::new("A"), PartyId::new("B"), usd.clone(), dec!(100), dec!(120))]
+        );
+        assert_eq!(diff.gross_change[&usd], dec!(0));
+
+        assert!(today.compare(&today).is_empty());
+    }
+
+    #[test]
+    fn test_subgraph_of_brics() {
+        let usd = CurrencyCode::new("USD");
+        let graph = PaymentGraph::from_obligations(vec![
+            ob("BR-TREASURY", "IN-RBI", dec!(100_000_000)),
+            ob("IN-RBI", "CN-PBOC", dec!(80_000_000)),
+            ob("CN-PBOC", "RU-CBR", dec!(120_000_000)),
+            ob("RU-CBR", "BR-TREASURY", dec!(90_000_000)),
+            ob("ZA-SARB", "IN-RBI", dec!(40_000_000)),
+            ob("CN-PBOC", "BR-TREASURY", dec!(70_000_000)),
+            ob("IN-RBI", "RU-CBR", dec!(30_000_000)),
+            ob("RU-CBR", "ZA-SARB", dec!(25_000_000)),
+        ]);
+        let region: HashSet<PartyId> = ["BR-TREASURY", "IN-RBI", "CN-PBOC"]
+            .into_iter()
+            .map(PartyId::new)
+            .collect();
+
+        let sub = graph.subgraph(&region);
+
+        assert_eq!(sub.party_count(), 3);
+        assert_eq!(sub.edges().len(), 3);
+        assert_eq!(sub.gross_total(), dec!(250_000_000));
+        assert_eq!(
+            sub.edge_amount(&PartyId::new("CN-PBOC"), &PartyId::new("BR-TREASURY"), &usd),
+            dec!(70_000_000)
+        );
+
+        // A member with no internal edges is kept as an isolated party
+        let with_za: HashSet<PartyId> =
+            ["BR-TREASURY", "ZA-SARB"].into_iter().map(PartyId::new).collect();
+        let sparse = graph.subgraph(&with_za);
+        assert_eq!(sparse.party_count(), 2);
+        assert!(sparse.edges().is_empty());
+    }
+
+    #[test]
+    fn test_bottleneck_parties() {
+        let usd = CurrencyCode::new("USD");
+        // Cycles A→B→C→A and A→B→D→A share the thin A→B edge
+        let graph = PaymentGraph::from_obligations(vec![
+            ob("A", "B", dec!(10)),
+            ob("B", "C", dec!(50)),
+            ob("C", "A", dec!(50)),
+            ob("B", "D", dec!(40)),
+            ob("D", "A", dec!(40)),
+        ]);
+
+        assert_eq!(graph.bottleneck_parties(&usd), vec![(PartyId::new("A"), 2)]);
+        assert!(graph.bottleneck_parties(&CurrencyCode::new("EUR")).is_empty());
+    }
 }
@@ -2,6 +2,7 @@ use crate::core::currency::CurrencyCode;
 use crate::core::party::PartyId;
 use crate::graph::payment_graph::PaymentGraph;
 use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
 /// A strongly connected component in the payment graph.
@@ -9,7 +10,7 @@ use std::collections::HashMap;
 /// All parties within an SCC can reach each other through payment chains,
 /// meaning multilateral netting is possible within the component.
 /// Parties in different SCCs can only settle bilaterally.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct StronglyConnectedComponent {
     pub parties: Vec<PartyId>,
     pub currency: CurrencyCode,
@@ -11,10 +11,14 @@
 //! - **graph** — Payment graph, cycle detection, strongly connected components
 //! - **optimization** — Bilateral and multilateral netting algorithms
 //! - **simulation** — Stress testing and FX volatility modeling
+//! - **session** — Stateful, event-driven clearing session
+//! - **error** — [`ClearingError`](error::ClearingError), aggregating every module's errors
 
 pub mod core;
+pub mod error;
 pub mod graph;
 pub mod optimization;
+pub mod session;
 pub mod simulation;
 
 /// Convenience re-exports for common usage.
@@ -23,6 +27,7 @@ pub mod prelude {
     pub use crate::core::ledger::Ledger;
     pub use crate::core::obligation::Obligation;
     pub use crate::core::party::PartyId;
+    pub use crate::error::ClearingError;
     pub use crate::graph::payment_graph::PaymentGraph;
     pub use crate::optimization::netting::{BilateralNettingResult, NettingEngine, NettingResult};
 }
@@ -11,6 +11,9 @@
 //! # Output as JSON
 //! clearing-engine net --input obligations.json --format json
 //!
+//! # Output as CSV
+//! clearing-engine net --input obligations.json --format csv
+//!
 //! # Analyze cycles
 //! clearing-engine cycles --input obligations.json
 //!
@@ -18,14 +21,17 @@
 //! clearing-engine generate --parties 10 --obligations 30
 //! ```
 
-use clearing_engine::core::currency::CurrencyCode;
-use clearing_engine::core::obligation::{Obligation, ObligationSet};
-use clearing_engine::core::party::PartyId;
+use clearing_engine::core::currency::{CurrencyCode, FxRateTable};
+use clearing_engine::core::obligation::ObligationSet;
+use clearing_engine::core::schema::{InputFormat, IoError, SchemaError};
 use clearing_engine::graph::cycle_detection::find_cycles;
 use clearing_engine::graph::payment_graph::PaymentGraph;
 use clearing_engine::optimization::liquidity::LiquidityAnalysis;
 use clearing_engine::optimization::netting::NettingEngine;
-use clearing_engine::simulation::stress_test::{generate_random_network, NetworkConfig};
+use clearing_engine::simulation::fx_volatility::FxShockConfig;
+use clearing_engine::simulation::stress_test::{
+    generate_random_network, NetworkConfig, StressScenario,
+};
 use rust_decimal::Decimal;
 use std::fs;
 use std::process;
@@ -41,11 +47,19 @@ COMMANDS:
     net         Run multilateral netting on an obligation set
     cycles      Detect payment cycles in the obligation graph
     generate    Generate a random obligation network (for testing)
+    stress      Net a seeded random network and apply FX shocks
     help        Show this message
 
 OPTIONS (net, cycles):
-    --input <FILE>      Path to JSON obligations file
+    --input <FILE>      Path to obligations file (JSON; .ndjson/.jsonl
+                        and .csv are read by extension)
     --format <FORMAT>   Output format: text (default) or json
+                        (net also accepts csv)
+
+OPTIONS (net):
+    --min-savings <PCT> Exit with code 2, printing nothing to stdout, if
+                        netting saves less than PCT percent of gross (the
+                        reason goes to stderr)
 
 OPTIONS (generate):
     --parties <N>       Number of parties (default: 10)
@@ -53,34 +67,26 @@ OPTIONS (generate):
     --currencies <LIST> Comma-separated currency codes (default: USD)
     --output <FILE>     Write to file instead of stdout
 
+OPTIONS (stress):
+    --parties, --obligations, --currencies   As for generate
+    --seed <N>          Seed for the generated network (default: 0)
+    --rate <F/T=R>      FX rate: 1 F = R T (repeatable); the first
+                        currency is the base for valuing shocks
+    --shock <F/T=S>     Move the F/T rate by -S, e.g. BRL/USD=0.10
+                        for a 10% depreciation (repeatable)
+    --format <FORMAT>   Output format: text (default) or json
+
 EXAMPLES:
     clearing-engine net --input obligations.json
     clearing-engine net --input obligations.json --format json
+    clearing-engine net --input obligations.json --format csv
     clearing-engine cycles --input obligations.json
     clearing-engine generate --parties 20 --obligations 60
-    clearing-engine generate --parties 5 --currencies USD,BRL,INR --output test.json"#
+    clearing-engine generate --parties 5 --currencies USD,BRL,INR --output test.json
+    clearing-engine stress --currencies USD,BRL --seed 7 --rate BRL/USD=0.2 --shock BRL/USD=0.1"#
     );
 }
 
-/// JSON schema for input obligations.
-#[derive(serde::Deserialize)]
-struct ObligationInput {
-    from: String,
-    to: String,
-    amount: String,
-    #[serde(default = "default_currency")]
-    currency: String,
-}
-
-fn default_currency() -> String {
-    "USD".to_string()
-}
-
-#[derive(serde::Deserialize)]
-struct ObligationsFile {
-    obligations: Vec<ObligationInput>,
-}
-
 /// JSON output schema for netting results.
 #[derive(serde::Serialize)]
 struct NettingOutput {
@@ -108,42 +114,47 @@ struct CycleOutput {
     potential_savings: String,
 }
 
+/// Pick the input format from the file extension: `.csv`, `.ndjson` /
+/// `.jsonl`, or JSON for anything else.
+fn input_format(path: &str) -> InputFormat {
+    let extension = std::path::Path::new(path)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.to_ascii_lowercase());
+    match extension.as_deref() {
+        Some("csv") => InputFormat::Csv,
+        Some("ndjson") | Some("jsonl") => InputFormat::Ndjson,
+        _ => InputFormat::Json,
+    }
+}
+
 fn load_obligations(path: &str) -> ObligationSet {
-    let content = fs::read_to_string(path).unwrap_or_else(|e| {
+    let file = fs::File::open(path).unwrap_or_else(|e| {
         eprintln!("Error reading file '{}': {}", path, e);
         process::exit(1);
     });
 
-    let file: ObligationsFile = serde_json::from_str(&content).unwrap_or_else(|e| {
-        eprintln!("Error parsing JSON: {}", e);
-        eprintln!("Expected format:");
-        eprintln!(r#"{{
+    ObligationSet::from_reader(file, input_format(path)).unwrap_or_else(|e| {
+        eprintln!("Error in '{}': {}", path, e);
+        if let IoError::Schema(SchemaError::Json(_)) = e {
+            eprintln!("Expected format:");
+            eprintln!(r#"{{
   "obligations": [
     {{ "from": "BR-TREASURY", "to": "IN-RBI", "amount": "100000000", "currency": "USD" }}
   ]
 }}"#);
+        }
         process::exit(1);
-    });
-
-    let mut set = ObligationSet::new();
-    for ob in file.obligations {
-        let amount: Decimal = ob.amount.parse().unwrap_or_else(|e| {
-            eprintln!("Invalid amount '{}': {}", ob.amount, e);
-            process::exit(1);
-        });
-        set.add(Obligation::new(
-            PartyId::new(&ob.from),
-            PartyId::new(&ob.to),
-            amount,
-            CurrencyCode::new(&ob.currency),
-        ));
-    }
-    set
+    })
 }
 
+/// Exit code of `net --min-savings` when savings fall below the threshold.
+const EXIT_BELOW_MIN_SAVINGS: i32 = 2;
+
 fn cmd_net(args: &[String]) {
     let mut input_path = None;
     let mut format = "text".to_string();
+    let mut min_savings = None;
     let mut i = 0;
     while i < args.len() {
         match args[i].as_str() {
@@ -157,10 +168,21 @@ fn cmd_net(args: &[String]) {
             "--format" => {
                 i += 1;
                 format = args.get(i).cloned().unwrap_or_else(|| {
-                    eprintln!("--format requires 'text' or 'json'");
+                    eprintln!("--format requires 'text', 'json' or 'csv'");
                     process::exit(1);
                 });
             }
+            "--min-savings" => {
+                i += 1;
+                min_savings = Some(
+                    args.get(i)
+                        .and_then(|s| s.parse::<f64>().ok())
+                        .unwrap_or_else(|| {
+                            eprintln!("--min-savings requires a percentage, e.g. 5");
+                            process::exit(1);
+                        }),
+                );
+            }
             _ => {
                 eprintln!("Unknown option: {}", args[i]);
                 process::exit(1);
@@ -177,6 +199,17 @@ fn cmd_net(args: &[String]) {
     let set = load_obligations(&path);
     let result = NettingEngine::multilateral_net(&set);
 
+    if let Some(threshold) = min_savings {
+        if !result.is_worthwhile(threshold) {
+            eprintln!(
+                "Netting saves {:.2}%, below the {}% minimum; skipping",
+                result.savings_percent(),
+                threshold
+            );
+            process::exit(EXIT_BELOW_MIN_SAVINGS);
+        }
+    }
+
     if format == "json" {
         let mut positions = Vec::new();
         for ((party, currency), amount) in result.ledger().all_positions() {
@@ -205,6 +238,8 @@ fn cmd_net(args: &[String]) {
         };
 
         println!("{}", serde_json::to_string_pretty(&output).unwrap());
+    } else if format == "csv" {
+        print!("{}", result.to_csv());
     } else {
         println!("{}", result);
 
@@ -358,33 +393,7 @@ fn cmd_generate(args: &[String]) {
 
     let set = generate_random_network(&config);
 
-    #[derive(serde::Serialize)]
-    struct OutputObligation {
-        from: String,
-        to: String,
-        amount: String,
-        currency: String,
-    }
-
-    #[derive(serde::Serialize)]
-    struct OutputFile {
-        obligations: Vec<OutputObligation>,
-    }
-
-    let output = OutputFile {
-        obligations: set
-            .obligations()
-            .iter()
-            .map(|ob| OutputObligation {
-                from: ob.debtor().to_string(),
-                to: ob.creditor().to_string(),
-                amount: ob.amount().to_string(),
-                currency: ob.currency().to_string(),
-            })
-            .collect(),
-    };
-
-    let json = serde_json::to_string_pretty(&output).unwrap();
+    let json = set.to_cli_json();
 
     if let Some(path) = output_path {
         fs::write(&path, &json).unwrap_or_else(|e| {
@@ -402,6 +411,121 @@ fn cmd_generate(args: &[String]) {
     }
 }
 
+/// Parse `FROM/TO=VALUE`, as used by `--rate` and `--shock`.
+fn parse_pair_value(flag: &str, raw: &str) -> (CurrencyCode, CurrencyCode, Decimal) {
+    let parsed = raw.split_once('=').and_then(|(pair, value)| {
+        let (from, to) = pair.split_once('/')?;
+        let value = value.trim().parse::<Decimal>().ok()?;
+        Some((CurrencyCode::new(from.trim()), CurrencyCode::new(to.trim()), value))
+    });
+    parsed.unwrap_or_else(|| {
+        eprintln!("{} requires FROM/TO=VALUE, got '{}'", flag, raw);
+        process::exit(1);
+    })
+}
+
+fn cmd_stress(args: &[String]) {
+    let mut parties = 10usize;
+    let mut obligations_count = 30usize;
+    let mut currencies_str = "USD".to_string();
+    let mut seed = 0u64;
+    let mut rates: Vec<(CurrencyCode, CurrencyCode, Decimal)> = Vec::new();
+    let mut shocks = FxShockConfig::default();
+    let mut format = "text".to_string();
+    let mut i = 0;
+    while i < args.len() {
+        let value = |i: usize| {
+            args.get(i).cloned().unwrap_or_else(|| {
+                eprintln!("{} requires a value", args[i - 1]);
+                process::exit(1);
+            })
+        };
+        let number = |i: usize| {
+            value(i).parse::<u64>().unwrap_or_else(|_| {
+                eprintln!("{} requires a number", args[i - 1]);
+                process::exit(1);
+            })
+        };
+        match args[i].as_str() {
+            "--parties" => {
+                i += 1;
+                parties = number(i) as usize;
+            }
+            "--obligations" => {
+                i += 1;
+                obligations_count = number(i) as usize;
+            }
+            "--currencies" => {
+                i += 1;
+                currencies_str = value(i);
+            }
+            "--seed" => {
+                i += 1;
+                seed = number(i);
+            }
+            "--rate" => {
+                i += 1;
+                rates.push(parse_pair_value("--rate", &value(i)));
+            }
+            "--shock" => {
+                i += 1;
+                let (from, to, shock) = parse_pair_value("--shock", &value(i));
+                shocks.shocks.insert((from, to), shock);
+            }
+            "--format" => {
+                i += 1;
+                format = value(i);
+            }
+            _ => {
+                eprintln!("Unknown option: {}", args[i]);
+                process::exit(1);
+            }
+        }
+        i += 1;
+    }
+
+    let currencies: Vec<CurrencyCode> = currencies_str
+        .split(',')
+        .map(|s| CurrencyCode::new(s.trim()))
+        .collect();
+
+    let rate_table = if rates.is_empty() && shocks.shocks.is_empty() {
+        None
+    } else {
+        let mut table = FxRateTable::new(currencies[0].clone());
+        for (from, to, rate) in rates {
+            table.set_rate(from, to, rate).unwrap_or_else(|e| {
+                eprintln!("Error: {}", e);
+                process::exit(1);
+            });
+        }
+        Some(table)
+    };
+
+    let scenario = StressScenario {
+        network: NetworkConfig {
+            party_count: parties,
+            currencies,
+            avg_obligations_per_party: obligations_count / parties.max(1),
+            ..Default::default()
+        },
+        seed,
+        rates: rate_table,
+        shocks,
+    };
+
+    let report = scenario.run().unwrap_or_else(|e| {
+        eprintln!("Error: {}", e);
+        process::exit(1);
+    });
+
+    if format == "json" {
+        println!("{}", serde_json::to_string_pretty(&report).unwrap());
+    } else {
+        print!("{}", report);
+    }
+}
+
 fn main() {
     let args: Vec<String> = std::env::args().collect();
 
@@ -417,6 +541,7 @@ fn main() {
         "net" => cmd_net(rest),
         "cycles" => cmd_cycles(rest),
         "generate" => cmd_generate(rest),
+        "stress" => cmd_stress(rest),
         "help" | "--help" | "-h" => print_usage(),
         _ => {
             eprintln!("Unknown command: {}", command);
@@ -0,0 +1,99 @@
+use crate::core::currency::CurrencyCode;
+use crate::core::obligation::ObligationSet;
+use crate::graph::cycle_detection::{find_cycles, PaymentCycle};
+use crate::graph::payment_graph::PaymentGraph;
+use crate::graph::scc::{find_sccs, StronglyConnectedComponent};
+use crate::optimization::liquidity::LiquidityAnalysis;
+use crate::optimization::netting::{NettingEngine, NettingResult};
+use serde::{Deserialize, Serialize};
+
+/// Every analysis of a clearing run, packaged as one archivable document.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AnalysisBundle {
+    /// Multilateral netting of the obligations.
+    pub netting: NettingResult,
+    /// Liquidity requirements derived from the netting.
+    pub liquidity: LiquidityAnalysis,
+    /// Simple cycles in every currency, currencies in code order.
+    pub cycles: Vec<PaymentCycle>,
+    /// Nettable strongly connected components (two or more parties) in
+    /// every currency, members sorted and components ordered by currency
+    /// then first member.
+    pub components: Vec<StronglyConnectedComponent>,
+}
+
+impl AnalysisBundle {
+    /// Run every analysis over `obligations`.
+    pub fn from_obligations(obligations: &ObligationSet) -> Self {
+        let netting = NettingEngine::multilateral_net(obligations);
+        let liquidity = LiquidityAnalysis::from_netting_result(&netting);
+
+        let graph = PaymentGraph::from_obligations(obligations.obligations().to_vec());
+        let mut currencies: Vec<&CurrencyCode> = graph.currencies().iter().collect();
+        currencies.sort();
+
+        let mut cycles = Vec::new();
+        let mut components = Vec::new();
+        for currency in currencies {
+            cycles.extend(find_cycles(&graph, currency));
+            let mut sccs: Vec<StronglyConnectedComponent> = find_sccs(&graph, currency)
+                .into_iter()
+                .filter(|scc| scc.is_nettable())
+                .map(|mut scc| {
+                    scc.parties.sort();
+                    scc
+                })
+                .collect();
+            sccs.sort_by(|a, b| a.parties.cmp(&b.parties));
+            components.extend(sccs);
+        }
+
+        Self {
+            netting,
+            liquidity,
+            cycles,
+            components,
+        }
+    }
+
+    /// Serialize the bundle as pretty-printed JSON.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("analysis bundles always serialize")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::obligation::Obligation;
+    use crate::core::party::PartyId;
+    use rust_decimal::Decimal;
+    use rust_decimal_macros::dec;
+
+    fn ob(debtor: &str, creditor: &str, amount: Decimal) -> Obligation {
+        let usd = CurrencyCode::new("USD");
+        Obligation::new(PartyId::new(debtor), PartyId::new(creditor), amount, usd)
+    }
+
+    #[test]
+    fn test_bundle_json_has_every_section() {
+        let set: ObligationSet = vec![
+            ob("A", "B", dec!(100)),
+            ob("B", "C", dec!(100)),
+            ob("C", "A", dec!(100)),
+            ob("C", "D", dec!(25)),
+        ]
+        .into_iter()
+        .collect();
+
+        let bundle = AnalysisBundle::from_obligations(&set);
+        assert_eq!(bundle.cycles.len(), 1);
+        assert_eq!(bundle.components.len(), 1);
+
+        let json: serde_json::Value = serde_json::from_str(&bundle.to_json()).unwrap();
+        for key in ["netting", "liquidity", "cycles", "components"] {
+            assert!(json.get(key).is_some(), "missing key {}", key);
+        }
+        assert_eq!(json["components"][0]["parties"], serde_json::json!(["A", "B", "C"]));
+    }
+}
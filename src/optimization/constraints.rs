@@ -0,0 +1,165 @@
+use crate::core::currency::CurrencyCode;
+use crate::core::obligation::ObligationSet;
+use crate::core::party::PartyId;
+use crate::optimization::netting::{NettingEngine, NettingResult};
+use crate::optimization::settlement::SettlementInstruction;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+/// Bilateral credit limits: the most a debtor may owe a creditor in a
+/// currency once netting is done.
+#[derive(Debug, Clone, Default)]
+pub struct ConstraintSet {
+    pub limits: HashMap<(PartyId, PartyId, CurrencyCode), Decimal>,
+}
+
+impl ConstraintSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cap what `debtor` may owe `creditor` in `currency` at `limit`.
+    pub fn with_limit(
+        mut self,
+        debtor: PartyId,
+        creditor: PartyId,
+        currency: CurrencyCode,
+        limit: Decimal,
+    ) -> Self {
+        self.limits.insert((debtor, creditor, currency), limit);
+        self
+    }
+
+    /// The limit on `debtor` owing `creditor` in `currency`, if any.
+    pub fn limit(
+        &self,
+        debtor: &PartyId,
+        creditor: &PartyId,
+        currency: &CurrencyCode,
+    ) -> Option<Decimal> {
+        self.limits
+            .get(&(debtor.clone(), creditor.clone(), currency.clone()))
+            .copied()
+    }
+}
+
+/// A netted exposure exceeding its bilateral credit limit.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConstraintViolation {
+    pub debtor: PartyId,
+    pub creditor: PartyId,
+    pub currency: CurrencyCode,
+    /// What the debtor would owe the creditor after netting.
+    pub proposed: Decimal,
+    pub limit: Decimal,
+}
+
+impl ConstraintViolation {
+    /// How far the proposed exposure is over the limit.
+    pub fn excess(&self) -> Decimal {
+        self.proposed - self.limit
+    }
+}
+
+/// A netting result checked against bilateral credit limits.
+///
+/// The result is returned whether or not limits are breached, so callers
+/// can decide whether to settle, renegotiate limits or fall back to gross.
+#[derive(Debug, Clone)]
+pub struct ConstrainedNettingResult {
+    pub result: NettingResult,
+    /// The transfers whose exposures were checked.
+    pub instructions: Vec<SettlementInstruction>,
+    /// Every breached limit, sorted by (debtor, creditor, currency).
+    pub violations: Vec<ConstraintViolation>,
+}
+
+impl ConstrainedNettingResult {
+    /// Returns true if every netted exposure is within its limit.
+    pub fn is_executable(&self) -> bool {
+        self.violations.is_empty()
+    }
+}
+
+impl NettingEngine {
+    /// Multilateral netting checked against bilateral credit limits.
+    ///
+    /// After netting, a debtor's exposure to a creditor is what its
+    /// settlement instructions (see
+    /// [`NettingResult::settlement_instructions`]) have it pay that
+    /// creditor. Each exposure above its limit is reported; pairs without
+    /// a limit are unconstrained.
+    pub fn multilateral_net_constrained(
+        obligations: &ObligationSet,
+        constraints: &ConstraintSet,
+    ) -> ConstrainedNettingResult {
+        let result = Self::multilateral_net(obligations);
+        let instructions = result.settlement_instructions();
+
+        let mut exposures: BTreeMap<(&PartyId, &PartyId, &CurrencyCode), Decimal> = BTreeMap::new();
+        for instruction in &instructions {
+            *exposures
+                .entry((&instruction.from, &instruction.to, &instruction.currency))
+                .or_insert(Decimal::ZERO) += instruction.amount;
+        }
+        let violations = exposures
+            .into_iter()
+            .filter_map(|((debtor, creditor, currency), proposed)| {
+                let limit = constraints.limit(debtor, creditor, currency)?;
+                (proposed > limit).then(|| ConstraintViolation {
+                    debtor: debtor.clone(),
+                    creditor: creditor.clone(),
+                    currency: currency.clone(),
+                    proposed,
+                    limit,
+                })
+            })
+            .collect();
+
+        ConstrainedNettingResult {
+            result,
+            instructions,
+            violations,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::obligation::Obligation;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_reports_only_breached_limits() {
+        let usd = CurrencyCode::new("USD");
+        let (a, b, c) = (PartyId::new("A"), PartyId::new("B"), PartyId::new("C"));
+        let mut set = ObligationSet::new();
+        set.add(Obligation::new(a.clone(), b.clone(), dec!(100), usd.clone()));
+        set.add(Obligation::new(b.clone(), c.clone(), dec!(60), usd.clone()));
+
+        // Netting leaves A paying C 60 and B 40
+        let constraints = ConstraintSet::new()
+            .with_limit(a.clone(), c.clone(), usd.clone(), dec!(50))
+            .with_limit(a.clone(), b.clone(), usd.clone(), dec!(50));
+        let constrained = NettingEngine::multilateral_net_constrained(&set, &constraints);
+
+        assert!(!constrained.is_executable());
+        assert_eq!(
+            constrained.violations,
+            vec![ConstraintViolation {
+                debtor: a.clone(),
+                creditor: c.clone(),
+                currency: usd.clone(),
+                proposed: dec!(60),
+                limit: dec!(50),
+            }]
+        );
+        assert_eq!(constrained.violations[0].excess(), dec!(10));
+        assert_eq!(constrained.result.net_position(&a, &usd), dec!(-100));
+
+        let unconstrained = NettingEngine::multilateral_net_constrained(&set, &ConstraintSet::new());
+        assert!(unconstrained.is_executable());
+    }
+}
@@ -0,0 +1,118 @@
+//! FX hedging requirements for single-currency settlement.
+//!
+//! When a multi-currency obligation set is netted and settled entirely in
+//! one currency (see [`NettingEngine::multilateral_net_with_fx`]), each
+//! party's foreign-currency positions are crystallized at the conversion
+//! rate. The gap between what a party was owed or owed in each currency
+//! and what it actually settles is the exposure it would need to hedge.
+//!
+//! [`NettingEngine::multilateral_net_with_fx`]: crate::optimization::netting::NettingEngine::multilateral_net_with_fx
+
+use crate::core::currency::{CurrencyCode, FxRateTable};
+use crate::core::party::PartyId;
+use crate::optimization::netting::{ConversionStep, NettingResult};
+use rust_decimal::Decimal;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+/// Each party's FX exposure from settling in one currency as `converted`.
+///
+/// `converted` is a result of `multilateral_net_with_fx`. Each obligation
+/// it converted is recovered from the result's conversion log and
+/// contributions: its original amount is the converted amount divided by
+/// the effective rate applied. For every party the exposure in a foreign
+/// currency is its original position there, and the settlement currency
+/// carries the offsetting leg, valued at `rates`. Pass the table used
+/// for netting to see the crystallized leg, or a later one to see it
+/// marked to market; a pair `rates` cannot price keeps the netting rate.
+/// Positive values mean the party is long the currency (it was owed it),
+/// negative values short.
+///
+/// Zero exposures are omitted, so a party trading only in the settlement
+/// currency does not appear. A deserialized result carries no obligation
+/// trace and reports no exposure.
+pub fn fx_hedge_requirement(
+    converted: &NettingResult,
+    rates: &FxRateTable,
+) -> HashMap<PartyId, HashMap<CurrencyCode, Decimal>> {
+    let steps: HashMap<Uuid, &ConversionStep> = converted
+        .conversion_log()
+        .iter()
+        .map(|step| (step.obligation_id, step))
+        .collect();
+
+    let mut exposures: HashMap<(&PartyId, &CurrencyCode), Decimal> = HashMap::new();
+    for (id, debtor, creditor, _, amount) in converted.contributions() {
+        let Some(step) = steps.get(id).filter(|step| step.from != step.to) else {
+            continue;
+        };
+        let original = *amount / step.rate;
+        let offset = rates
+            .conversion_path(&step.from, &step.to)
+            .map(|path| original * path.iter().map(|(_, _, rate)| rate).product::<Decimal>())
+            .unwrap_or(*amount);
+        *exposures.entry((debtor, &step.from)).or_insert(Decimal::ZERO) -= original;
+        *exposures.entry((debtor, &step.to)).or_insert(Decimal::ZERO) += offset;
+        *exposures.entry((creditor, &step.from)).or_insert(Decimal::ZERO) += original;
+        *exposures.entry((creditor, &step.to)).or_insert(Decimal::ZERO) -= offset;
+    }
+
+    let mut hedges: HashMap<PartyId, HashMap<CurrencyCode, Decimal>> = HashMap::new();
+    for ((party, currency), exposure) in exposures {
+        if exposure != Decimal::ZERO {
+            hedges
+                .entry(party.clone())
+                .or_default()
+                .insert(currency.clone(), exposure);
+        }
+    }
+    hedges
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::obligation::{Obligation, ObligationSet};
+    use crate::optimization::netting::NettingEngine;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_brl_obligation_needs_brl_hedge() {
+        let usd = CurrencyCode::new("USD");
+        let brl = CurrencyCode::new("BRL");
+        let a = PartyId::new("A");
+        let b = PartyId::new("B");
+        let c = PartyId::new("C");
+
+        let mut set = ObligationSet::new();
+        set.add(Obligation::new(a.clone(), b.clone(), dec!(5000), brl.clone()));
+        set.add(Obligation::new(b.clone(), a.clone(), dec!(300), usd.clone()));
+        set.add(Obligation::new(c.clone(), b.clone(), dec!(50), usd.clone()));
+
+        let mut rates = FxRateTable::new(usd.clone());
+        rates.set_rate(brl.clone(), usd.clone(), dec!(0.20)).unwrap();
+
+        let converted = NettingEngine::multilateral_net_with_fx(&set, &rates, &usd).unwrap();
+        let hedges = fx_hedge_requirement(&converted, &rates);
+
+        // A owed 5000 BRL but settles -700 USD: short BRL, long the 1000 USD it pays for it
+        assert_eq!(converted.net_position(&a, &usd), dec!(-700));
+        assert_eq!(hedges[&a][&brl], dec!(-5000));
+        assert_eq!(hedges[&a][&usd], dec!(1000));
+        assert_eq!(hedges[&b][&brl], dec!(5000));
+        assert_eq!(hedges[&b][&usd], dec!(-1000));
+        // C only ever dealt in USD
+        assert!(!hedges.contains_key(&c));
+
+        // At a later 0.25 the BRL A owed is worth 1250 USD
+        let mut later = FxRateTable::new(usd.clone());
+        later.set_rate(brl.clone(), usd.clone(), dec!(0.25)).unwrap();
+        let marked = fx_hedge_requirement(&converted, &later);
+        assert_eq!(marked[&a][&brl], dec!(-5000));
+        assert_eq!(marked[&a][&usd], dec!(1250));
+
+        // Without conversions there is nothing to hedge
+        let plain = NettingEngine::multilateral_net(&set);
+        assert!(fx_hedge_requirement(&plain, &rates).is_empty());
+    }
+}
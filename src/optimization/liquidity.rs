@@ -1,9 +1,11 @@
-use crate::core::currency::CurrencyCode;
+use crate::core::currency::{CurrencyCode, FxError, FxRateTable};
+use crate::core::obligation::ObligationSet;
 use crate::core::party::PartyId;
+use crate::graph::payment_graph::PaymentGraph;
 use crate::optimization::netting::NettingResult;
 use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 /// Analysis of liquidity requirements for settlement.
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -47,6 +49,174 @@ impl LiquidityAnalysis {
         }
     }
 
+    /// Reserve each net debtor must post, per currency.
+    ///
+    /// The reserve is the debtor's net debit (as in `debtor_requirements`)
+    /// scaled by `multiplier`; net creditors post nothing.
+    pub fn reserve_requirements(
+        &self,
+        multiplier: Decimal,
+    ) -> HashMap<PartyId, HashMap<CurrencyCode, Decimal>> {
+        self.debtor_requirements
+            .iter()
+            .map(|(party, requirements)| {
+                let reserves = requirements
+                    .iter()
+                    .map(|(currency, amount)| (currency.clone(), *amount * multiplier))
+                    .collect();
+                (party.clone(), reserves)
+            })
+            .collect()
+    }
+
+    /// Smallest top-up each net debtor needs to settle, per currency.
+    ///
+    /// The injection is the debtor's net debit less what `available`
+    /// says it already holds (nothing, if absent). Parties and currencies
+    /// that are fully funded are omitted, so an empty map means the batch
+    /// can settle as is.
+    pub fn minimum_injections(
+        &self,
+        available: &HashMap<PartyId, HashMap<CurrencyCode, Decimal>>,
+    ) -> HashMap<PartyId, HashMap<CurrencyCode, Decimal>> {
+        self.debtor_requirements
+            .iter()
+            .filter_map(|(party, requirements)| {
+                let held = available.get(party);
+                let injections: HashMap<CurrencyCode, Decimal> = requirements
+                    .iter()
+                    .filter_map(|(currency, required)| {
+                        let balance = held
+                            .and_then(|h| h.get(currency))
+                            .copied()
+                            .unwrap_or(Decimal::ZERO);
+                        let shortfall = *required - balance;
+                        (shortfall > Decimal::ZERO).then(|| (currency.clone(), shortfall))
+                    })
+                    .collect();
+                (!injections.is_empty()).then(|| (party.clone(), injections))
+            })
+            .collect()
+    }
+
+    /// Order in which parties should pay to minimize peak liquidity.
+    ///
+    /// Each party, on its turn, pays all of its outgoing edges in
+    /// `currency`, using whatever it has already received and injecting
+    /// external funds for the rest. The order is built greedily: parties
+    /// whose incoming payments have all arrived go first (a topological
+    /// order, which is optimal on an acyclic residual graph), picking the
+    /// one with the smallest funding gap; if none is ready (a cycle), the
+    /// smallest funding gap overall is chosen. Ties break by party id.
+    pub fn optimal_settlement_order(graph: &PaymentGraph, currency: &CurrencyCode) -> Vec<PartyId> {
+        let mut pending: Vec<PartyId> = graph.parties().iter().cloned().collect();
+        pending.sort();
+        let mut paid: HashSet<PartyId> = HashSet::new();
+        let mut received: HashMap<PartyId, Decimal> = HashMap::new();
+        let mut order = Vec::with_capacity(pending.len());
+
+        while !pending.is_empty() {
+            let candidate = |ready_only: bool| {
+                pending
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, party)| {
+                        !ready_only
+                            || graph
+                                .incoming(party, currency)
+                                .iter()
+                                .all(|(debtor, _)| paid.contains(*debtor))
+                    })
+                    .min_by_key(|(_, party)| {
+                        let outgoing: Decimal =
+                            graph.outgoing(party, currency).iter().map(|(_, a)| *a).sum();
+                        let balance = received.get(*party).copied().unwrap_or(Decimal::ZERO);
+                        (outgoing - balance).max(Decimal::ZERO)
+                    })
+                    .map(|(index, _)| index)
+            };
+            let index = candidate(true)
+                .or_else(|| candidate(false))
+                .expect("pending is non-empty");
+
+            let party = pending.remove(index);
+            for (creditor, amount) in graph.outgoing(&party, currency) {
+                *received.entry(creditor.clone()).or_insert(Decimal::ZERO) += amount;
+            }
+            paid.insert(party.clone());
+            order.push(party);
+        }
+        order
+    }
+
+    /// Peak liquidity needed to settle `currency` by paying in `order`.
+    ///
+    /// Simulates the sequence described in
+    /// [`LiquidityAnalysis::optimal_settlement_order`]. Funds are only ever
+    /// injected, never withdrawn, so the total external funding injected
+    /// is also the peak amount in flight. Parties missing from `order`
+    /// never pay.
+    pub fn peak_sequential_requirement(
+        graph: &PaymentGraph,
+        currency: &CurrencyCode,
+        order: &[PartyId],
+    ) -> Decimal {
+        let mut balance: HashMap<&PartyId, Decimal> = HashMap::new();
+        let mut injected = Decimal::ZERO;
+        for party in order {
+            let outgoing = graph.outgoing(party, currency);
+            let due: Decimal = outgoing.iter().map(|(_, a)| *a).sum();
+            let held = balance.get(party).copied().unwrap_or(Decimal::ZERO);
+            injected += (due - held).max(Decimal::ZERO);
+            balance.insert(party, (held - due).max(Decimal::ZERO));
+            for (creditor, amount) in outgoing {
+                *balance.entry(creditor).or_insert(Decimal::ZERO) += amount;
+            }
+        }
+        injected
+    }
+
+    /// Total liquidity required across all currencies, expressed in `base`.
+    ///
+    /// Each currency's total requirement is converted at the table's rate
+    /// and summed. Currencies are visited in code order, so a missing rate
+    /// always reports the same pair.
+    pub fn total_required_in_base(
+        &self,
+        rates: &FxRateTable,
+        base: &CurrencyCode,
+    ) -> Result<Decimal, FxError> {
+        let mut currencies: Vec<&CurrencyCode> = self.total_required.keys().collect();
+        currencies.sort();
+        let mut total = Decimal::ZERO;
+        for currency in currencies {
+            total += rates.convert(self.total_required[currency], currency, base)?;
+        }
+        Ok(total)
+    }
+
+    /// Liquidity savings ratio per currency.
+    ///
+    /// [`LiquidityAnalysis::savings_ratio`] adds amounts across currencies;
+    /// this compares each currency's gross (from `obligations`, which
+    /// should be the set this analysis was computed from) with its own net
+    /// requirement. Currencies with no gross flow are omitted.
+    pub fn savings_ratio_by_currency(&self, obligations: &ObligationSet) -> HashMap<CurrencyCode, f64> {
+        let mut gross: HashMap<&CurrencyCode, Decimal> = HashMap::new();
+        for ob in obligations.obligations() {
+            *gross.entry(ob.currency()).or_insert(Decimal::ZERO) += ob.amount();
+        }
+        gross
+            .into_iter()
+            .filter(|(_, gross)| *gross > Decimal::ZERO)
+            .map(|(currency, gross)| {
+                let net = self.total_required.get(currency).copied().unwrap_or(Decimal::ZERO);
+                let ratio = (gross - net) / gross;
+                (currency.clone(), ratio.to_string().parse::<f64>().unwrap_or(0.0))
+            })
+            .collect()
+    }
+
     /// Liquidity savings ratio.
     pub fn savings_ratio(&self) -> f64 {
         if self.gross_requirement == Decimal::ZERO {
@@ -82,7 +252,7 @@ impl std::fmt::Display for LiquidityAnalysis {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::core::obligation::{Obligation, ObligationSet};
+    use crate::core::obligation::Obligation;
     use crate::optimization::netting::NettingEngine;
     use rust_decimal_macros::dec;
 
@@ -135,4 +305,120 @@ mod tests {
         assert_eq!(analysis.net_requirement, Decimal::ZERO);
         assert!((analysis.savings_ratio() - 1.0).abs() < 0.001);
     }
+
+    #[test]
+    fn test_optimal_order_beats_reverse() {
+        let usd = CurrencyCode::new("USD");
+        let graph = PaymentGraph::from_obligations(vec![
+            Obligation::new(PartyId::new("A"), PartyId::new("B"), dec!(100), usd.clone()),
+            Obligation::new(PartyId::new("B"), PartyId::new("C"), dec!(80), usd.clone()),
+            Obligation::new(PartyId::new("C"), PartyId::new("D"), dec!(120), usd.clone()),
+        ]);
+
+        let order = LiquidityAnalysis::optimal_settlement_order(&graph, &usd);
+        let names: Vec<&str> = order.iter().map(|p| p.as_str()).collect();
+        assert_eq!(names, vec!["A", "B", "C", "D"]);
+
+        let reversed: Vec<PartyId> = order.iter().rev().cloned().collect();
+        let best = LiquidityAnalysis::peak_sequential_requirement(&graph, &usd, &order);
+        let worst = LiquidityAnalysis::peak_sequential_requirement(&graph, &usd, &reversed);
+
+        // Optimal: A funds 100, C tops up 40 — exactly the net debtor positions
+        assert_eq!(best, dec!(140));
+        assert_eq!(worst, dec!(300));
+        assert!(best < worst);
+    }
+
+    #[test]
+    fn test_total_required_in_base() {
+        let usd = CurrencyCode::new("USD");
+        let brl = CurrencyCode::new("BRL");
+        let mut set = ObligationSet::new();
+        set.add(Obligation::new(PartyId::new("A"), PartyId::new("B"), dec!(100), usd.clone()));
+        set.add(Obligation::new(PartyId::new("B"), PartyId::new("A"), dec!(500), brl.clone()));
+
+        let analysis = LiquidityAnalysis::from_netting_result(&NettingEngine::multilateral_net(&set));
+
+        let mut rates = FxRateTable::new(usd.clone());
+        assert!(matches!(
+            analysis.total_required_in_base(&rates, &usd),
+            Err(FxError::RateNotFound { .. })
+        ));
+
+        rates.set_rate(brl.clone(), usd.clone(), dec!(0.2)).unwrap();
+        // 100 USD + 500 BRL × 0.2
+        assert_eq!(analysis.total_required_in_base(&rates, &usd).unwrap(), dec!(200));
+    }
+
+    #[test]
+    fn test_base_currency_needs_no_rates() {
+        let usd = CurrencyCode::new("USD");
+        let mut set = ObligationSet::new();
+        set.add(Obligation::new(PartyId::new("A"), PartyId::new("B"), dec!(100), usd.clone()));
+        set.add(Obligation::new(PartyId::new("B"), PartyId::new("C"), dec!(60), usd.clone()));
+
+        let rates = FxRateTable::new(usd.clone());
+        let preview = set.convert_preview(&rates, &usd).unwrap();
+        assert!(preview.iter().all(|(_, original, converted)| original == converted));
+
+        let analysis = LiquidityAnalysis::from_netting_result(&NettingEngine::multilateral_net(&set));
+        assert_eq!(analysis.total_required_in_base(&rates, &usd).unwrap(), dec!(100));
+    }
+
+    #[test]
+    fn test_reserve_requirements() {
+        let usd = CurrencyCode::new("USD");
+        let brl = CurrencyCode::new("BRL");
+        let mut set = ObligationSet::new();
+        set.add(Obligation::new(PartyId::new("A"), PartyId::new("B"), dec!(100), usd.clone()));
+        set.add(Obligation::new(PartyId::new("B"), PartyId::new("A"), dec!(500), brl.clone()));
+
+        let analysis = LiquidityAnalysis::from_netting_result(&NettingEngine::multilateral_net(&set));
+        let reserves = analysis.reserve_requirements(dec!(1.2));
+
+        assert_eq!(reserves[&PartyId::new("A")][&usd], dec!(120));
+        assert_eq!(reserves[&PartyId::new("B")][&brl], dec!(600));
+        assert_eq!(reserves[&PartyId::new("A")].len(), 1);
+    }
+
+    #[test]
+    fn test_minimum_injections() {
+        let usd = CurrencyCode::new("USD");
+        let mut set = ObligationSet::new();
+        set.add(Obligation::new(PartyId::new("A"), PartyId::new("C"), dec!(100), usd.clone()));
+        set.add(Obligation::new(PartyId::new("B"), PartyId::new("C"), dec!(50), usd.clone()));
+
+        let analysis = LiquidityAnalysis::from_netting_result(&NettingEngine::multilateral_net(&set));
+        let available = HashMap::from([
+            (PartyId::new("A"), HashMap::from([(usd.clone(), dec!(90))])),
+            (PartyId::new("B"), HashMap::from([(usd.clone(), dec!(75))])),
+        ]);
+        let injections = analysis.minimum_injections(&available);
+
+        assert_eq!(injections.len(), 1);
+        assert_eq!(injections[&PartyId::new("A")], HashMap::from([(usd.clone(), dec!(10))]));
+
+        // With nothing on hand every debtor tops up its full net debit
+        let unfunded = analysis.minimum_injections(&HashMap::new());
+        assert_eq!(unfunded[&PartyId::new("B")][&usd], dec!(50));
+    }
+
+    #[test]
+    fn test_savings_ratio_by_currency() {
+        let usd = CurrencyCode::new("USD");
+        let brl = CurrencyCode::new("BRL");
+        let mut set = ObligationSet::new();
+        // USD: perfect cycle
+        set.add(Obligation::new(PartyId::new("A"), PartyId::new("B"), dec!(100), usd.clone()));
+        set.add(Obligation::new(PartyId::new("B"), PartyId::new("C"), dec!(100), usd.clone()));
+        set.add(Obligation::new(PartyId::new("C"), PartyId::new("A"), dec!(100), usd.clone()));
+        // BRL: one-way payment, nothing to net
+        set.add(Obligation::new(PartyId::new("A"), PartyId::new("B"), dec!(500), brl.clone()));
+
+        let analysis = LiquidityAnalysis::from_netting_result(&NettingEngine::multilateral_net(&set));
+        let ratios = analysis.savings_ratio_by_currency(&set);
+
+        assert!((ratios[&usd] - 1.0).abs() < 0.001);
+        assert!(ratios[&brl].abs() < 0.001);
+    }
 }
@@ -1,2 +1,7 @@
+pub mod analysis;
+pub mod constraints;
+pub mod hedging;
 pub mod liquidity;
 pub mod netting;
+pub mod settlement;
+pub mod statement;
@@ -1,10 +1,24 @@
-use crate::core::currency::CurrencyCode;
+use crate::core::amount::{round_to_minor_units, RoundingMode};
+use crate::core::currency::{Basket, CurrencyCode, FxError, FxRateTable};
 use crate::core::ledger::Ledger;
-use crate::core::obligation::ObligationSet;
+use crate::core::obligation::{Obligation, ObligationSet};
 use crate::core::party::PartyId;
+use crate::error::ClearingError;
+use crate::graph::cycle_detection::{
+    compress_currency_cycles, compress_cycles, compress_cycles_traced, PaymentCycle,
+};
+use crate::graph::payment_graph::PaymentGraph;
+use crate::graph::scc::find_sccs;
+use crate::optimization::settlement::{
+    cheapest_candidate_instructions, default_account, greedy_instructions, match_in_order,
+    min_count_instructions, split_positions, Infeasibility, SettlementInstruction,
+    SettlementOrdering, Shortfall, SubAccount,
+};
+use chrono::{DateTime, Duration, DurationRound, Utc};
 use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use uuid::Uuid;
 
 /// Result of a bilateral netting computation between two parties.
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -31,8 +45,29 @@ pub struct NettingResult {
     gross_total: Decimal,
     /// Net total after netting.
     net_total: Decimal,
-    /// Per-currency breakdown.
-    currency_breakdown: HashMap<CurrencyCode, CurrencyNettingResult>,
+    /// Per-currency breakdown, ordered by currency code.
+    currency_breakdown: BTreeMap<CurrencyCode, CurrencyNettingResult>,
+    /// Aggregated gross flows (debtor, creditor, currency, amount), sorted.
+    /// Like `contributions` and `accounts`, this traces the input and is
+    /// not serialized, so payloads grow with parties, not obligations.
+    #[serde(skip)]
+    flows: Vec<(PartyId, PartyId, CurrencyCode, Decimal)>,
+    /// Contributing obligations (id, debtor, creditor, currency, amount),
+    /// in input order.
+    #[serde(skip)]
+    contributions: Vec<(Uuid, PartyId, PartyId, CurrencyCode, Decimal)>,
+    /// Settlement account named for each (party, currency), sorted. The
+    /// first account an obligation names for the party wins.
+    #[serde(skip)]
+    accounts: Vec<(PartyId, CurrencyCode, String)>,
+    /// Portion of `gross_total` carried forward from a prior cycle's
+    /// residual positions (zero unless netted with a carryforward).
+    #[serde(default)]
+    carried_forward_total: Decimal,
+    /// How each obligation was converted into the settlement currency
+    /// (empty unless netted with FX normalization), in input order.
+    #[serde(default)]
+    conversion_log: Vec<ConversionStep>,
 }
 
 impl NettingResult {
@@ -46,6 +81,21 @@ impl NettingResult {
         self.net_total
     }
 
+    /// Portion of the gross total carried forward from a prior residual.
+    pub fn carried_forward_total(&self) -> Decimal {
+        self.carried_forward_total
+    }
+
+    /// How each obligation was converted into the settlement currency.
+    pub fn conversion_log(&self) -> &[ConversionStep] {
+        &self.conversion_log
+    }
+
+    /// Portion of the gross total arising from this cycle's obligations.
+    pub fn new_obligations_total(&self) -> Decimal {
+        self.gross_total - self.carried_forward_total
+    }
+
     /// Absolute liquidity saved.
     pub fn savings(&self) -> Decimal {
         self.gross_total - self.net_total
@@ -62,6 +112,14 @@ impl NettingResult {
         pct.to_string().parse::<f64>().unwrap_or(0.0)
     }
 
+    /// Returns true if netting saves at least `min_savings_percent` of gross.
+    ///
+    /// Settlement runs carry operational cost; below the threshold a run
+    /// is not worth executing.
+    pub fn is_worthwhile(&self, min_savings_percent: f64) -> bool {
+        self.savings_percent() >= min_savings_percent
+    }
+
     /// The resulting ledger with net positions.
     pub fn ledger(&self) -> &Ledger {
         &self.ledger
@@ -73,7 +131,7 @@ impl NettingResult {
     }
 
     /// Per-currency breakdown of netting results.
-    pub fn currency_breakdown(&self) -> &HashMap<CurrencyCode, CurrencyNettingResult> {
+    pub fn currency_breakdown(&self) -> &BTreeMap<CurrencyCode, CurrencyNettingResult> {
         &self.currency_breakdown
     }
 
@@ -81,6 +139,707 @@ impl NettingResult {
     pub fn is_valid(&self) -> bool {
         self.ledger.is_balanced()
     }
+
+    /// Largest per-currency imbalance in the ledger, as an absolute amount.
+    ///
+    /// Every obligation credits and debits the same amount, so this is zero
+    /// for same-currency netting. On the converted path, summing amounts
+    /// carried to 28 significant digits can drift; it stays within
+    /// [`MAX_ROUNDING_RESIDUAL`]. A haircut result (see
+    /// [`NettingResult::apply_haircut`]) is unbalanced by design.
+    pub fn max_rounding_residual(&self) -> Decimal {
+        let mut sums: HashMap<&CurrencyCode, Decimal> = HashMap::new();
+        for ((_, currency), amount) in self.ledger.all_positions() {
+            *sums.entry(currency).or_insert(Decimal::ZERO) += amount;
+        }
+        sums.into_values()
+            .map(|sum| sum.abs())
+            .max()
+            .unwrap_or(Decimal::ZERO)
+    }
+
+    /// Compare two results within `epsilon`.
+    ///
+    /// Gross total, net total and every ledger position must each differ by
+    /// at most `epsilon`; a position present in only one ledger is compared
+    /// against zero. Useful for asserting on converted or rounded results.
+    pub fn approx_eq(&self, other: &NettingResult, epsilon: Decimal) -> bool {
+        let close = |a: Decimal, b: Decimal| (a - b).abs() <= epsilon;
+        close(self.gross_total, other.gross_total)
+            && close(self.net_total, other.net_total)
+            && self
+                .ledger
+                .all_positions()
+                .keys()
+                .chain(other.ledger.all_positions().keys())
+                .all(|(party, currency)| {
+                    close(
+                        self.ledger.position(party, currency),
+                        other.ledger.position(party, currency),
+                    )
+                })
+    }
+
+    /// Withhold a haircut of `bps` basis points from every creditor position.
+    ///
+    /// Debtor positions are left intact, so the returned result's ledger is
+    /// intentionally unbalanced by exactly the withheld buffer (and
+    /// [`NettingResult::is_valid`] reports `false` whenever anything was
+    /// withheld). Returns the adjusted result together with the total
+    /// withheld per currency.
+    pub fn apply_haircut(&self, bps: u32) -> (NettingResult, HashMap<CurrencyCode, Decimal>) {
+        let fraction = Decimal::from(bps) / Decimal::from(10_000);
+        let mut ledger = self.ledger.clone();
+        let mut withheld: HashMap<CurrencyCode, Decimal> = HashMap::new();
+
+        for ((party, currency), amount) in self.ledger.all_positions() {
+            if *amount > Decimal::ZERO {
+                let haircut = *amount * fraction;
+                ledger.adjust_position(party, currency, -haircut);
+                *withheld.entry(currency.clone()).or_insert(Decimal::ZERO) += haircut;
+            }
+        }
+
+        let currency_net = ledger.net_settlement_by_currency();
+        let mut currency_breakdown = self.currency_breakdown.clone();
+        for breakdown in currency_breakdown.values_mut() {
+            breakdown.net_total = currency_net
+                .get(&breakdown.currency)
+                .copied()
+                .unwrap_or(Decimal::ZERO);
+        }
+
+        let result = NettingResult {
+            net_total: ledger.total_net_settlement(),
+            ledger,
+            gross_total: self.gross_total,
+            currency_breakdown,
+            flows: self.flows.clone(),
+            contributions: self.contributions.clone(),
+            accounts: self.accounts.clone(),
+            carried_forward_total: self.carried_forward_total,
+            conversion_log: self.conversion_log.clone(),
+        };
+        (result, withheld)
+    }
+
+    /// Export as CSV, mirroring the CLI's JSON output fields.
+    ///
+    /// The first block is a one-row summary (`gross_total, net_total,
+    /// savings, savings_percent, valid`); after a blank line follows one
+    /// row per non-zero position (`party, currency, net_position, status`),
+    /// sorted by party then currency.
+    pub fn to_csv(&self) -> String {
+        let mut out = String::from("gross_total,net_total,savings,savings_percent,valid\n");
+        out.push_str(&format!(
+            "{},{},{},{},{}\n",
+            self.gross_total,
+            self.net_total,
+            self.savings(),
+            self.savings_percent(),
+            self.is_valid()
+        ));
+
+        out.push_str("\nparty,currency,net_position,status\n");
+        let mut positions: Vec<_> = self
+            .ledger
+            .all_positions()
+            .iter()
+            .filter(|(_, amount)| **amount != Decimal::ZERO)
+            .collect();
+        positions.sort_by(|a, b| a.0.cmp(b.0));
+        for ((party, currency), amount) in positions {
+            let status = if *amount > Decimal::ZERO {
+                "CREDITOR"
+            } else {
+                "DEBTOR"
+            };
+            out.push_str(&format!(
+                "{},{},{},{}\n",
+                csv_field(party.as_str()),
+                csv_field(currency.as_str()),
+                amount,
+                status
+            ));
+        }
+        out
+    }
+
+    /// Serialize to MessagePack, a compact binary alternative to JSON.
+    ///
+    /// Fields are encoded by name, so results written by older versions
+    /// still load after fields are added.
+    #[cfg(feature = "msgpack")]
+    pub fn to_msgpack(&self) -> Vec<u8> {
+        rmp_serde::to_vec_named(self).expect("netting results always serialize")
+    }
+
+    /// Deserialize a result written by [`NettingResult::to_msgpack`].
+    #[cfg(feature = "msgpack")]
+    pub fn from_msgpack(bytes: &[u8]) -> Result<NettingResult, rmp_serde::decode::Error> {
+        rmp_serde::from_slice(bytes)
+    }
+
+    /// Contributing obligations as (id, debtor, creditor, currency, amount),
+    /// in input order, with amounts as netted.
+    pub(crate) fn contributions(&self) -> &[(Uuid, PartyId, PartyId, CurrencyCode, Decimal)] {
+        &self.contributions
+    }
+
+    /// Aggregated gross flows that were netted, as (debtor, creditor, currency, amount).
+    pub fn flows(&self) -> &[(PartyId, PartyId, CurrencyCode, Decimal)] {
+        &self.flows
+    }
+
+    /// The aggregated flows keyed by (debtor, creditor, currency).
+    fn flow_edges(&self) -> HashMap<(PartyId, PartyId, CurrencyCode), Decimal> {
+        self.flows
+            .iter()
+            .map(|(d, c, cur, amt)| ((d.clone(), c.clone(), cur.clone()), *amt))
+            .collect()
+    }
+
+    /// Flows that no netting can offset.
+    ///
+    /// A flow is purely gross when it lies on no cycle: there is no
+    /// reverse obligation and no chain of obligations leading from the
+    /// creditor back to the debtor in the same currency. Equivalently,
+    /// its endpoints sit in different strongly connected components.
+    /// These flows must always be funded in full.
+    pub fn purely_gross_flows(&self) -> Vec<(PartyId, PartyId, CurrencyCode, Decimal)> {
+        let edges = self.flow_edges();
+        let graph = PaymentGraph::from_edge_map(&edges, &HashSet::new());
+
+        let mut component: HashMap<(PartyId, CurrencyCode), usize> = HashMap::new();
+        for currency in graph.currencies() {
+            for (index, scc) in find_sccs(&graph, currency).into_iter().enumerate() {
+                for party in scc.parties {
+                    component.insert((party, currency.clone()), index);
+                }
+            }
+        }
+
+        self.flows
+            .iter()
+            .filter(|(debtor, creditor, currency, _)| {
+                component.get(&(debtor.clone(), currency.clone()))
+                    != component.get(&(creditor.clone(), currency.clone()))
+            })
+            .cloned()
+            .collect()
+    }
+
+    /// Estimated date by which `party`'s net position in `currency` resolves.
+    ///
+    /// The net position only settles once every obligation feeding it has
+    /// come due, so this is the latest settlement date among the
+    /// obligations in `obligations` where `party` is debtor or creditor in
+    /// `currency`. Returns `None` when none of them carries a date.
+    pub fn expected_settlement_date(
+        &self,
+        party: &PartyId,
+        currency: &CurrencyCode,
+        obligations: &ObligationSet,
+    ) -> Option<DateTime<Utc>> {
+        obligations
+            .obligations()
+            .iter()
+            .filter(|ob| {
+                ob.currency() == currency && (ob.debtor() == party || ob.creditor() == party)
+            })
+            .filter_map(|ob| ob.settlement_date())
+            .max()
+    }
+
+    /// Net debit exposure per currency, weighted by each debtor's risk.
+    ///
+    /// Each net debtor's position (as a positive amount) is multiplied by
+    /// its weight in `risk` and summed per currency. Parties without a
+    /// score count at weight one rather than vanishing from the figure.
+    pub fn risk_weighted_exposure(
+        &self,
+        risk: &HashMap<PartyId, Decimal>,
+    ) -> HashMap<CurrencyCode, Decimal> {
+        let mut exposure: HashMap<CurrencyCode, Decimal> = HashMap::new();
+        for ((party, currency), amount) in self.ledger.all_positions() {
+            if *amount < Decimal::ZERO {
+                let weight = risk.get(party).copied().unwrap_or(Decimal::ONE);
+                *exposure.entry(currency.clone()).or_insert(Decimal::ZERO) += -*amount * weight;
+            }
+        }
+        exposure
+    }
+
+    /// Total of `cost(party, currency, position)` over every net position.
+    pub fn cost(&self, cost: impl Fn(&PartyId, &CurrencyCode, Decimal) -> Decimal) -> Decimal {
+        self.ledger
+            .all_positions()
+            .iter()
+            .map(|((party, currency), amount)| cost(party, currency, *amount))
+            .sum()
+    }
+
+    /// Parties in this result with no activity in `currency`.
+    ///
+    /// A party that only transacts in other currencies has no position
+    /// here at all, which is easy to confuse with a fully netted one in
+    /// per-currency reports. Sorted by party id.
+    pub fn orphan_parties(&self, currency: &CurrencyCode) -> Vec<PartyId> {
+        let active: HashSet<&PartyId> = self
+            .ledger
+            .all_positions()
+            .keys()
+            .filter(|(_, c)| c == currency)
+            .map(|(party, _)| party)
+            .collect();
+        let mut orphans: Vec<PartyId> = self
+            .ledger
+            .all_positions()
+            .keys()
+            .map(|(party, _)| party)
+            .filter(|party| !active.contains(party))
+            .cloned()
+            .collect();
+        orphans.dedup();
+        orphans
+    }
+
+    /// Every (party, currency) position as a separate settlement account.
+    ///
+    /// For systems that settle per account rather than per party: a party
+    /// active in several currencies yields one sub-account per currency.
+    /// Sorted by party, then currency; flat positions are included.
+    pub fn as_sub_accounts(&self) -> Vec<SubAccount> {
+        let mut accounts: Vec<SubAccount> = self
+            .ledger
+            .all_positions()
+            .iter()
+            .map(|((party, currency), &net_position)| SubAccount {
+                party: party.clone(),
+                currency: currency.clone(),
+                net_position,
+            })
+            .collect();
+        accounts.sort_by(|a, b| (&a.party, &a.currency).cmp(&(&b.party, &b.currency)));
+        accounts
+    }
+
+    /// The `n` largest individual contributors to savings, largest first.
+    ///
+    /// Mutual pairs are offset first (each saving twice the smaller
+    /// direction), then the remaining cycles are compressed (each saving
+    /// its compressed amount times its length). Savings from re-routing
+    /// acyclic chains belong to no single pair or cycle and are not listed,
+    /// so the sources may sum to less than [`NettingResult::savings`].
+    pub fn top_savings_sources(&self, n: usize) -> Vec<SavingsSource> {
+        let mut sources = Vec::new();
+        let edges = self.flow_edges();
+        let mut netted: HashMap<(PartyId, PartyId, CurrencyCode), Decimal> = HashMap::new();
+        for (debtor, creditor, currency, amount) in &self.flows {
+            let reverse = edges
+                .get(&(creditor.clone(), debtor.clone(), currency.clone()))
+                .copied()
+                .unwrap_or(Decimal::ZERO);
+            if reverse > Decimal::ZERO && debtor < creditor {
+                sources.push(SavingsSource::BilateralPair {
+                    parties: (debtor.clone(), creditor.clone()),
+                    currency: currency.clone(),
+                    saved: (*amount).min(reverse) * Decimal::TWO,
+                });
+            }
+            if *amount > reverse {
+                netted.insert((debtor.clone(), creditor.clone(), currency.clone()), amount - reverse);
+            }
+        }
+
+        let graph = PaymentGraph::from_edge_map(&netted, &HashSet::new());
+        let (_, compressions) = compress_cycles_traced(&graph);
+        let mut cycles: BTreeMap<String, (PaymentCycle, Decimal)> = BTreeMap::new();
+        for cycle in compressions {
+            let saved = cycle.potential_savings();
+            cycles
+                .entry(cycle.canonical_id())
+                .or_insert_with(|| (cycle, Decimal::ZERO))
+                .1 += saved;
+        }
+        for (cycle, saved) in cycles.into_values() {
+            let mut parties = cycle.parties;
+            if let Some(start) = parties.iter().enumerate().min_by_key(|(_, p)| *p).map(|(i, _)| i) {
+                parties.rotate_left(start);
+            }
+            sources.push(SavingsSource::Cycle {
+                parties,
+                currency: cycle.currency,
+                saved,
+            });
+        }
+
+        // Stable: ties keep pairs (in flow order) ahead of cycles (by id)
+        sources.sort_by_key(|source| std::cmp::Reverse(source.saved()));
+        sources.truncate(n);
+        sources
+    }
+
+    /// Split savings into bilateral offset and multilateral netting.
+    ///
+    /// Per currency, the bilateral portion is gross minus the value left
+    /// after netting every pair against its reverse flow; the multilateral
+    /// portion is what full netting saves beyond that. The two portions
+    /// sum to the currency's total savings.
+    pub fn savings_decomposition(&self) -> SavingsDecomposition {
+        let mut pair_flows: HashMap<(&PartyId, &PartyId, &CurrencyCode), Decimal> = HashMap::new();
+        for (debtor, creditor, currency, amount) in &self.flows {
+            // Orient each pair consistently so reverse flows offset
+            let (key, signed) = if debtor < creditor {
+                ((debtor, creditor, currency), *amount)
+            } else {
+                ((creditor, debtor, currency), -*amount)
+            };
+            *pair_flows.entry(key).or_insert(Decimal::ZERO) += signed;
+        }
+        let mut bilateral_net: HashMap<&CurrencyCode, Decimal> = HashMap::new();
+        for ((_, _, currency), net) in pair_flows {
+            *bilateral_net.entry(currency).or_insert(Decimal::ZERO) += net.abs();
+        }
+
+        let mut decomposition = SavingsDecomposition::default();
+        for (currency, breakdown) in &self.currency_breakdown {
+            let after_bilateral = bilateral_net.get(currency).copied().unwrap_or(Decimal::ZERO);
+            decomposition
+                .bilateral
+                .insert(currency.clone(), breakdown.gross_total - after_bilateral);
+            decomposition
+                .multilateral
+                .insert(currency.clone(), after_bilateral - breakdown.net_total);
+        }
+        decomposition
+    }
+
+    /// Obligations whose whole value is offset by netting.
+    ///
+    /// The aggregated flows are traced through [`compress_cycles`]: the
+    /// amount compressed out of each edge is allocated to that edge's
+    /// obligations in input order, and an obligation is reported when its
+    /// full amount was absorbed. An obligation larger than what is left on
+    /// its edge is passed over, so the rest can still go to later ones.
+    /// Obligations on purely gross or residual flows are not reported.
+    /// Ids are returned in input order. Empty on a deserialized result,
+    /// which carries no obligation trace.
+    pub fn fully_netted_obligation_ids(&self) -> Vec<Uuid> {
+        let edges = self.flow_edges();
+        let residual = compress_cycles(&PaymentGraph::from_edge_map(&edges, &HashSet::new()));
+        let mut absorbed: HashMap<(PartyId, PartyId, CurrencyCode), Decimal> = edges
+            .into_iter()
+            .map(|((d, c, cur), amt)| {
+                let remaining = residual.edge_amount(&d, &c, &cur);
+                ((d, c, cur), amt - remaining)
+            })
+            .collect();
+
+        self.contributions
+            .iter()
+            .filter_map(|(id, debtor, creditor, currency, amount)| {
+                let left = absorbed.get_mut(&(debtor.clone(), creditor.clone(), currency.clone()))?;
+                (*left >= *amount).then(|| {
+                    *left -= *amount;
+                    *id
+                })
+            })
+            .collect()
+    }
+
+    /// Concrete transfers that settle every net position.
+    ///
+    /// Net debtors are greedily matched against net creditors per
+    /// currency, largest first. The instruction total per currency equals
+    /// that currency's net total.
+    pub fn settlement_instructions(&self) -> Vec<SettlementInstruction> {
+        self.settlement_instructions_with(SettlementOrdering::ByAmountDesc)
+    }
+
+    /// Settlement instructions with amounts rounded to `decimals` minor
+    /// units using `mode`.
+    ///
+    /// Each instruction is rounded independently, so per-currency totals
+    /// may differ from the net total by up to half a minor unit per
+    /// instruction. Instructions that round to zero are dropped.
+    pub fn settlement_instructions_rounded(
+        &self,
+        decimals: u32,
+        mode: RoundingMode,
+    ) -> Vec<SettlementInstruction> {
+        self.settlement_instructions()
+            .into_iter()
+            .map(|instruction| SettlementInstruction {
+                amount: round_to_minor_units(instruction.amount, decimals, mode),
+                ..instruction
+            })
+            .filter(|instruction| instruction.amount > Decimal::ZERO)
+            .collect()
+    }
+
+    /// Concrete transfers that settle every net position, matching debtors
+    /// and creditors in the order given by `ordering`.
+    ///
+    /// Every ordering settles the same positions with the same per-currency
+    /// total; only the pairing of payers and payees differs. Instructions
+    /// carry the accounts the obligations named for each party in that
+    /// currency, falling back to
+    /// [`default_account`].
+    pub fn settlement_instructions_with(
+        &self,
+        ordering: SettlementOrdering,
+    ) -> Vec<SettlementInstruction> {
+        self.with_accounts(greedy_instructions(&self.ledger, ordering))
+    }
+
+    /// Settlement instructions using as few transfers as practical.
+    ///
+    /// Uses a subset-sum heuristic that settles exact-match groups of
+    /// parties before greedy matching (see
+    /// [`NettingResult::settlement_instructions`]); never more transfers
+    /// than the greedy version, and at most `n - 1` per currency for `n`
+    /// parties with non-zero positions. Accounts are filled in as for
+    /// [`NettingResult::settlement_instructions_with`].
+    pub fn settlement_instructions_min_count(&self) -> Vec<SettlementInstruction> {
+        self.with_accounts(min_count_instructions(&self.ledger))
+    }
+
+    /// Settlement instructions picked by cost from a few candidate matchings.
+    ///
+    /// `cost` prices one side of a transfer as `(party, currency, amount)`,
+    /// with the payer's amount negative and the payee's positive. Per
+    /// currency this prices the greedy matching of
+    /// [`NettingResult::settlement_instructions`], the exact-group matching
+    /// of [`NettingResult::settlement_instructions_min_count`], and a
+    /// greedy matching that serves the costliest parties first, and keeps
+    /// the cheapest; with per-transfer fees, the split of a large position
+    /// tends to land on a cheaper party. This is a heuristic, not a
+    /// minimizer: with a fixed part per transfer, finding the cheapest
+    /// settlement is a fixed-charge transportation problem. A cost linear
+    /// in the amount prices every matching the same, so the greedy
+    /// instructions are returned. Accounts are filled in as for
+    /// [`NettingResult::settlement_instructions_with`].
+    pub fn settlement_instructions_by_cost(
+        &self,
+        cost: impl Fn(&PartyId, &CurrencyCode, Decimal) -> Decimal,
+    ) -> Vec<SettlementInstruction> {
+        self.with_accounts(cheapest_candidate_instructions(&self.ledger, cost))
+    }
+
+    /// Fill in the accounts named for each payer and payee.
+    fn with_accounts(
+        &self,
+        mut instructions: Vec<SettlementInstruction>,
+    ) -> Vec<SettlementInstruction> {
+        for instruction in &mut instructions {
+            if let Some(account) = self.account(&instruction.from, &instruction.currency) {
+                instruction.from_account = account.to_string();
+            }
+            if let Some(account) = self.account(&instruction.to, &instruction.currency) {
+                instruction.to_account = account.to_string();
+            }
+        }
+        instructions
+    }
+
+    /// Settlement instructions limited to `max_transfers` transfers.
+    ///
+    /// The full instruction set (see
+    /// [`NettingResult::settlement_instructions`]) is ranked by amount,
+    /// largest first, and the top `max_transfers` are returned. Every
+    /// remaining transfer is deferred as an obligation from payer to payee,
+    /// so the returned instructions plus the deferred set still settle
+    /// every net position.
+    pub fn settlement_instructions_capped(
+        &self,
+        max_transfers: usize,
+    ) -> (Vec<SettlementInstruction>, ObligationSet) {
+        let mut instructions = self.settlement_instructions();
+        instructions.sort_by_key(|i| std::cmp::Reverse(i.amount));
+        let rest = instructions.split_off(max_transfers.min(instructions.len()));
+        let deferred = rest
+            .into_iter()
+            .map(|i| Obligation::new(i.from, i.to, i.amount, i.currency))
+            .collect();
+        (instructions, deferred)
+    }
+
+    /// Settlement instructions that pay higher-priority creditors first when
+    /// liquidity is short.
+    ///
+    /// `available` holds each party's funds per currency (as in
+    /// [`NettingEngine::net_atomic`]); a net debtor pays at most what it
+    /// holds. Creditors are served in descending `creditor_priority`
+    /// (absent = 0), then largest claim first, so lower-priority creditors
+    /// absorb any shortfall. Whatever a creditor is not paid is left
+    /// outstanding rather than instructed.
+    pub fn settlement_instructions_prioritized(
+        &self,
+        creditor_priority: &HashMap<PartyId, u8>,
+        available: &Ledger,
+    ) -> Vec<SettlementInstruction> {
+        let priority = |party: &PartyId| creditor_priority.get(party).copied().unwrap_or(0);
+        let mut instructions = Vec::new();
+        for (currency, (debtors, mut creditors)) in
+            split_positions(&self.ledger, SettlementOrdering::ByAmountDesc)
+        {
+            let funded = debtors
+                .into_iter()
+                .map(|(party, owed)| {
+                    let funds = available.position(&party, &currency).max(Decimal::ZERO);
+                    (party, owed.min(funds))
+                })
+                .filter(|(_, amount)| *amount > Decimal::ZERO)
+                .collect();
+            // Stable sort keeps largest-claim-first within each priority
+            creditors.sort_by_key(|(party, _)| std::cmp::Reverse(priority(party)));
+            match_in_order(&currency, funded, creditors, &mut instructions);
+        }
+        self.with_accounts(instructions)
+    }
+
+    /// Gross settlement: one transfer per original obligation, unnetted.
+    ///
+    /// Sits beside [`NettingResult::settlement_instructions`] to show how
+    /// many transfers netting removes. Accounts come from each obligation,
+    /// then from this result, then [`default_account`].
+    pub fn gross_instructions(&self, obligations: &ObligationSet) -> Vec<SettlementInstruction> {
+        obligations
+            .obligations()
+            .iter()
+            .map(|ob| {
+                let account = |party: &PartyId, named: Option<&str>| {
+                    named
+                        .or_else(|| self.account(party, ob.currency()))
+                        .map(str::to_string)
+                        .unwrap_or_else(|| default_account(party))
+                };
+                SettlementInstruction {
+                    from: ob.debtor().clone(),
+                    to: ob.creditor().clone(),
+                    amount: ob.amount(),
+                    currency: ob.currency().clone(),
+                    from_account: account(ob.debtor(), ob.debtor_account()),
+                    to_account: account(ob.creditor(), ob.creditor_account()),
+                }
+            })
+            .collect()
+    }
+
+    /// Totals and per-currency breakdown, without the ledger.
+    pub fn summary(&self) -> NettingSummary {
+        let parties: HashSet<&PartyId> =
+            self.ledger.all_positions().keys().map(|(party, _)| party).collect();
+        NettingSummary {
+            gross_total: self.gross_total,
+            net_total: self.net_total,
+            savings: self.savings(),
+            savings_percent: self.savings_percent(),
+            currency_breakdown: self.currency_breakdown.clone(),
+            party_count: parties.len(),
+            obligation_count: self.contributions.len(),
+        }
+    }
+
+    /// Settlement account named for `party` in `currency`, if any.
+    pub fn account(&self, party: &PartyId, currency: &CurrencyCode) -> Option<&str> {
+        self.accounts
+            .iter()
+            .find(|(p, c, _)| p == party && c == currency)
+            .map(|(_, _, account)| account.as_str())
+    }
+}
+
+/// Quote a CSV field if it contains a delimiter, quote or line break.
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// A single contributor to netting savings, see
+/// [`NettingResult::top_savings_sources`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum SavingsSource {
+    /// Mutual obligations between two parties offset each other.
+    BilateralPair {
+        parties: (PartyId, PartyId),
+        currency: CurrencyCode,
+        saved: Decimal,
+    },
+    /// A payment cycle compressed out of the network.
+    Cycle {
+        /// Parties in cycle order, starting from the smallest id.
+        parties: Vec<PartyId>,
+        currency: CurrencyCode,
+        saved: Decimal,
+    },
+}
+
+impl SavingsSource {
+    /// Gross value removed by this source.
+    pub fn saved(&self) -> Decimal {
+        match self {
+            SavingsSource::BilateralPair { saved, .. } | SavingsSource::Cycle { saved, .. } => {
+                *saved
+            }
+        }
+    }
+}
+
+/// Totals of a netting run without per-party positions.
+///
+/// A compact payload for status reporting; the full result serializes
+/// the whole ledger, which grows with the network.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NettingSummary {
+    pub gross_total: Decimal,
+    pub net_total: Decimal,
+    pub savings: Decimal,
+    pub savings_percent: f64,
+    /// Per-currency breakdown, ordered by currency code.
+    pub currency_breakdown: BTreeMap<CurrencyCode, CurrencyNettingResult>,
+    /// Distinct parties holding a position.
+    pub party_count: usize,
+    /// Obligations netted.
+    pub obligation_count: usize,
+}
+
+/// Where the savings of a netting run come from, per currency.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SavingsDecomposition {
+    /// Savings from offsetting mutual obligations within each pair.
+    pub bilateral: HashMap<CurrencyCode, Decimal>,
+    /// Additional savings from multilateral (cycle) netting on top of
+    /// bilateral offset.
+    pub multilateral: HashMap<CurrencyCode, Decimal>,
+}
+
+impl SavingsDecomposition {
+    /// Bilateral-offset savings across all currencies.
+    pub fn bilateral_total(&self) -> Decimal {
+        self.bilateral.values().sum()
+    }
+
+    /// Multilateral savings across all currencies.
+    pub fn multilateral_total(&self) -> Decimal {
+        self.multilateral.values().sum()
+    }
+}
+
+/// Conversion of one obligation into the settlement currency.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ConversionStep {
+    pub obligation_id: Uuid,
+    pub from: CurrencyCode,
+    pub to: CurrencyCode,
+    /// Effective rate applied: the product of the leg rates.
+    pub rate: Decimal,
+    /// Legs (from, to, rate) in order; two when triangulated via base.
+    pub path: Vec<(CurrencyCode, CurrencyCode, Decimal)>,
 }
 
 /// Netting result for a single currency.
@@ -106,6 +865,17 @@ impl CurrencyNettingResult {
     }
 }
 
+/// Largest per-currency ledger imbalance the converted netting path may
+/// leave (see [`NettingResult::max_rounding_residual`]): one minor unit.
+pub const MAX_ROUNDING_RESIDUAL: Decimal = Decimal::from_parts(1, 0, 0, false, 2);
+
+/// Obligations processed between progress callbacks in
+/// [`NettingEngine::multilateral_net_with_progress`].
+pub const PROGRESS_INTERVAL: usize = 10_000;
+
+/// Batch that obligations without a `batch_id` are netted in.
+pub const DEFAULT_BATCH: &str = "default";
+
 /// The core netting engine.
 ///
 /// Provides algorithms for bilateral and multilateral netting
@@ -168,16 +938,181 @@ impl NettingEngine {
     ///
     /// The ledger is guaranteed to be balanced: sum of all positions = 0.
     pub fn multilateral_net(obligations: &ObligationSet) -> NettingResult {
-        let mut ledger = Ledger::new();
-        let mut gross_total = Decimal::ZERO;
+        Self::net_seeded(obligations, &Ledger::new(), &mut |_, _| {})
+    }
 
-        // Per-currency tracking
-        let mut currency_gross: HashMap<CurrencyCode, Decimal> = HashMap::new();
-        let mut currency_parties: HashMap<CurrencyCode, HashMap<PartyId, bool>> = HashMap::new();
+    /// Multilateral netting that reports progress on long runs.
+    ///
+    /// `progress` is called with (processed, total) obligations every
+    /// [`PROGRESS_INTERVAL`] obligations and once more when all have been
+    /// processed, so the last call always reports `(total, total)`. The
+    /// result is identical to [`NettingEngine::multilateral_net`].
+    pub fn multilateral_net_with_progress(
+        obligations: &ObligationSet,
+        mut progress: impl FnMut(usize, usize),
+    ) -> NettingResult {
+        Self::net_seeded(obligations, &Ledger::new(), &mut progress)
+    }
+
+    /// Multilateral netting seeded with a prior cycle's residual.
+    ///
+    /// In continuous clearing, positions left unsettled by the previous
+    /// cycle carry into today: `residual` is applied to the ledger before
+    /// today's obligations. The residual's net settlement is counted in
+    /// the gross total and reported separately as
+    /// [`NettingResult::carried_forward_total`], so
+    /// [`NettingResult::new_obligations_total`] is today's gross alone.
+    pub fn net_with_carryforward(today: &ObligationSet, residual: &Ledger) -> NettingResult {
+        Self::net_seeded(today, residual, &mut |_, _| {})
+    }
+
+    /// Multilateral netting of each connected component, merged.
+    ///
+    /// Obligations are split into components of parties linked by any
+    /// obligation in any currency; components share no parties, so each
+    /// can be netted on its own and the results merged. With the
+    /// `parallel` feature the components are netted on the rayon thread
+    /// pool, otherwise one after another. The merged result is identical
+    /// to [`NettingEngine::multilateral_net`], including contribution
+    /// order.
+    pub fn multilateral_net_partitioned_parallel(obligations: &ObligationSet) -> NettingResult {
+        let partitions = connected_partitions(obligations);
+        let subsets: Vec<ObligationSet> = partitions
+            .iter()
+            .map(|indices| {
+                indices
+                    .iter()
+                    .map(|&i| obligations.obligations()[i].clone())
+                    .collect()
+            })
+            .collect();
+
+        #[cfg(feature = "parallel")]
+        let parts: Vec<NettingResult> = {
+            use rayon::prelude::*;
+            subsets.par_iter().map(Self::multilateral_net).collect()
+        };
+        #[cfg(not(feature = "parallel"))]
+        let parts: Vec<NettingResult> = subsets.iter().map(Self::multilateral_net).collect();
+
+        merge_disjoint(partitions, parts)
+    }
+
+    /// Net obligations in settlement-date windows, each independently.
+    ///
+    /// Obligations are bucketed by `settlement_date` truncated to `window`
+    /// (one-day windows start at midnight UTC), so obligations due in
+    /// different windows are never netted against each other. Buckets are
+    /// returned in window order, keyed by window start; obligations without
+    /// a settlement date are netted together in a final `None` bucket.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `window` is not positive.
+    pub fn multilateral_net_windowed(
+        obligations: &ObligationSet,
+        window: Duration,
+    ) -> Vec<(Option<DateTime<Utc>>, NettingResult)> {
+        assert!(
+            window > Duration::zero(),
+            "Window duration must be positive, got {}",
+            window
+        );
+
+        let mut scheduled: BTreeMap<DateTime<Utc>, ObligationSet> = BTreeMap::new();
+        let mut unscheduled = ObligationSet::new();
+        for ob in obligations.obligations() {
+            match ob.settlement_date() {
+                Some(date) => {
+                    let start = date.duration_trunc(window).unwrap_or(date);
+                    scheduled.entry(start).or_default().add(ob.clone());
+                }
+                None => unscheduled.add(ob.clone()),
+            }
+        }
+
+        let mut buckets: Vec<(Option<DateTime<Utc>>, NettingResult)> = scheduled
+            .into_iter()
+            .map(|(start, set)| (Some(start), Self::multilateral_net(&set)))
+            .collect();
+        if !unscheduled.is_empty() {
+            buckets.push((None, Self::multilateral_net(&unscheduled)));
+        }
+        buckets
+    }
 
+    /// Net each clearing batch independently.
+    ///
+    /// Obligations are grouped by [`Obligation::batch_id`]; those without
+    /// one fall into [`DEFAULT_BATCH`]. Each batch is netted on its own,
+    /// so positions never offset across sessions. Batches are ordered by
+    /// id.
+    pub fn net_by_batch(obligations: &ObligationSet) -> BTreeMap<String, NettingResult> {
+        let mut batches: BTreeMap<String, ObligationSet> = BTreeMap::new();
         for ob in obligations.obligations() {
+            batches
+                .entry(ob.batch_id().unwrap_or(DEFAULT_BATCH).to_string())
+                .or_default()
+                .add(ob.clone());
+        }
+        batches
+            .into_iter()
+            .map(|(batch, set)| (batch, Self::multilateral_net(&set)))
+            .collect()
+    }
+
+    fn net_seeded(
+        obligations: &ObligationSet,
+        residual: &Ledger,
+        progress: &mut dyn FnMut(usize, usize),
+    ) -> NettingResult {
+        let mut ledger = residual.clone();
+        let carried_forward_total = residual.total_net_settlement();
+        let mut gross_total = carried_forward_total;
+        let mut edges: HashMap<(PartyId, PartyId, CurrencyCode), Decimal> = HashMap::new();
+        let mut contributions = Vec::with_capacity(obligations.len());
+        let mut accounts: HashMap<(PartyId, CurrencyCode), String> = HashMap::new();
+
+        // Per-currency tracking, starting from the carried-forward residual
+        let mut currency_gross: HashMap<CurrencyCode, Decimal> =
+            residual.net_settlement_by_currency();
+        let mut currency_parties: HashMap<CurrencyCode, HashMap<PartyId, bool>> = HashMap::new();
+        for ((party, currency), amount) in residual.all_positions() {
+            if *amount != Decimal::ZERO {
+                currency_parties
+                    .entry(currency.clone())
+                    .or_default()
+                    .insert(party.clone(), true);
+            }
+        }
+
+        let total = obligations.len();
+        for (processed, ob) in obligations.obligations().iter().enumerate() {
+            if processed > 0 && processed % PROGRESS_INTERVAL == 0 {
+                progress(processed, total);
+            }
             ledger.apply_obligation(ob);
             gross_total += ob.amount();
+            *edges
+                .entry((ob.debtor().clone(), ob.creditor().clone(), ob.currency().clone()))
+                .or_insert(Decimal::ZERO) += ob.amount();
+            for (party, account) in [
+                (ob.debtor(), ob.debtor_account()),
+                (ob.creditor(), ob.creditor_account()),
+            ] {
+                if let Some(account) = account {
+                    accounts
+                        .entry((party.clone(), ob.currency().clone()))
+                        .or_insert_with(|| account.to_string());
+                }
+            }
+            contributions.push((
+                ob.id(),
+                ob.debtor().clone(),
+                ob.creditor().clone(),
+                ob.currency().clone(),
+                ob.amount(),
+            ));
 
             *currency_gross
                 .entry(ob.currency().clone())
@@ -190,18 +1125,15 @@ impl NettingEngine {
             parties.insert(ob.creditor().clone(), true);
         }
 
+        progress(total, total);
+
         let net_total = ledger.total_net_settlement();
+        let currency_net = ledger.net_settlement_by_currency();
 
         // Build per-currency breakdown
-        let mut currency_breakdown = HashMap::new();
+        let mut currency_breakdown = BTreeMap::new();
         for (currency, gross) in &currency_gross {
-            // Compute net for this currency specifically
-            let mut currency_net = Decimal::ZERO;
-            for ((_, cur), amount) in ledger.all_positions() {
-                if cur == currency && *amount > Decimal::ZERO {
-                    currency_net += amount;
-                }
-            }
+            let net = currency_net.get(currency).copied().unwrap_or(Decimal::ZERO);
 
             let party_count = currency_parties
                 .get(currency)
@@ -213,18 +1145,251 @@ impl NettingEngine {
                 CurrencyNettingResult {
                     currency: currency.clone(),
                     gross_total: *gross,
-                    net_total: currency_net,
+                    net_total: net,
                     party_count,
                 },
             );
         }
 
+        let mut flows: Vec<(PartyId, PartyId, CurrencyCode, Decimal)> = edges
+            .into_iter()
+            .map(|((d, c, cur), amt)| (d, c, cur, amt))
+            .collect();
+        flows.sort();
+        let mut accounts: Vec<(PartyId, CurrencyCode, String)> = accounts
+            .into_iter()
+            .map(|((party, currency), account)| (party, currency, account))
+            .collect();
+        accounts.sort();
+
         NettingResult {
             ledger,
             gross_total,
             net_total,
             currency_breakdown,
+            flows,
+            contributions,
+            accounts,
+            carried_forward_total,
+            conversion_log: Vec::new(),
+        }
+    }
+
+    /// Net a batch that must settle atomically: all or nothing.
+    ///
+    /// `available` holds each party's funds per currency (positive
+    /// balances). Settlement instructions are only returned when every net
+    /// debtor can fund its position in full; otherwise every shortfall is
+    /// reported and nothing is settled.
+    pub fn net_atomic(
+        obligations: &ObligationSet,
+        available: &Ledger,
+    ) -> Result<(NettingResult, Vec<SettlementInstruction>), Infeasibility> {
+        let result = Self::multilateral_net(obligations);
+
+        let mut shortfalls: Vec<Shortfall> = result
+            .ledger
+            .all_positions()
+            .iter()
+            .filter(|(_, amount)| **amount < Decimal::ZERO)
+            .filter_map(|((party, currency), amount)| {
+                let required = amount.abs();
+                let funds = available.position(party, currency);
+                (funds < required).then(|| Shortfall {
+                    party: party.clone(),
+                    currency: currency.clone(),
+                    required,
+                    available: funds,
+                })
+            })
+            .collect();
+
+        if !shortfalls.is_empty() {
+            shortfalls.sort_by(|a, b| (&a.party, &a.currency).cmp(&(&b.party, &b.currency)));
+            return Err(Infeasibility { shortfalls });
+        }
+
+        let instructions = result.settlement_instructions();
+        Ok((result, instructions))
+    }
+
+    /// Perform multilateral netting on interest-accrued amounts.
+    ///
+    /// Each obligation carrying a rate is first restated at its accrued
+    /// value as of `as_of` (see [`Obligation::accrued_amount`]), keeping
+    /// every other field; the restated set is then netted as usual.
+    pub fn net_with_accrual(obligations: &ObligationSet, as_of: DateTime<Utc>) -> NettingResult {
+        let accrued: ObligationSet = obligations
+            .obligations()
+            .iter()
+            .map(|ob| ob.restated(ob.accrued_amount(as_of), ob.currency().clone()))
+            .collect();
+        Self::multilateral_net(&accrued)
+    }
+
+    /// Reconstruct the multilateral netting as it stood at `at`.
+    ///
+    /// Only obligations with `created_at <= at` are netted, giving a
+    /// point-in-time view from a full obligation history.
+    pub fn net_as_of(obligations: &ObligationSet, at: DateTime<Utc>) -> NettingResult {
+        let historical: ObligationSet = obligations
+            .obligations()
+            .iter()
+            .filter(|ob| ob.created_at() <= at)
+            .cloned()
+            .collect();
+        Self::multilateral_net(&historical)
+    }
+
+    /// Net obligations in member currencies against a basket unit of account.
+    ///
+    /// Every obligation is restated in units of `basket` (see
+    /// [`Basket::to_units`]), keeping its id, accounts and batch, and the
+    /// restated set is netted as a single currency reported under the
+    /// basket's code. Errors if any required rate is missing.
+    pub fn net_in_unit_of_account(
+        obligations: &ObligationSet,
+        basket: &Basket,
+        rates: &FxRateTable,
+    ) -> Result<NettingResult, FxError> {
+        let restated = obligations
+            .obligations()
+            .iter()
+            .map(|ob| {
+                let units = basket.to_units(rates, ob.amount(), ob.currency())?;
+                Ok(ob.restated(units, basket.code.clone()))
+            })
+            .collect::<Result<ObligationSet, FxError>>()?;
+        Ok(Self::multilateral_net(&restated))
+    }
+
+    /// Multilateral netting consolidated into a single settlement currency.
+    ///
+    /// Every obligation is converted into `settlement_currency`, keeping
+    /// its id, accounts and other fields, triangulating through the table's base currency where no
+    /// direct rate is set (see [`FxRateTable::convert_via_base`]). All
+    /// positions, `gross_total` and `net_total` are then in that currency,
+    /// and the result's conversion log records each obligation's path.
+    /// Errors on the first missing rate rather than dropping obligations.
+    pub fn multilateral_net_with_fx(
+        obligations: &ObligationSet,
+        rates: &FxRateTable,
+        settlement_currency: &CurrencyCode,
+    ) -> Result<NettingResult, FxError> {
+        let mut conversion_log = Vec::with_capacity(obligations.len());
+        let mut converted = ObligationSet::new();
+        for ob in obligations.obligations() {
+            let path = rates.conversion_path(ob.currency(), settlement_currency)?;
+            let rate: Decimal = path.iter().map(|(_, _, rate)| rate).product();
+            converted.add(ob.restated(ob.amount() * rate, settlement_currency.clone()));
+            conversion_log.push(ConversionStep {
+                obligation_id: ob.id(),
+                from: ob.currency().clone(),
+                to: settlement_currency.clone(),
+                rate,
+                path,
+            });
+        }
+        let mut result = Self::multilateral_net(&converted);
+        result.conversion_log = conversion_log;
+        // Both sides get the same converted amount; only the precision of
+        // summing long cross-rate fractions can leave a residual
+        debug_assert!(result.max_rounding_residual() <= MAX_ROUNDING_RESIDUAL);
+        Ok(result)
+    }
+
+    /// The candidate settlement currency with the lowest net liquidity need.
+    ///
+    /// Nets the whole set in each candidate via
+    /// [`NettingEngine::multilateral_net_with_fx`] and values the resulting
+    /// net total in the table's base currency so candidates compare on one
+    /// scale. Returns the winner and its need in base; ties go to the
+    /// earlier candidate. With mutually consistent rates every candidate
+    /// needs the same; they differ when direct quotes diverge from the
+    /// cross rates through base. Errors on the first missing rate, or with
+    /// [`ClearingError::InvalidConfig`] if `candidates` is empty.
+    pub fn best_settlement_currency(
+        obligations: &ObligationSet,
+        rates: &FxRateTable,
+        candidates: &[CurrencyCode],
+    ) -> Result<(CurrencyCode, Decimal), ClearingError> {
+        let mut best: Option<(CurrencyCode, Decimal)> = None;
+        for candidate in candidates {
+            let net = Self::multilateral_net_with_fx(obligations, rates, candidate)?.net_total();
+            let need = rates.convert_via_base(net, candidate, &rates.base_currency)?;
+            if best.as_ref().is_none_or(|(_, lowest)| need < *lowest) {
+                best = Some((candidate.clone(), need));
+            }
         }
+        best.ok_or_else(|| {
+            ClearingError::InvalidConfig("no candidate settlement currencies".into())
+        })
+    }
+
+    /// Compress every payment cycle in `currency` out of `graph`.
+    ///
+    /// Each cycle's bottleneck is subtracted from every edge along it and
+    /// drained edges are removed, until `currency` is acyclic (see
+    /// [`compress_currency_cycles`]). Every party's net position is
+    /// unchanged while gross flow in `currency` falls by the bottleneck
+    /// times the length of each compressed cycle. Other currencies are
+    /// left as they are.
+    pub fn compress_cycles(graph: &PaymentGraph, currency: &CurrencyCode) -> PaymentGraph {
+        compress_currency_cycles(graph, currency)
+    }
+
+    /// Savings in `currency` as hypothetical reciprocal volume is added.
+    ///
+    /// For each step f = 0%, 10%, …, 100%, every `currency` obligation is
+    /// mirrored by a reverse obligation for f of its amount and the result
+    /// netted. Returns (reciprocal volume added, savings) per step. Mirroring
+    /// scales every net position by (1 − f) while gross grows by f, so
+    /// savings rise with each step, up to full offset at 100%. Obligations
+    /// in other currencies are ignored.
+    pub fn reciprocity_sensitivity(
+        obligations: &ObligationSet,
+        currency: &CurrencyCode,
+    ) -> Vec<(Decimal, Decimal)> {
+        let base: Vec<&Obligation> = obligations
+            .obligations()
+            .iter()
+            .filter(|ob| ob.currency() == currency)
+            .collect();
+
+        (0..=10)
+            .map(|step| {
+                let fraction = Decimal::new(step, 1);
+                let mut set: ObligationSet = base.iter().map(|ob| (*ob).clone()).collect();
+                let mut added = Decimal::ZERO;
+                if step > 0 {
+                    for ob in &base {
+                        let amount = ob.amount() * fraction;
+                        added += amount;
+                        set.add(Obligation::new(
+                            ob.creditor().clone(),
+                            ob.debtor().clone(),
+                            amount,
+                            currency.clone(),
+                        ));
+                    }
+                }
+                (added, Self::multilateral_net(&set).savings())
+            })
+            .collect()
+    }
+
+    /// Perform multilateral netting and also return the residual graph.
+    ///
+    /// The residual graph is the obligation graph with every cycle
+    /// compressed out (see [`compress_cycles`]): acyclic per currency,
+    /// with net positions identical to the returned result's ledger.
+    /// Useful for visualizing the network before and after netting.
+    pub fn multilateral_net_with_residual(
+        obligations: &ObligationSet,
+    ) -> (NettingResult, PaymentGraph) {
+        let result = Self::multilateral_net(obligations);
+        let graph = PaymentGraph::from_obligations(obligations.obligations().to_vec());
+        (result, compress_cycles(&graph))
     }
 }
 
@@ -248,13 +1413,117 @@ impl std::fmt::Display for NettingResult {
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::core::obligation::Obligation;
-    use rust_decimal_macros::dec;
+/// Obligation indices grouped by connected component (union-find over
+/// parties), each group ascending and groups ordered by first index.
+fn connected_partitions(obligations: &ObligationSet) -> Vec<Vec<usize>> {
+    fn find(parent: &mut [usize], mut x: usize) -> usize {
+        while parent[x] != x {
+            parent[x] = parent[parent[x]];
+            x = parent[x];
+        }
+        x
+    }
 
-    #[test]
+    let mut node: HashMap<&PartyId, usize> = HashMap::new();
+    let mut parent: Vec<usize> = Vec::new();
+    let mut id = |party| {
+        *node.entry(party).or_insert_with(|| {
+            parent.push(parent.len());
+            parent.len() - 1
+        })
+    };
+    let edges: Vec<(usize, usize)> = obligations
+        .obligations()
+        .iter()
+        .map(|ob| (id(ob.debtor()), id(ob.creditor())))
+        .collect();
+    for &(d, c) in &edges {
+        let (rd, rc) = (find(&mut parent, d), find(&mut parent, c));
+        parent[rd] = rc;
+    }
+
+    let mut groups: Vec<Vec<usize>> = Vec::new();
+    let mut group_of_root: HashMap<usize, usize> = HashMap::new();
+    for (i, &(d, _)) in edges.iter().enumerate() {
+        let root = find(&mut parent, d);
+        let group = *group_of_root.entry(root).or_insert_with(|| {
+            groups.push(Vec::new());
+            groups.len() - 1
+        });
+        groups[group].push(i);
+    }
+    groups
+}
+
+/// Merge results over disjoint party sets back into one.
+///
+/// `partitions[k]` holds the original indices of the obligations netted
+/// in `parts[k]`, used to restore input order of contributions.
+fn merge_disjoint(partitions: Vec<Vec<usize>>, parts: Vec<NettingResult>) -> NettingResult {
+    let mut ledger = Ledger::new();
+    let mut gross_total = Decimal::ZERO;
+    let mut currency_breakdown: BTreeMap<CurrencyCode, CurrencyNettingResult> = BTreeMap::new();
+    let mut flows = Vec::new();
+    let mut contributions = Vec::new();
+    let mut accounts = Vec::new();
+
+    for (indices, part) in partitions.into_iter().zip(parts) {
+        for ((party, currency), amount) in part.ledger.all_positions() {
+            ledger.adjust_position(party, currency, *amount);
+        }
+        gross_total += part.gross_total;
+        for (currency, breakdown) in part.currency_breakdown {
+            let merged = currency_breakdown
+                .entry(currency.clone())
+                .or_insert_with(|| CurrencyNettingResult {
+                    currency,
+                    gross_total: Decimal::ZERO,
+                    net_total: Decimal::ZERO,
+                    party_count: 0,
+                });
+            merged.gross_total += breakdown.gross_total;
+            merged.net_total += breakdown.net_total;
+            merged.party_count += breakdown.party_count;
+        }
+        flows.extend(part.flows);
+        contributions.extend(indices.into_iter().zip(part.contributions));
+        accounts.extend(part.accounts);
+    }
+    flows.sort();
+    accounts.sort();
+    contributions.sort_by_key(|(index, _)| *index);
+
+    NettingResult {
+        net_total: ledger.total_net_settlement(),
+        ledger,
+        gross_total,
+        currency_breakdown,
+        flows,
+        contributions: contributions.into_iter().map(|(_, c)| c).collect(),
+        accounts,
+        carried_forward_total: Decimal::ZERO,
+        conversion_log: Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::cycle_detection::find_cycles;
+    use crate::optimization::settlement::verify_instructions;
+    use crate::simulation::bench_fixtures::{brics_network, standard_network};
+    use chrono::{Duration, TimeZone};
+    use rust_decimal_macros::dec;
+
+    fn ob(debtor: &str, creditor: &str, amount: Decimal) -> Obligation {
+        ob_in(debtor, creditor, amount, &CurrencyCode::new("USD"))
+    }
+
+    fn ob_in(debtor: &str, creditor: &str, amount: Decimal, currency: &CurrencyCode) -> Obligation {
+        Obligation::new(PartyId::new(debtor), PartyId::new(creditor), amount, currency.clone())
+    }
+
+    #[test]
     fn test_bilateral_netting() {
         let mut set = ObligationSet::new();
         let usd = CurrencyCode::new("USD");
@@ -414,4 +1683,1023 @@ mod tests {
         assert!(result.net_total() < result.gross_total());
         assert!(result.savings_percent() > 0.0);
     }
+
+    #[test]
+    fn test_residual_graph_matches_ledger() {
+        let set = brics_network();
+        let usd = CurrencyCode::new("USD");
+        let (result, residual) = NettingEngine::multilateral_net_with_residual(&set);
+
+        assert!(find_cycles(&residual, &usd).is_empty());
+        assert!(residual.gross_total() < result.gross_total());
+
+        let residual_net = residual.compute_net_positions();
+        for party in set.parties() {
+            assert_eq!(
+                residual_net.net_position(&party, &usd),
+                result.net_position(&party, &usd),
+                "residual position mismatch for {}",
+                party
+            );
+        }
+    }
+
+    #[test]
+    fn test_net_with_accrual_one_year() {
+        let usd = CurrencyCode::new("USD");
+        let start = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        let maturity = start + Duration::days(365);
+
+        let mut set = ObligationSet::new();
+        set.add(
+            Obligation::new(PartyId::new("A"), PartyId::new("B"), dec!(1000), usd.clone())
+                .with_created_at(start)
+                .with_settlement_date(maturity)
+                .with_rate(dec!(0.10))
+                .with_debtor_account("A-LOAN"),
+        );
+        set.add(
+            Obligation::new(PartyId::new("B"), PartyId::new("A"), dec!(400), usd.clone())
+                .with_created_at(start),
+        );
+
+        let result = NettingEngine::net_with_accrual(&set, maturity);
+        assert_eq!(result.gross_total(), dec!(1500));
+        assert_eq!(result.net_position(&PartyId::new("A"), &usd), dec!(-700));
+        assert!(result.is_valid());
+        assert_eq!(result.settlement_instructions()[0].from_account, "A-LOAN");
+    }
+
+    #[test]
+    fn test_haircut_withholds_from_creditors() {
+        let usd = CurrencyCode::new("USD");
+        let set = brics_network();
+        let result = NettingEngine::multilateral_net(&set);
+        let (cut, withheld) = result.apply_haircut(50);
+
+        let mut expected = Decimal::ZERO;
+        for party in set.parties() {
+            let before = result.net_position(&party, &usd);
+            let after = cut.net_position(&party, &usd);
+            if before > Decimal::ZERO {
+                expected += before - after;
+                assert_eq!(after, before * dec!(0.995));
+            } else {
+                assert_eq!(after, before);
+            }
+        }
+
+        assert_eq!(withheld[&usd], expected);
+        assert_eq!(cut.net_total(), result.net_total() - expected);
+        assert!(!cut.is_valid());
+    }
+
+    #[test]
+    fn test_purely_gross_flows() {
+        let usd = CurrencyCode::new("USD");
+        let mut set = ObligationSet::new();
+        // B <-> C offset each other; A -> B can never be netted
+        set.add(Obligation::new(PartyId::new("A"), PartyId::new("B"), dec!(100), usd.clone()));
+        set.add(Obligation::new(PartyId::new("B"), PartyId::new("C"), dec!(50), usd.clone()));
+        set.add(Obligation::new(PartyId::new("C"), PartyId::new("B"), dec!(30), usd.clone()));
+
+        let result = NettingEngine::multilateral_net(&set);
+        let gross = result.purely_gross_flows();
+        assert_eq!(
+            gross,
+            vec![(PartyId::new("A"), PartyId::new("B"), usd, dec!(100))]
+        );
+    }
+
+    #[test]
+    fn test_settlement_orderings_differ_but_settle() {
+        let usd = CurrencyCode::new("USD");
+        let mut set = ObligationSet::new();
+        set.add(Obligation::new(PartyId::new("A"), PartyId::new("C"), dec!(40), usd.clone()));
+        set.add(Obligation::new(PartyId::new("A"), PartyId::new("D"), dec!(30), usd.clone()));
+        set.add(Obligation::new(PartyId::new("B"), PartyId::new("D"), dec!(30), usd.clone()));
+        let result = NettingEngine::multilateral_net(&set);
+
+        let by_amount = result.settlement_instructions_with(SettlementOrdering::ByAmountDesc);
+        let by_party = result.settlement_instructions_with(SettlementOrdering::ByPartyId);
+        assert_ne!(by_amount, by_party);
+
+        for instructions in [&by_amount, &by_party] {
+            let total: Decimal = instructions.iter().map(|i| i.amount).sum();
+            assert_eq!(total, result.net_total());
+
+            let mut settled = result.ledger().clone();
+            for ins in instructions.iter() {
+                settled.adjust_position(&ins.from, &ins.currency, ins.amount);
+                settled.adjust_position(&ins.to, &ins.currency, -ins.amount);
+            }
+            assert!(settled.all_positions().values().all(|v| *v == Decimal::ZERO));
+        }
+
+        // A custom policy: smallest positions first
+        let smallest_first = result.settlement_instructions_with(SettlementOrdering::Custom(
+            |a, b| a.1.cmp(&b.1).then_with(|| a.0.cmp(&b.0)),
+        ));
+        assert_eq!(smallest_first[0].from, PartyId::new("B"));
+    }
+
+    #[test]
+    fn test_summary_omits_ledger() {
+        let set = standard_network(50);
+        let result = NettingEngine::multilateral_net(&set);
+        let summary = result.summary();
+
+        assert_eq!(summary.gross_total, result.gross_total());
+        assert_eq!(summary.net_total, result.net_total());
+        assert_eq!(summary.savings, result.savings());
+        assert_eq!(summary.party_count, 50);
+        assert_eq!(summary.obligation_count, set.len());
+
+        let full = serde_json::to_string(&result).unwrap();
+        let compact = serde_json::to_value(&summary).unwrap();
+        assert!(compact.get("ledger").is_none());
+        // The full result carries the ledger (the obligation trace is not
+        // serialized), one position per party
+        assert!(compact.to_string().len() * 5 < full.len());
+    }
+
+    #[test]
+    fn test_to_csv_creditor_row() {
+        let result = NettingEngine::multilateral_net(&brics_network());
+        let csv = result.to_csv();
+        let lines: Vec<&str> = csv.lines().collect();
+
+        assert_eq!(lines[0], "gross_total,net_total,savings,savings_percent,valid");
+        assert!(lines[1].starts_with("555000000,125000000,430000000,"));
+        assert!(lines[1].ends_with(",true"));
+        assert_eq!(lines[3], "party,currency,net_position,status");
+        assert!(lines.contains(&"BR-TREASURY,USD,60000000,CREDITOR"));
+        assert!(lines.contains(&"ZA-SARB,USD,-15000000,DEBTOR"));
+    }
+
+    #[test]
+    fn test_csv_field_quoting() {
+        assert_eq!(csv_field("BANK, N.A."), "\"BANK, N.A.\"");
+        assert_eq!(csv_field("PLAIN"), "PLAIN");
+    }
+
+    #[test]
+    fn test_net_atomic_aborts_on_shortfall() {
+        let usd = CurrencyCode::new("USD");
+        let set = brics_network();
+        // Net debtors: CN-PBOC owes 110M, ZA-SARB owes 15M
+        let mut available = Ledger::new();
+        available.adjust_position(&PartyId::new("CN-PBOC"), &usd, dec!(200_000_000));
+        available.adjust_position(&PartyId::new("ZA-SARB"), &usd, dec!(15_000_000));
+
+        let (result, instructions) = NettingEngine::net_atomic(&set, &available).unwrap();
+        let paid: Decimal = instructions.iter().map(|i| i.amount).sum();
+        assert_eq!(paid, result.net_total());
+
+        // South Africa is 1 short: the whole batch is rejected
+        available.adjust_position(&PartyId::new("ZA-SARB"), &usd, dec!(-1));
+        let err = NettingEngine::net_atomic(&set, &available).unwrap_err();
+        assert_eq!(err.shortfalls.len(), 1);
+        assert_eq!(err.shortfalls[0].party, PartyId::new("ZA-SARB"));
+        assert_eq!(err.shortfalls[0].amount(), dec!(1));
+    }
+
+    #[test]
+    fn test_approx_eq_tolerates_rounding() {
+        let result = NettingEngine::multilateral_net(&brics_network());
+        let mut rounded = result.clone();
+        rounded.gross_total += dec!(0.004);
+        rounded.net_total -= dec!(0.003);
+        rounded
+            .ledger
+            .adjust_position(&PartyId::new("BR-TREASURY"), &CurrencyCode::new("USD"), dec!(0.005));
+
+        assert!(result.approx_eq(&result, Decimal::ZERO));
+        assert!(result.approx_eq(&rounded, dec!(0.01)));
+        assert!(rounded.approx_eq(&result, dec!(0.01)));
+        assert!(!result.approx_eq(&rounded, dec!(0.001)));
+
+        // A position missing on one side compares against zero
+        rounded
+            .ledger
+            .adjust_position(&PartyId::new("XX"), &CurrencyCode::new("USD"), dec!(1));
+        assert!(!result.approx_eq(&rounded, dec!(0.01)));
+    }
+
+    #[test]
+    fn test_fully_netted_obligation_ids() {
+
+        // Perfect trilateral cycle: everything is absorbed
+        let cycle = vec![ob("A", "B", dec!(100)), ob("B", "C", dec!(100)), ob("C", "A", dec!(100))];
+        let ids: Vec<Uuid> = cycle.iter().map(|o| o.id()).collect();
+        let set: ObligationSet = cycle.into_iter().collect();
+        let result = NettingEngine::multilateral_net(&set);
+        assert_eq!(result.fully_netted_obligation_ids(), ids);
+
+        // A→B is split across two obligations; only 60 of it is absorbed
+        let first = ob("A", "B", dec!(60));
+        let second = ob("A", "B", dec!(40));
+        let back = ob("B", "A", dec!(60));
+        let expected = vec![first.id(), back.id()];
+        let set: ObligationSet = vec![first, second, back].into_iter().collect();
+        let result = NettingEngine::multilateral_net(&set);
+        assert_eq!(result.fully_netted_obligation_ids(), expected);
+
+        // A 70 that does not fit leaves room for the later 30
+        let large = ob("A", "B", dec!(70));
+        let small = ob("A", "B", dec!(30));
+        let back = ob("B", "A", dec!(60));
+        let expected = vec![small.id(), back.id()];
+        let set: ObligationSet = vec![large, small, back].into_iter().collect();
+        let result = NettingEngine::multilateral_net(&set);
+        assert_eq!(result.fully_netted_obligation_ids(), expected);
+    }
+
+    #[test]
+    fn test_savings_decomposition() {
+        let usd = CurrencyCode::new("USD");
+        let set: ObligationSet = vec![
+            // Bilateral pair: offsets 2 × 60
+            ob("A", "B", dec!(100)),
+            ob("B", "A", dec!(60)),
+            // Separate three-cycle: only multilateral netting removes it
+            ob("C", "D", dec!(50)),
+            ob("D", "E", dec!(50)),
+            ob("E", "C", dec!(50)),
+        ]
+        .into_iter()
+        .collect();
+
+        let result = NettingEngine::multilateral_net(&set);
+        let decomposition = result.savings_decomposition();
+
+        assert_eq!(decomposition.bilateral[&usd], dec!(120));
+        assert_eq!(decomposition.multilateral[&usd], dec!(150));
+        assert_eq!(
+            decomposition.bilateral_total() + decomposition.multilateral_total(),
+            result.savings()
+        );
+    }
+
+    #[test]
+    fn test_settlement_instructions_rounded() {
+        let usd = CurrencyCode::new("USD");
+        let mut set = ObligationSet::new();
+        set.add(Obligation::new(PartyId::new("A"), PartyId::new("B"), dec!(10.125), usd.clone()));
+
+        let result = NettingEngine::multilateral_net(&set);
+        let bankers = result.settlement_instructions_rounded(2, RoundingMode::Bankers);
+        let half_up = result.settlement_instructions_rounded(2, RoundingMode::HalfUp);
+        assert_eq!(bankers[0].amount, dec!(10.12));
+        assert_eq!(half_up[0].amount, dec!(10.13));
+    }
+
+    #[test]
+    fn test_net_in_unit_of_account() {
+        let usd = CurrencyCode::new("USD");
+        let brl = CurrencyCode::new("BRL");
+        let xdr = CurrencyCode::new("XDR");
+        let mut rates = FxRateTable::new(usd.clone());
+        rates.set_rate(brl.clone(), usd.clone(), dec!(0.2)).unwrap();
+
+        // 1 XDR = 0.6 USD + 2 BRL = 1 USD
+        let basket = Basket::new(xdr.clone())
+            .with_component(usd.clone(), dec!(0.6))
+            .with_component(brl.clone(), dec!(2));
+
+        let mut set = ObligationSet::new();
+        set.add(
+            Obligation::new(PartyId::new("A"), PartyId::new("B"), dec!(100), usd.clone())
+                .with_debtor_account("A-XDR"),
+        );
+        set.add(Obligation::new(PartyId::new("B"), PartyId::new("A"), dec!(300), brl.clone()));
+
+        let result = NettingEngine::net_in_unit_of_account(&set, &basket, &rates).unwrap();
+        assert_eq!(result.gross_total(), dec!(160));
+        assert_eq!(result.net_position(&PartyId::new("A"), &xdr), dec!(-40));
+        assert_eq!(result.net_position(&PartyId::new("B"), &xdr), dec!(40));
+        assert!(result.is_valid());
+        assert_eq!(result.account(&PartyId::new("A"), &xdr), Some("A-XDR"));
+
+        let unknown = Basket::new(xdr).with_component(CurrencyCode::new("INR"), dec!(1));
+        assert!(NettingEngine::net_in_unit_of_account(&set, &unknown, &rates).is_err());
+    }
+
+    #[test]
+    fn test_multilateral_net_with_fx() {
+        let usd = CurrencyCode::new("USD");
+        let eur = CurrencyCode::new("EUR");
+        let brl = CurrencyCode::new("BRL");
+        let mut rates = FxRateTable::new(usd.clone());
+        rates.set_rate(eur.clone(), usd.clone(), dec!(1.10)).unwrap();
+        rates.set_rate(brl.clone(), usd.clone(), dec!(0.20)).unwrap();
+
+        // A → B 100 USD, B → C 100 EUR (110 USD), C → A 500 BRL (100 USD)
+        let mut set = ObligationSet::new();
+        set.add(Obligation::new(PartyId::new("A"), PartyId::new("B"), dec!(100), usd.clone()));
+        set.add(Obligation::new(PartyId::new("B"), PartyId::new("C"), dec!(100), eur.clone()));
+        set.add(Obligation::new(PartyId::new("C"), PartyId::new("A"), dec!(500), brl.clone()));
+
+        let result = NettingEngine::multilateral_net_with_fx(&set, &rates, &usd).unwrap();
+        assert_eq!(result.gross_total(), dec!(310));
+        assert_eq!(result.net_position(&PartyId::new("A"), &usd), dec!(0));
+        assert_eq!(result.net_position(&PartyId::new("B"), &usd), dec!(-10));
+        assert_eq!(result.net_position(&PartyId::new("C"), &usd), dec!(10));
+        assert_eq!(result.net_total(), dec!(10));
+        assert_eq!(result.currency_breakdown().keys().collect::<Vec<_>>(), vec![&usd]);
+        assert!(result.is_valid());
+
+        let mut missing = set.clone();
+        missing.add(Obligation::new(
+            PartyId::new("A"),
+            PartyId::new("C"),
+            dec!(1),
+            CurrencyCode::new("INR"),
+        ));
+        assert!(matches!(
+            NettingEngine::multilateral_net_with_fx(&missing, &rates, &usd),
+            Err(FxError::RateNotFound { .. })
+        ));
+    }
+
+    #[test]
+    fn test_fx_netting_keeps_named_accounts() {
+        let usd = CurrencyCode::new("USD");
+        let brl = CurrencyCode::new("BRL");
+        let mut rates = FxRateTable::new(usd.clone());
+        rates.set_rate(brl.clone(), usd.clone(), dec!(0.20)).unwrap();
+
+        let mut set = ObligationSet::new();
+        set.add(
+            Obligation::new(PartyId::new("A"), PartyId::new("B"), dec!(500), brl)
+                .with_debtor_account("A-BRL-NOSTRO")
+                .with_creditor_account("B-USD-VOSTRO"),
+        );
+
+        let result = NettingEngine::multilateral_net_with_fx(&set, &rates, &usd).unwrap();
+        let instructions = result.settlement_instructions();
+        assert_eq!(instructions.len(), 1);
+        assert_eq!(instructions[0].amount, dec!(100));
+        assert_eq!(instructions[0].from_account, "A-BRL-NOSTRO");
+        assert_eq!(instructions[0].to_account, "B-USD-VOSTRO");
+    }
+
+    #[test]
+    fn test_fx_conversion_log_records_triangulation() {
+        let usd = CurrencyCode::new("USD");
+        let brl = CurrencyCode::new("BRL");
+        let inr = CurrencyCode::new("INR");
+        let mut rates = FxRateTable::new(usd.clone());
+        rates.set_rate(brl.clone(), usd.clone(), dec!(0.20)).unwrap();
+        rates.set_rate(usd.clone(), inr.clone(), dec!(80)).unwrap();
+
+        let brl_leg = Obligation::new(PartyId::new("A"), PartyId::new("B"), dec!(100), brl.clone());
+        let inr_leg = Obligation::new(PartyId::new("B"), PartyId::new("A"), dec!(1000), inr.clone());
+        let set: ObligationSet = vec![brl_leg.clone(), inr_leg.clone()].into_iter().collect();
+
+        let result = NettingEngine::multilateral_net_with_fx(&set, &rates, &inr).unwrap();
+        let log = result.conversion_log();
+        assert_eq!(log.len(), 2);
+
+        assert_eq!(log[0].obligation_id, brl_leg.id());
+        assert_eq!((&log[0].from, &log[0].to, log[0].rate), (&brl, &inr, dec!(16)));
+        assert_eq!(
+            log[0].path,
+            vec![(brl.clone(), usd.clone(), dec!(0.20)), (usd.clone(), inr.clone(), dec!(80))]
+        );
+
+        // Already in the settlement currency: no legs
+        assert_eq!((log[1].rate, log[1].path.len()), (Decimal::ONE, 0));
+        assert_eq!(result.net_position(&PartyId::new("A"), &inr), dec!(-600));
+
+        assert!(NettingEngine::multilateral_net(&set).conversion_log().is_empty());
+    }
+
+    #[test]
+    fn test_max_rounding_residual() {
+        let result = NettingEngine::multilateral_net(&brics_network());
+        assert_eq!(result.max_rounding_residual(), Decimal::ZERO);
+
+        // 1 EUR = 1/3 USD leaves repeating decimals in every converted amount
+        let usd = CurrencyCode::new("USD");
+        let eur = CurrencyCode::new("EUR");
+        let mut rates = FxRateTable::new(usd.clone());
+        rates.set_rate(usd.clone(), eur.clone(), dec!(3)).unwrap();
+        let mut set = ObligationSet::new();
+        set.add(Obligation::new(PartyId::new("A"), PartyId::new("B"), dec!(100), eur.clone()));
+        set.add(Obligation::new(PartyId::new("B"), PartyId::new("C"), dec!(50), eur));
+        let converted = NettingEngine::multilateral_net_with_fx(&set, &rates, &usd).unwrap();
+        assert_eq!(converted.max_rounding_residual(), Decimal::ZERO);
+
+        let (haircut, withheld) = result.apply_haircut(50);
+        assert_eq!(haircut.max_rounding_residual(), withheld[&usd]);
+    }
+
+    #[test]
+    fn test_compress_cycles_trilateral() {
+        let usd = CurrencyCode::new("USD");
+        let brl = CurrencyCode::new("BRL");
+        let graph = PaymentGraph::from_obligations(vec![
+            Obligation::new(PartyId::new("A"), PartyId::new("B"), dec!(100), usd.clone()),
+            Obligation::new(PartyId::new("B"), PartyId::new("C"), dec!(80), usd.clone()),
+            Obligation::new(PartyId::new("C"), PartyId::new("A"), dec!(120), usd.clone()),
+            Obligation::new(PartyId::new("A"), PartyId::new("B"), dec!(10), brl.clone()),
+            Obligation::new(PartyId::new("B"), PartyId::new("A"), dec!(10), brl.clone()),
+        ]);
+
+        let residual = NettingEngine::compress_cycles(&graph, &usd);
+
+        // One cycle with bottleneck 80 across three edges
+        assert_eq!(graph.gross_total() - residual.gross_total(), dec!(80) * dec!(3));
+        assert!(residual.edges().len() < graph.edges().len());
+        assert!(find_cycles(&residual, &usd).is_empty());
+        assert_eq!(
+            residual.compute_net_positions().ledger().all_positions(),
+            graph.compute_net_positions().ledger().all_positions()
+        );
+        // The BRL pair is another currency and left alone
+        assert_eq!(find_cycles(&residual, &brl).len(), 1);
+    }
+
+    #[test]
+    fn test_multilateral_net_windowed_by_day() {
+        let usd = CurrencyCode::new("USD");
+        let day = |d: u32, h: u32| Utc.with_ymd_and_hms(2024, 3, d, h, 0, 0).unwrap();
+        let mut set = ObligationSet::new();
+        set.add(ob("A", "B", dec!(100)).with_settlement_date(day(3, 9)));
+        set.add(ob("B", "A", dec!(100)).with_settlement_date(day(1, 17)));
+        set.add(ob("B", "A", dec!(60)).with_settlement_date(day(1, 8)));
+        set.add(ob("A", "B", dec!(30)).with_settlement_date(day(2, 12)));
+        set.add(ob("C", "A", dec!(5)));
+
+        let buckets = NettingEngine::multilateral_net_windowed(&set, Duration::days(1));
+        let starts: Vec<Option<DateTime<Utc>>> = buckets.iter().map(|(start, _)| *start).collect();
+        assert_eq!(starts, vec![Some(day(1, 0)), Some(day(2, 0)), Some(day(3, 0)), None]);
+
+        // Day 1 nets its two obligations; the day-3 A → B is not offset
+        // against them, though it would be in a single run
+        assert_eq!(buckets[0].1.gross_total(), dec!(160));
+        assert_eq!(buckets[0].1.net_position(&PartyId::new("A"), &usd), dec!(160));
+        assert_eq!(buckets[2].1.net_position(&PartyId::new("A"), &usd), dec!(-100));
+        assert_eq!(buckets[3].1.gross_total(), dec!(5));
+        assert_eq!(
+            NettingEngine::multilateral_net(&set).net_position(&PartyId::new("A"), &usd),
+            dec!(35)
+        );
+    }
+
+    #[test]
+    fn test_reciprocity_sensitivity_is_monotonic() {
+        let usd = CurrencyCode::new("USD");
+        let set = brics_network();
+        let curve = NettingEngine::reciprocity_sensitivity(&set, &usd);
+
+        assert_eq!(curve.len(), 11);
+        assert_eq!(curve[0], (Decimal::ZERO, NettingEngine::multilateral_net(&set).savings()));
+        for pair in curve.windows(2) {
+            assert!(pair[1].0 > pair[0].0);
+            assert!(pair[1].1 > pair[0].1);
+        }
+        // Fully reciprocated flows offset completely: everything is saved
+        let (added, saved) = curve[10];
+        assert_eq!(added, set.gross_total());
+        assert_eq!(saved, set.gross_total() * dec!(2));
+
+        let eur = CurrencyCode::new("EUR");
+        assert!(NettingEngine::reciprocity_sensitivity(&set, &eur)
+            .iter()
+            .all(|(added, saved)| added.is_zero() && saved.is_zero()));
+    }
+
+    #[test]
+    fn test_best_settlement_currency() {
+        let usd = CurrencyCode::new("USD");
+        let brl = CurrencyCode::new("BRL");
+        let inr = CurrencyCode::new("INR");
+        let mut rates = FxRateTable::new(usd.clone());
+        rates.set_rate(brl.clone(), usd.clone(), dec!(0.20)).unwrap();
+        rates.set_rate(inr.clone(), usd.clone(), dec!(0.0125)).unwrap();
+        // Direct quote below the 16 INR cross rate through USD
+        rates.set_rate(brl.clone(), inr.clone(), dec!(15)).unwrap();
+
+        // 100 BRL and 1600 INR are both worth 20 USD
+        let mut set = ObligationSet::new();
+        set.add(Obligation::new(PartyId::new("A"), PartyId::new("B"), dec!(100), brl.clone()));
+        set.add(Obligation::new(PartyId::new("B"), PartyId::new("A"), dec!(1600), inr.clone()));
+
+        let candidates = [inr.clone(), brl.clone(), usd.clone()];
+        let (best, need) =
+            NettingEngine::best_settlement_currency(&set, &rates, &candidates).unwrap();
+        assert_eq!((best, need), (usd.clone(), dec!(0)));
+
+        // In INR the BRL leg is only worth 1500, leaving 100 INR to settle
+        let (best, need) =
+            NettingEngine::best_settlement_currency(&set, &rates, &[inr.clone(), brl]).unwrap();
+        assert_eq!((best, need), (inr, dec!(1.25)));
+
+        let unpriced = [CurrencyCode::new("ZAR")];
+        assert!(matches!(
+            NettingEngine::best_settlement_currency(&set, &rates, &unpriced),
+            Err(ClearingError::Fx(FxError::RateNotFound { .. }))
+        ));
+        assert!(matches!(
+            NettingEngine::best_settlement_currency(&set, &rates, &[]),
+            Err(ClearingError::InvalidConfig(_))
+        ));
+    }
+
+    #[test]
+    fn test_as_sub_accounts() {
+        let usd = CurrencyCode::new("USD");
+        let brl = CurrencyCode::new("BRL");
+        let mut set = ObligationSet::new();
+        set.add(Obligation::new(PartyId::new("A"), PartyId::new("B"), dec!(100), usd.clone()));
+        set.add(Obligation::new(PartyId::new("C"), PartyId::new("A"), dec!(30), brl.clone()));
+
+        let accounts = NettingEngine::multilateral_net(&set).as_sub_accounts();
+        assert_eq!(accounts.len(), 4);
+
+        let a: Vec<&SubAccount> = accounts.iter().filter(|s| s.party.as_str() == "A").collect();
+        assert_eq!(a.len(), 2);
+        assert_eq!((&a[0].currency, a[0].net_position), (&brl, dec!(30)));
+        assert_eq!((&a[1].currency, a[1].net_position), (&usd, dec!(-100)));
+    }
+
+    #[test]
+    fn test_is_worthwhile() {
+        let usd = CurrencyCode::new("USD");
+        let mut set = ObligationSet::new();
+        set.add(Obligation::new(PartyId::new("A"), PartyId::new("B"), dec!(100), usd.clone()));
+        set.add(Obligation::new(PartyId::new("B"), PartyId::new("A"), dec!(1), usd.clone()));
+
+        // Saves 2 of 101 gross: under 2%
+        let result = NettingEngine::multilateral_net(&set);
+        assert!(!result.is_worthwhile(5.0));
+        assert!(result.is_worthwhile(1.0));
+        assert!(NettingEngine::multilateral_net(&brics_network()).is_worthwhile(5.0));
+    }
+
+    #[test]
+    fn test_settlement_instructions_clear_brics() {
+        let result = NettingEngine::multilateral_net(&brics_network());
+        let instructions = result.settlement_instructions();
+
+        // Greedy matching needs at most one fewer transfer than parties
+        assert!(instructions.len() < result.ledger().all_positions().len());
+
+        let mut paid: HashMap<CurrencyCode, Decimal> = HashMap::new();
+        let mut ledger = result.ledger().clone();
+        for instruction in &instructions {
+            assert!(instruction.amount > Decimal::ZERO);
+            *paid.entry(instruction.currency.clone()).or_default() += instruction.amount;
+            ledger.adjust_position(&instruction.from, &instruction.currency, instruction.amount);
+            ledger.adjust_position(&instruction.to, &instruction.currency, -instruction.amount);
+        }
+
+        for (currency, breakdown) in result.currency_breakdown() {
+            assert_eq!(paid[currency], breakdown.net_total);
+        }
+        assert!(ledger.all_positions().values().all(|p| p.is_zero()));
+    }
+
+    #[test]
+    fn test_min_count_instructions_cancel_exact_matches() {
+        let usd = CurrencyCode::new("USD");
+        let mut set = ObligationSet::new();
+        // Debtors 70 and 30; creditors 50, 30 and 20
+        set.add(Obligation::new(PartyId::new("D1"), PartyId::new("C1"), dec!(50), usd.clone()));
+        set.add(Obligation::new(PartyId::new("D1"), PartyId::new("C3"), dec!(20), usd.clone()));
+        set.add(Obligation::new(PartyId::new("D2"), PartyId::new("C2"), dec!(30), usd.clone()));
+        let result = NettingEngine::multilateral_net(&set);
+
+        let greedy = result.settlement_instructions();
+        let minimal = result.settlement_instructions_min_count();
+        assert_eq!(greedy.len(), 4);
+        assert_eq!(minimal.len(), 3);
+        assert!(minimal
+            .iter()
+            .any(|i| (i.from.as_str(), i.to.as_str(), i.amount) == ("D2", "C2", dec!(30))));
+        assert!(verify_instructions(&result, &minimal));
+    }
+
+    #[test]
+    fn test_min_count_never_exceeds_greedy() {
+        let mut results = vec![NettingEngine::multilateral_net(&brics_network())];
+        results.extend(
+            [10, 25, 50].map(|size| NettingEngine::multilateral_net(&standard_network(size))),
+        );
+        for result in results {
+            let minimal = result.settlement_instructions_min_count();
+            assert!(minimal.len() <= result.settlement_instructions().len());
+            assert!(verify_instructions(&result, &minimal));
+            for currency in result.currency_breakdown().keys() {
+                let parties = result
+                    .ledger()
+                    .all_positions()
+                    .iter()
+                    .filter(|((_, c), amount)| c == currency && !amount.is_zero())
+                    .count();
+                let transfers = minimal.iter().filter(|i| &i.currency == currency).count();
+                assert!(transfers <= parties.saturating_sub(1));
+            }
+        }
+    }
+
+    #[test]
+    fn test_prioritized_instructions_pay_critical_creditor_first() {
+        let usd = CurrencyCode::new("USD");
+        let (a, b, c) = (PartyId::new("A"), PartyId::new("B"), PartyId::new("C"));
+        let mut set = ObligationSet::new();
+        set.add(Obligation::new(a.clone(), b.clone(), dec!(40), usd.clone()));
+        set.add(Obligation::new(a.clone(), c.clone(), dec!(60), usd.clone()));
+        let result = NettingEngine::multilateral_net(&set);
+
+        let mut available = Ledger::new();
+        available.adjust_position(&a, &usd, dec!(70));
+        let priority = HashMap::from([(b.clone(), 9)]);
+        let instructions = result.settlement_instructions_prioritized(&priority, &available);
+
+        let paid = |to: &PartyId| -> Decimal {
+            instructions.iter().filter(|i| &i.to == to).map(|i| i.amount).sum()
+        };
+        // B is paid in full despite its smaller claim; C absorbs the 30 shortfall
+        assert_eq!(paid(&b), dec!(40));
+        assert_eq!(paid(&c), dec!(30));
+        assert_eq!(instructions[0].to, b);
+
+        // With ample liquidity everyone is paid as usual
+        available.adjust_position(&a, &usd, dec!(1_000));
+        let full = result.settlement_instructions_prioritized(&priority, &available);
+        assert!(verify_instructions(&result, &full));
+    }
+
+    #[test]
+    fn test_instructions_preserve_accounts() {
+        let usd = CurrencyCode::new("USD");
+        let mut set = ObligationSet::new();
+        set.add(
+            Obligation::new(PartyId::new("A"), PartyId::new("B"), dec!(100), usd.clone())
+                .with_debtor_account("A-NOSTRO-1")
+                .with_creditor_account("B-VOSTRO-9"),
+        );
+        set.add(Obligation::new(PartyId::new("C"), PartyId::new("A"), dec!(30), usd.clone()));
+
+        let instructions = NettingEngine::multilateral_net(&set).settlement_instructions();
+        let to_b: Vec<&SettlementInstruction> =
+            instructions.iter().filter(|i| i.to.as_str() == "B").collect();
+        assert_eq!(to_b.len(), 2);
+        for instruction in to_b {
+            assert_eq!(instruction.to_account, "B-VOSTRO-9");
+            let expected = match instruction.from.as_str() {
+                "A" => "A-NOSTRO-1",
+                _ => "C",
+            };
+            assert_eq!(instruction.from_account, expected);
+        }
+    }
+
+    #[cfg(feature = "msgpack")]
+    #[test]
+    fn test_msgpack_round_trip() {
+        let result = NettingEngine::multilateral_net(&brics_network());
+        let bytes = result.to_msgpack();
+        assert!(bytes.len() < serde_json::to_vec(&result).unwrap().len());
+
+        let back = NettingResult::from_msgpack(&bytes).unwrap();
+        assert_eq!(back.ledger().all_positions(), result.ledger().all_positions());
+        assert_eq!(back.gross_total(), result.gross_total());
+        // The obligation trace is not serialized
+        assert!(back.flows().is_empty());
+    }
+
+    #[test]
+    fn test_top_savings_sources() {
+        let usd = CurrencyCode::new("USD");
+        let set: ObligationSet = vec![
+            // Bilateral pair saving 60
+            ob("A", "B", dec!(100)),
+            ob("B", "A", dec!(30)),
+            // Three-cycle saving 150
+            ob("D", "E", dec!(50)),
+            ob("E", "C", dec!(50)),
+            ob("C", "D", dec!(50)),
+            // Three-cycle saving 30
+            ob("F", "G", dec!(10)),
+            ob("G", "H", dec!(10)),
+            ob("H", "F", dec!(10)),
+        ]
+        .into_iter()
+        .collect();
+
+        let result = NettingEngine::multilateral_net(&set);
+        let top = result.top_savings_sources(2);
+        assert_eq!(top.len(), 2);
+        assert_eq!(
+            top[0],
+            SavingsSource::Cycle {
+                parties: vec![PartyId::new("C"), PartyId::new("D"), PartyId::new("E")],
+                currency: usd.clone(),
+                saved: dec!(150),
+            }
+        );
+        assert!(matches!(top[1], SavingsSource::BilateralPair { saved, .. } if saved == dec!(60)));
+
+        let all: Decimal = result.top_savings_sources(10).iter().map(|s| s.saved()).sum();
+        assert_eq!(all, result.savings());
+    }
+
+    #[test]
+    fn test_breakdown_and_positions_iterate_in_order() {
+        let mut set = ObligationSet::new();
+        for (debtor, creditor, currency) in [
+            ("Z", "A", "ZAR"),
+            ("M", "B", "BRL"),
+            ("A", "Z", "USD"),
+            ("B", "M", "CNY"),
+        ] {
+            set.add(ob_in(debtor, creditor, dec!(10), &CurrencyCode::new(currency)));
+        }
+
+        let result = NettingEngine::multilateral_net(&set);
+        let currencies: Vec<&str> = result.currency_breakdown().keys().map(|c| c.as_str()).collect();
+        assert_eq!(currencies, vec!["BRL", "CNY", "USD", "ZAR"]);
+
+        let keys: Vec<_> = result.ledger().all_positions().keys().cloned().collect();
+        let mut sorted = keys.clone();
+        sorted.sort();
+        assert_eq!(keys, sorted);
+    }
+
+    #[test]
+    fn test_expected_settlement_date() {
+        let usd = CurrencyCode::new("USD");
+        let early = Utc.with_ymd_and_hms(2024, 3, 1, 0, 0, 0).unwrap();
+        let late = Utc.with_ymd_and_hms(2024, 3, 15, 0, 0, 0).unwrap();
+        let mut set = ObligationSet::new();
+        set.add(
+            Obligation::new(PartyId::new("A"), PartyId::new("B"), dec!(100), usd.clone())
+                .with_settlement_date(early),
+        );
+        set.add(
+            Obligation::new(PartyId::new("C"), PartyId::new("B"), dec!(50), usd.clone())
+                .with_settlement_date(late),
+        );
+        set.add(Obligation::new(PartyId::new("D"), PartyId::new("E"), dec!(5), usd.clone()));
+
+        let result = NettingEngine::multilateral_net(&set);
+        assert_eq!(result.expected_settlement_date(&PartyId::new("B"), &usd, &set), Some(late));
+        assert_eq!(result.expected_settlement_date(&PartyId::new("A"), &usd, &set), Some(early));
+        assert_eq!(result.expected_settlement_date(&PartyId::new("D"), &usd, &set), None);
+    }
+
+    #[test]
+    fn test_net_with_carryforward() {
+        let usd = CurrencyCode::new("USD");
+        let a = PartyId::new("A");
+        let b = PartyId::new("B");
+        let mut today = ObligationSet::new();
+        today.add(Obligation::new(a.clone(), b.clone(), dec!(100), usd.clone()));
+
+        // Yesterday A was left owing B another 50
+        let mut residual = Ledger::new();
+        residual.adjust_position(&a, &usd, dec!(-50));
+        residual.adjust_position(&b, &usd, dec!(50));
+
+        let fresh = NettingEngine::multilateral_net(&today);
+        let rolled = NettingEngine::net_with_carryforward(&today, &residual);
+
+        assert_eq!(rolled.net_position(&a, &usd), dec!(-150));
+        assert!(rolled.net_total() > fresh.net_total());
+        assert_eq!(rolled.carried_forward_total(), dec!(50));
+        assert_eq!(rolled.new_obligations_total(), dec!(100));
+        assert_eq!(rolled.gross_total(), dec!(150));
+        assert_eq!(rolled.currency_breakdown()[&usd].gross_total, dec!(150));
+        assert!(rolled.is_valid());
+        assert_eq!(fresh.carried_forward_total(), Decimal::ZERO);
+    }
+
+    #[test]
+    fn test_net_by_batch() {
+        let usd = CurrencyCode::new("USD");
+        let a = PartyId::new("A");
+        let b = PartyId::new("B");
+        let mut set = ObligationSet::new();
+        set.add(Obligation::new(a.clone(), b.clone(), dec!(100), usd.clone()).with_batch_id("AM"));
+        set.add(Obligation::new(b.clone(), a.clone(), dec!(100), usd.clone()).with_batch_id("PM"));
+        set.add(Obligation::new(b.clone(), a.clone(), dec!(30), usd.clone()));
+
+        let batches = NettingEngine::net_by_batch(&set);
+
+        assert_eq!(batches.keys().collect::<Vec<_>>(), vec!["AM", "PM", DEFAULT_BATCH]);
+        // The AM and PM legs would cancel if netted together
+        assert_eq!(batches["AM"].net_position(&a, &usd), dec!(-100));
+        assert_eq!(batches["PM"].net_position(&a, &usd), dec!(100));
+        assert_eq!(batches[DEFAULT_BATCH].net_total(), dec!(30));
+        assert_eq!(NettingEngine::multilateral_net(&set).net_total(), dec!(30));
+    }
+
+    #[test]
+    fn test_gross_instructions_outnumber_netted() {
+        let set: ObligationSet = vec![
+            ob("A", "B", dec!(100)),
+            ob("B", "C", dec!(80)),
+            ob("C", "A", dec!(60)),
+        ]
+        .into_iter()
+        .collect();
+
+        let result = NettingEngine::multilateral_net(&set);
+        let gross = result.gross_instructions(&set);
+        let netted = result.settlement_instructions();
+
+        assert_eq!(gross.len(), 3);
+        assert_eq!(gross[0].from_account, "A");
+        let gross_sum: Decimal = gross.iter().map(|i| i.amount).sum();
+        assert_eq!(gross_sum, result.gross_total());
+        assert!(netted.len() < gross.len());
+    }
+
+    #[test]
+    fn test_settlement_instructions_capped_defers_smallest() {
+        let set: ObligationSet = vec![
+            ob("A", "D", dec!(100)),
+            ob("B", "E", dec!(50)),
+            ob("C", "F", dec!(10)),
+        ]
+        .into_iter()
+        .collect();
+        let result = NettingEngine::multilateral_net(&set);
+
+        let (instructions, deferred) = result.settlement_instructions_capped(2);
+
+        assert_eq!(instructions.len(), 2);
+        assert_eq!(instructions[0].amount, dec!(100));
+        assert_eq!(instructions[1].amount, dec!(50));
+        assert_eq!(deferred.len(), 1);
+        let residual = &deferred.obligations()[0];
+        assert_eq!(residual.debtor(), &PartyId::new("C"));
+        assert_eq!(residual.amount(), dec!(10));
+
+        let (all, none) = result.settlement_instructions_capped(10);
+        assert_eq!(all.len(), 3);
+        assert!(none.is_empty());
+    }
+
+    #[test]
+    fn test_risk_weighted_exposure() {
+        let usd = CurrencyCode::new("USD");
+        let set: ObligationSet = vec![ob("A", "C", dec!(100)), ob("B", "C", dec!(50))]
+            .into_iter()
+            .collect();
+        let result = NettingEngine::multilateral_net(&set);
+
+        let mut risk = HashMap::from([
+            (PartyId::new("A"), dec!(0.5)),
+            (PartyId::new("B"), dec!(1)),
+            (PartyId::new("C"), dec!(3)),
+        ]);
+        // 100 × 0.5 + 50 × 1; creditor C carries no exposure
+        assert_eq!(result.risk_weighted_exposure(&risk)[&usd], dec!(100));
+
+        risk.insert(PartyId::new("A"), dec!(1.0));
+        // A's contribution doubles from 50 to 100
+        assert_eq!(result.risk_weighted_exposure(&risk)[&usd], dec!(150));
+    }
+
+    #[test]
+    fn test_multilateral_net_with_progress() {
+        use crate::simulation::stress_test::{generate_seeded_network, NetworkConfig};
+
+        let config = NetworkConfig {
+            party_count: 50,
+            avg_obligations_per_party: 500,
+            ..Default::default()
+        };
+        let set = generate_seeded_network(&config, 11);
+        let mut calls = Vec::new();
+
+        let result = NettingEngine::multilateral_net_with_progress(&set, |done, total| {
+            calls.push((done, total));
+        });
+
+        assert_eq!(calls, vec![(10_000, 25_000), (20_000, 25_000), (25_000, 25_000)]);
+        assert_eq!(calls.last().unwrap().0, set.len());
+        let plain = NettingEngine::multilateral_net(&set);
+        assert_eq!(result.net_total(), plain.net_total());
+        assert_eq!(result.ledger().all_positions(), plain.ledger().all_positions());
+    }
+
+    #[test]
+    fn test_orphan_parties() {
+        let usd = CurrencyCode::new("USD");
+        let brl = CurrencyCode::new("BRL");
+        let mut set = ObligationSet::new();
+        set.add(Obligation::new(PartyId::new("A"), PartyId::new("B"), dec!(100), usd.clone()));
+        set.add(Obligation::new(PartyId::new("B"), PartyId::new("A"), dec!(100), usd.clone()));
+        set.add(Obligation::new(PartyId::new("A"), PartyId::new("C"), dec!(50), brl.clone()));
+
+        let result = NettingEngine::multilateral_net(&set);
+
+        // A and B net to zero in USD but are not orphans
+        assert_eq!(result.orphan_parties(&usd), vec![PartyId::new("C")]);
+        assert_eq!(result.orphan_parties(&brl), vec![PartyId::new("B")]);
+    }
+
+    #[test]
+    fn test_instructions_by_linear_cost_match_greedy() {
+        let result = NettingEngine::multilateral_net(&brics_network());
+        let funding =
+            |_: &PartyId, _: &CurrencyCode, amount: Decimal| amount.min(Decimal::ZERO).abs();
+        assert_eq!(
+            result.settlement_instructions_by_cost(funding),
+            result.settlement_instructions()
+        );
+        assert_eq!(result.cost(funding), result.net_total());
+    }
+
+    #[test]
+    fn test_instructions_by_cost_shift_split_toward_cheaper_party() {
+        let usd = CurrencyCode::new("USD");
+        let (a, b) = (PartyId::new("A"), PartyId::new("B"));
+        let (c, d) = (PartyId::new("C"), PartyId::new("D"));
+        let mut set = ObligationSet::new();
+        set.add(Obligation::new(a.clone(), c.clone(), dec!(75), usd.clone()));
+        set.add(Obligation::new(a.clone(), d.clone(), dec!(25), usd.clone()));
+        set.add(Obligation::new(b.clone(), d.clone(), dec!(50), usd.clone()));
+
+        // A owes 100 and B 50 against C's 75 and D's 75, so one creditor
+        // must take two transfers. Each transfer into D costs 10, others 1
+        let fee = |party: &PartyId, _: &CurrencyCode, _: Decimal| {
+            if *party == d { dec!(10) } else { Decimal::ONE }
+        };
+        let receipts = |instructions: &[SettlementInstruction], party: &PartyId| {
+            instructions.iter().filter(|i| &i.to == party).count()
+        };
+
+        let result = NettingEngine::multilateral_net(&set);
+        let plain = result.settlement_instructions();
+        assert_eq!(receipts(&plain, &d), 2);
+
+        let by_cost = result.settlement_instructions_by_cost(fee);
+        assert!(verify_instructions(&result, &by_cost));
+        assert_eq!(receipts(&by_cost, &d), 1);
+        assert_eq!(receipts(&by_cost, &c), 2);
+        let total = |instructions: &[SettlementInstruction]| -> Decimal {
+            instructions
+                .iter()
+                .map(|i| fee(&i.from, &i.currency, -i.amount) + fee(&i.to, &i.currency, i.amount))
+                .sum()
+        };
+        assert!(total(&by_cost) < total(&plain));
+    }
+
+    #[test]
+    fn test_net_as_of_excludes_later_obligations() {
+        let usd = CurrencyCode::new("USD");
+        let (a, b) = (PartyId::new("A"), PartyId::new("B"));
+        let cutoff = Utc.with_ymd_and_hms(2024, 3, 1, 12, 0, 0).unwrap();
+        let mut set = ObligationSet::new();
+        set.add(
+            Obligation::new(a.clone(), b.clone(), dec!(100), usd.clone())
+                .with_created_at(cutoff - Duration::hours(2)),
+        );
+        set.add(
+            Obligation::new(b.clone(), a.clone(), dec!(60), usd.clone()).with_created_at(cutoff),
+        );
+        set.add(
+            Obligation::new(a.clone(), b.clone(), dec!(500), usd.clone())
+                .with_created_at(cutoff + Duration::seconds(1)),
+        );
+
+        let historical = NettingEngine::net_as_of(&set, cutoff);
+
+        assert_eq!(historical.net_position(&a, &usd), dec!(-40));
+        assert_eq!(historical.gross_total(), dec!(160));
+        assert_eq!(NettingEngine::multilateral_net(&set).net_position(&a, &usd), dec!(-540));
+    }
+
+    #[test]
+    fn test_partitioned_parallel_equals_serial() {
+        let usd = CurrencyCode::new("USD");
+        let eur = CurrencyCode::new("EUR");
+        // Two components, interleaved in input order
+        let set: ObligationSet = vec![
+            ob_in("A", "B", dec!(100), &usd),
+            ob_in("X", "Y", dec!(40), &usd),
+            ob_in("B", "C", dec!(70), &usd),
+            ob_in("Y", "X", dec!(15), &eur),
+            ob_in("C", "A", dec!(20), &usd).with_debtor_account("C-OPS"),
+        ]
+        .into_iter()
+        .collect();
+
+        assert_eq!(connected_partitions(&set), vec![vec![0, 2, 4], vec![1, 3]]);
+
+        let serial = NettingEngine::multilateral_net(&set);
+        let partitioned = NettingEngine::multilateral_net_partitioned_parallel(&set);
+        assert_eq!(
+            serde_json::to_string(&partitioned).unwrap(),
+            serde_json::to_string(&serial).unwrap()
+        );
+    }
 }
@@ -0,0 +1,402 @@
+use crate::core::currency::CurrencyCode;
+use crate::core::ledger::Ledger;
+use crate::core::party::PartyId;
+use crate::optimization::netting::NettingResult;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BTreeMap, HashMap};
+use thiserror::Error;
+
+/// A concrete transfer to execute after netting: `from` pays `to`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SettlementInstruction {
+    pub from: PartyId,
+    pub to: PartyId,
+    pub amount: Decimal,
+    pub currency: CurrencyCode,
+    /// Account debited at the payer.
+    #[serde(default)]
+    pub from_account: String,
+    /// Account credited at the payee.
+    #[serde(default)]
+    pub to_account: String,
+}
+
+/// Account used for a party that names none on its obligations: the
+/// party id itself.
+pub fn default_account(party: &PartyId) -> String {
+    party.to_string()
+}
+
+impl SettlementInstruction {
+    /// Returns true if `party` pays or receives in this instruction.
+    pub fn involves(&self, party: &PartyId) -> bool {
+        &self.from == party || &self.to == party
+    }
+}
+
+/// A (party, currency) pair treated as its own settlement account.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SubAccount {
+    pub party: PartyId,
+    pub currency: CurrencyCode,
+    /// Positive = net creditor, negative = net debtor.
+    pub net_position: Decimal,
+}
+
+/// A net debtor whose available funds do not cover its net position.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Shortfall {
+    pub party: PartyId,
+    pub currency: CurrencyCode,
+    /// Amount the party must pay (absolute value of its net position).
+    pub required: Decimal,
+    /// Funds the party has available in this currency.
+    pub available: Decimal,
+}
+
+impl Shortfall {
+    /// How much the party is short.
+    pub fn amount(&self) -> Decimal {
+        self.required - self.available
+    }
+}
+
+/// A batch cannot settle atomically because some debtors are short of funds.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("batch is infeasible: {} debtor position(s) underfunded", .shortfalls.len())]
+pub struct Infeasibility {
+    /// Every underfunded debtor position, sorted by party then currency.
+    pub shortfalls: Vec<Shortfall>,
+}
+
+/// How net debtors and net creditors are ordered before greedy matching.
+///
+/// The greedy generator always pairs the first outstanding debtor with
+/// the first outstanding creditor, so this ordering fully determines the
+/// resulting instructions.
+#[derive(Debug, Clone, Copy, Default)]
+pub enum SettlementOrdering {
+    /// Largest outstanding amount first, ties broken by party id.
+    #[default]
+    ByAmountDesc,
+    /// Ascending party id, regardless of amount.
+    ByPartyId,
+    /// A caller-supplied comparator over (party, outstanding amount),
+    /// applied to debtors and creditors alike. Amounts are positive on
+    /// both sides.
+    Custom(fn(&(PartyId, Decimal), &(PartyId, Decimal)) -> Ordering),
+}
+
+impl SettlementOrdering {
+    fn compare(&self, a: &(PartyId, Decimal), b: &(PartyId, Decimal)) -> Ordering {
+        match self {
+            SettlementOrdering::ByAmountDesc => b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)),
+            SettlementOrdering::ByPartyId => a.0.cmp(&b.0),
+            SettlementOrdering::Custom(cmp) => cmp(a, b),
+        }
+    }
+}
+
+/// Check that `instructions` settle exactly the net positions of `result`.
+///
+/// Applies every instruction to a fresh ledger (the payer's position
+/// falls, the payee's rises) and compares the non-zero positions against
+/// the result's, per party and currency. A correctness gate for any
+/// instruction generator.
+pub fn verify_instructions(result: &NettingResult, instructions: &[SettlementInstruction]) -> bool {
+    let mut ledger = Ledger::new();
+    for instruction in instructions {
+        ledger.adjust_position(&instruction.from, &instruction.currency, -instruction.amount);
+        ledger.adjust_position(&instruction.to, &instruction.currency, instruction.amount);
+    }
+    let non_zero = |ledger: &Ledger| -> BTreeMap<(PartyId, CurrencyCode), Decimal> {
+        ledger
+            .all_positions()
+            .iter()
+            .filter(|(_, amount)| **amount != Decimal::ZERO)
+            .map(|(key, amount)| (key.clone(), *amount))
+            .collect()
+    };
+    non_zero(&ledger) == non_zero(result.ledger())
+}
+
+/// Parties with an outstanding amount, used for one side of the matching.
+pub(crate) type Side = Vec<(PartyId, Decimal)>;
+
+/// Net debtors and net creditors of each currency, as positive amounts.
+///
+/// Both lists are sorted by `ordering`, so that matching is deterministic
+/// regardless of ledger iteration order.
+pub(crate) fn split_positions(
+    ledger: &Ledger,
+    ordering: SettlementOrdering,
+) -> BTreeMap<CurrencyCode, (Side, Side)> {
+    let mut sides: BTreeMap<CurrencyCode, (Side, Side)> = BTreeMap::new();
+    for ((party, currency), amount) in ledger.all_positions() {
+        let entry = sides.entry(currency.clone()).or_default();
+        if *amount < Decimal::ZERO {
+            entry.0.push((party.clone(), -*amount));
+        } else if *amount > Decimal::ZERO {
+            entry.1.push((party.clone(), *amount));
+        }
+    }
+    for (debtors, creditors) in sides.values_mut() {
+        debtors.sort_by(|a, b| ordering.compare(a, b));
+        creditors.sort_by(|a, b| ordering.compare(a, b));
+    }
+    sides
+}
+
+/// Greedily match net debtors against net creditors, currency by currency.
+///
+/// The first outstanding debtor (per `ordering`) pays the first
+/// outstanding creditor as much as both can absorb; whichever side is
+/// exhausted advances. Each step clears at least one position, so at most
+/// `n - 1` transfers are emitted per currency for `n` parties with
+/// non-zero positions.
+pub(crate) fn greedy_instructions(
+    ledger: &Ledger,
+    ordering: SettlementOrdering,
+) -> Vec<SettlementInstruction> {
+    let mut instructions = Vec::new();
+    for (currency, (debtors, creditors)) in split_positions(ledger, ordering) {
+        match_in_order(&currency, debtors, creditors, &mut instructions);
+    }
+    instructions
+}
+
+/// Settle each currency in as few transfers as a subset-sum heuristic finds.
+///
+/// Minimizing transfers is NP-hard in general: every group of `k` parties
+/// whose balances sum to zero can settle in `k - 1` transfers, so the
+/// optimum maximizes the number of disjoint zero-sum groups. The heuristic
+/// first cancels exact one-to-one matches, then exact groups of two
+/// debtors and one creditor (or one debtor and two creditors), and
+/// greedily matches whatever is left. Per currency it keeps the greedy
+/// [`SettlementOrdering::ByAmountDesc`] matching instead when that is no
+/// longer, so it never emits more transfers than the greedy generator and
+/// at most `n - 1` per currency for `n` parties with non-zero positions.
+pub(crate) fn min_count_instructions(ledger: &Ledger) -> Vec<SettlementInstruction> {
+    let mut instructions = Vec::new();
+    for (currency, (debtors, creditors)) in split_positions(ledger, SettlementOrdering::ByAmountDesc)
+    {
+        let mut greedy = Vec::new();
+        match_in_order(&currency, debtors.clone(), creditors.clone(), &mut greedy);
+        let mut grouped = Vec::new();
+        match_zero_sum_groups(&currency, debtors, creditors, &mut grouped);
+        instructions.extend(if grouped.len() < greedy.len() { grouped } else { greedy });
+    }
+    instructions
+}
+
+/// Settle each currency with whichever of a few candidate matchings costs
+/// least. A heuristic: the cheapest candidate, not the cheapest settlement.
+///
+/// `cost(party, currency, amount)` prices one side of a transfer, signed
+/// like a position: negative for the payer, positive for the payee. Every
+/// candidate settles the same net positions; per currency they are the
+/// greedy [`SettlementOrdering::ByAmountDesc`] matching, the exact-group
+/// matching of [`min_count_instructions`], and a greedy matching that
+/// serves first the parties whose whole position costs most in one
+/// transfer, so a split position falls on a cheaper party. Ties keep the
+/// earlier candidate, so a cost that is linear in the amount reproduces
+/// the plain greedy matching.
+pub(crate) fn cheapest_candidate_instructions(
+    ledger: &Ledger,
+    cost: impl Fn(&PartyId, &CurrencyCode, Decimal) -> Decimal,
+) -> Vec<SettlementInstruction> {
+    let mut instructions = Vec::new();
+    for (currency, (debtors, creditors)) in split_positions(ledger, SettlementOrdering::ByAmountDesc)
+    {
+        let total = |candidate: &[SettlementInstruction]| -> Decimal {
+            candidate
+                .iter()
+                .map(|i| cost(&i.from, &currency, -i.amount) + cost(&i.to, &currency, i.amount))
+                .sum()
+        };
+        let costliest_first = |side: &Side, sign: Decimal| -> Side {
+            let mut side = side.clone();
+            side.sort_by_cached_key(|(party, amount)| {
+                (Reverse(cost(party, &currency, *amount * sign)), Reverse(*amount), party.clone())
+            });
+            side
+        };
+
+        let mut greedy = Vec::new();
+        match_in_order(&currency, debtors.clone(), creditors.clone(), &mut greedy);
+        let mut grouped = Vec::new();
+        match_zero_sum_groups(&currency, debtors.clone(), creditors.clone(), &mut grouped);
+        let mut by_cost = Vec::new();
+        match_in_order(
+            &currency,
+            costliest_first(&debtors, -Decimal::ONE),
+            costliest_first(&creditors, Decimal::ONE),
+            &mut by_cost,
+        );
+
+        let cheapest = [greedy, grouped, by_cost]
+            .into_iter()
+            .map(|candidate| (total(&candidate), candidate))
+            .reduce(|best, next| if next.0 < best.0 { next } else { best })
+            .map(|(_, candidate)| candidate)
+            .unwrap_or_default();
+        instructions.extend(cheapest);
+    }
+    instructions
+}
+
+/// Settle exact-match groups first, then greedily match the remainder.
+fn match_zero_sum_groups(
+    currency: &CurrencyCode,
+    mut debtors: Side,
+    mut creditors: Side,
+    out: &mut Vec<SettlementInstruction>,
+) {
+    // One debtor owes exactly what one creditor is owed
+    let mut d = 0;
+    while d < debtors.len() {
+        match creditors.iter().position(|c| c.1 == debtors[d].1) {
+            Some(c) => {
+                let creditor = creditors.remove(c);
+                let debtor = debtors.remove(d);
+                match_in_order(currency, vec![debtor], vec![creditor], out);
+            }
+            None => d += 1,
+        }
+    }
+
+    // Two parties on one side exactly cover one on the other
+    loop {
+        if let Some((i, j, c)) = find_pair_sum(&debtors, &creditors) {
+            let creditor = creditors.remove(c);
+            let (second, first) = (debtors.remove(j), debtors.remove(i));
+            match_in_order(currency, vec![first, second], vec![creditor], out);
+        } else if let Some((i, j, d)) = find_pair_sum(&creditors, &debtors) {
+            let debtor = debtors.remove(d);
+            let (second, first) = (creditors.remove(j), creditors.remove(i));
+            match_in_order(currency, vec![debtor], vec![first, second], out);
+        } else {
+            break;
+        }
+    }
+
+    match_in_order(currency, debtors, creditors, out);
+}
+
+/// First (i, j, t) with `i < j` and `pairs[i] + pairs[j] == targets[t]`.
+fn find_pair_sum(pairs: &Side, targets: &Side) -> Option<(usize, usize, usize)> {
+    let mut by_amount: HashMap<Decimal, Vec<usize>> = HashMap::new();
+    for (index, (_, amount)) in pairs.iter().enumerate() {
+        by_amount.entry(*amount).or_default().push(index);
+    }
+    for (t, (_, target)) in targets.iter().enumerate() {
+        for (i, (_, amount)) in pairs.iter().enumerate() {
+            let complement = by_amount.get(&(*target - *amount));
+            if let Some(&j) = complement.and_then(|js| js.iter().find(|&&j| j > i)) {
+                return Some((i, j, t));
+            }
+        }
+    }
+    None
+}
+
+/// Walk both sides in the given order, emitting one transfer per step.
+pub(crate) fn match_in_order(
+    currency: &CurrencyCode,
+    mut debtors: Side,
+    mut creditors: Side,
+    out: &mut Vec<SettlementInstruction>,
+) {
+    let (mut d, mut c) = (0, 0);
+    while d < debtors.len() && c < creditors.len() {
+        let amount = debtors[d].1.min(creditors[c].1);
+        if amount > Decimal::ZERO {
+            out.push(SettlementInstruction {
+                from: debtors[d].0.clone(),
+                to: creditors[c].0.clone(),
+                amount,
+                currency: currency.clone(),
+                from_account: default_account(&debtors[d].0),
+                to_account: default_account(&creditors[c].0),
+            });
+        }
+        debtors[d].1 -= amount;
+        creditors[c].1 -= amount;
+        if debtors[d].1 == Decimal::ZERO {
+            d += 1;
+        }
+        if creditors[c].1 == Decimal::ZERO {
+            c += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::obligation::Obligation;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_greedy_clears_all_positions() {
+        let usd = CurrencyCode::new("USD");
+        let mut ledger = Ledger::new();
+        ledger.apply_obligation(&Obligation::new(
+            PartyId::new("A"),
+            PartyId::new("B"),
+            dec!(100),
+            usd.clone(),
+        ));
+        ledger.apply_obligation(&Obligation::new(
+            PartyId::new("A"),
+            PartyId::new("C"),
+            dec!(30),
+            usd.clone(),
+        ));
+        ledger.apply_obligation(&Obligation::new(
+            PartyId::new("D"),
+            PartyId::new("C"),
+            dec!(20),
+            usd.clone(),
+        ));
+
+        let instructions = greedy_instructions(&ledger, SettlementOrdering::ByAmountDesc);
+        assert_eq!(instructions.len(), 3);
+        assert_eq!(instructions[0].from, PartyId::new("A"));
+        assert_eq!(instructions[0].to, PartyId::new("B"));
+        assert_eq!(instructions[0].amount, dec!(100));
+
+        let mut settled = ledger.clone();
+        for ins in &instructions {
+            settled.apply_obligation(&Obligation::new(
+                ins.to.clone(),
+                ins.from.clone(),
+                ins.amount,
+                ins.currency.clone(),
+            ));
+        }
+        assert_eq!(settled.total_net_settlement(), Decimal::ZERO);
+    }
+
+    #[test]
+    fn test_verify_instructions() {
+        use crate::core::obligation::ObligationSet;
+        use crate::optimization::netting::NettingEngine;
+
+        let usd = CurrencyCode::new("USD");
+        let mut set = ObligationSet::new();
+        set.add(Obligation::new(PartyId::new("A"), PartyId::new("B"), dec!(100), usd.clone()));
+        set.add(Obligation::new(PartyId::new("B"), PartyId::new("C"), dec!(70), usd.clone()));
+        set.add(Obligation::new(PartyId::new("C"), PartyId::new("A"), dec!(20), usd.clone()));
+        let result = NettingEngine::multilateral_net(&set);
+
+        let mut instructions = result.settlement_instructions();
+        assert!(verify_instructions(&result, &instructions));
+
+        instructions[0].amount += dec!(0.01);
+        assert!(!verify_instructions(&result, &instructions));
+        assert!(!verify_instructions(&result, &[]));
+    }
+}
@@ -0,0 +1,135 @@
+use crate::core::currency::CurrencyCode;
+use crate::core::obligation::{Obligation, ObligationSet};
+use crate::core::party::PartyId;
+use crate::optimization::netting::NettingResult;
+use crate::optimization::settlement::SettlementInstruction;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// A single participant's view of a clearing run.
+///
+/// Lists the party's own obligations on both sides, its gross flows and
+/// net position per currency, and the settlement instructions it must
+/// execute or will receive.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PartyStatement {
+    pub party: PartyId,
+    /// Obligations where this party is the debtor.
+    pub payable: Vec<Obligation>,
+    /// Obligations where this party is the creditor.
+    pub receivable: Vec<Obligation>,
+    /// Gross amount owed by this party, per currency.
+    pub gross_out: HashMap<CurrencyCode, Decimal>,
+    /// Gross amount owed to this party, per currency.
+    pub gross_in: HashMap<CurrencyCode, Decimal>,
+    /// Net position per currency (positive = net creditor).
+    pub net_positions: HashMap<CurrencyCode, Decimal>,
+    /// Settlement instructions in which this party pays or receives.
+    pub instructions: Vec<SettlementInstruction>,
+}
+
+impl NettingResult {
+    /// Build the statement for one party from this result and the
+    /// obligations it was computed from.
+    pub fn party_statement(&self, party: &PartyId, obligations: &ObligationSet) -> PartyStatement {
+        let mut payable = Vec::new();
+        let mut receivable = Vec::new();
+        let mut gross_out: HashMap<CurrencyCode, Decimal> = HashMap::new();
+        let mut gross_in: HashMap<CurrencyCode, Decimal> = HashMap::new();
+
+        for ob in obligations.obligations() {
+            if ob.debtor() == party {
+                *gross_out
+                    .entry(ob.currency().clone())
+                    .or_insert(Decimal::ZERO) += ob.amount();
+                payable.push(ob.clone());
+            }
+            if ob.creditor() == party {
+                *gross_in
+                    .entry(ob.currency().clone())
+                    .or_insert(Decimal::ZERO) += ob.amount();
+                receivable.push(ob.clone());
+            }
+        }
+
+        let instructions = self
+            .settlement_instructions()
+            .into_iter()
+            .filter(|ins| ins.involves(party))
+            .collect();
+
+        PartyStatement {
+            party: party.clone(),
+            payable,
+            receivable,
+            gross_out,
+            gross_in,
+            net_positions: self.ledger().positions_for_party(party),
+            instructions,
+        }
+    }
+}
+
+impl std::fmt::Display for PartyStatement {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "=== Statement: {} ===", self.party)?;
+
+        let mut currencies: Vec<&CurrencyCode> = self
+            .gross_out
+            .keys()
+            .chain(self.gross_in.keys())
+            .chain(self.net_positions.keys())
+            .collect();
+        currencies.sort();
+        currencies.dedup();
+
+        for currency in currencies {
+            let zero = Decimal::ZERO;
+            writeln!(f, "\n--- {} ---", currency)?;
+            writeln!(f, "  Gross Out: {}", self.gross_out.get(currency).unwrap_or(&zero))?;
+            writeln!(f, "  Gross In:  {}", self.gross_in.get(currency).unwrap_or(&zero))?;
+            writeln!(f, "  Net:       {}", self.net_positions.get(currency).unwrap_or(&zero))?;
+        }
+
+        writeln!(f, "\nSettlement Instructions:")?;
+        for ins in &self.instructions {
+            if ins.from == self.party {
+                writeln!(f, "  Pay {} {} to {}", ins.amount, ins.currency, ins.to)?;
+            } else {
+                writeln!(f, "  Receive {} {} from {}", ins.amount, ins.currency, ins.from)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::optimization::netting::NettingEngine;
+    use crate::simulation::bench_fixtures::brics_network;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_brazil_statement_matches_ledger() {
+        let usd = CurrencyCode::new("USD");
+        let brazil = PartyId::new("BR-TREASURY");
+        let set = brics_network();
+
+        let result = NettingEngine::multilateral_net(&set);
+        let statement = result.party_statement(&brazil, &set);
+
+        assert_eq!(statement.payable.len(), 1);
+        assert_eq!(statement.receivable.len(), 2);
+        assert_eq!(statement.gross_out[&usd], dec!(100_000_000));
+        assert_eq!(statement.gross_in[&usd], dec!(160_000_000));
+        assert_eq!(statement.net_positions[&usd], result.net_position(&brazil, &usd));
+        assert_eq!(statement.net_positions[&usd], dec!(60_000_000));
+
+        // Brazil is a net creditor: it only receives, and receives exactly its net
+        assert!(statement.instructions.iter().all(|ins| ins.to == brazil));
+        let received: Decimal = statement.instructions.iter().map(|ins| ins.amount).sum();
+        assert_eq!(received, dec!(60_000_000));
+    }
+}
@@ -0,0 +1,191 @@
+//! Event-sourced clearing session.
+//!
+//! A [`ClearingSession`] is the stateful façade a service embeds: it
+//! accepts [`ObligationEvent`]s one at a time, keeps the current
+//! obligation set and an incrementally maintained ledger, and can net or
+//! produce settlement instructions at any point.
+
+use crate::core::currency::CurrencyCode;
+use crate::core::event_log::ObligationEvent;
+use crate::core::ledger::Ledger;
+use crate::core::obligation::{Obligation, ObligationSet};
+use crate::core::party::PartyId;
+use crate::error::ClearingError;
+use crate::optimization::netting::{NettingEngine, NettingResult};
+use crate::optimization::settlement::SettlementInstruction;
+use rust_decimal::Decimal;
+
+/// A running clearing session driven by obligation events.
+#[derive(Debug, Clone, Default)]
+pub struct ClearingSession {
+    events: Vec<ObligationEvent>,
+    obligations: ObligationSet,
+    ledger: Ledger,
+    net_total: Decimal,
+}
+
+impl ClearingSession {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Apply one event and record it in the session log.
+    ///
+    /// Semantics match [`replay`](crate::core::event_log::replay):
+    /// reversing or amending an unknown id has no effect. An amendment to
+    /// a non-positive amount is rejected and not recorded.
+    pub fn apply(&mut self, event: ObligationEvent) -> Result<(), ClearingError> {
+        match &event {
+            ObligationEvent::Add(ob) => {
+                self.post(ob, ob.amount());
+                self.obligations.add(ob.clone());
+            }
+            ObligationEvent::Reverse(id, _) => {
+                if let Some(ob) = self.obligations.remove(*id) {
+                    self.post(&ob, -ob.amount());
+                }
+            }
+            ObligationEvent::Amend(id, amount, _) => {
+                if let Some(previous) = self.obligations.amend(*id, *amount)? {
+                    self.post(&previous, *amount - previous.amount());
+                }
+            }
+        }
+        self.events.push(event);
+        Ok(())
+    }
+
+    /// Add one obligation and report exactly which positions changed.
+    ///
+    /// Returns (party, currency, delta) for the debtor and the creditor,
+    /// in that order, so a live view can update only the affected rows.
+    /// The deltas equal the difference between the ledgers before and
+    /// after the add.
+    pub fn add_tracked(&mut self, ob: Obligation) -> Vec<(PartyId, CurrencyCode, Decimal)> {
+        let deltas = vec![
+            (ob.debtor().clone(), ob.currency().clone(), -ob.amount()),
+            (ob.creditor().clone(), ob.currency().clone(), ob.amount()),
+        ];
+        self.post(&ob, ob.amount());
+        self.obligations.add(ob.clone());
+        self.events.push(ObligationEvent::Add(ob));
+        deltas
+    }
+
+    /// Every event applied so far, in order.
+    pub fn events(&self) -> &[ObligationEvent] {
+        &self.events
+    }
+
+    /// The obligations currently outstanding.
+    pub fn obligations(&self) -> &ObligationSet {
+        &self.obligations
+    }
+
+    /// Current net positions.
+    pub fn ledger(&self) -> &Ledger {
+        &self.ledger
+    }
+
+    /// Current net settlement total, maintained incrementally.
+    pub fn net_total(&self) -> Decimal {
+        self.net_total
+    }
+
+    /// Net the current obligations.
+    pub fn result(&self) -> NettingResult {
+        NettingEngine::multilateral_net(&self.obligations)
+    }
+
+    /// Settlement instructions for the current obligations.
+    pub fn settlement_instructions(&self) -> Vec<SettlementInstruction> {
+        self.result().settlement_instructions()
+    }
+
+    /// Move `amount` from the obligation's debtor to its creditor.
+    fn post(&mut self, ob: &Obligation, amount: Decimal) {
+        self.shift(ob.debtor(), ob.currency(), -amount);
+        self.shift(ob.creditor(), ob.currency(), amount);
+    }
+
+    /// Adjust one position, keeping the net total in step.
+    fn shift(&mut self, party: &PartyId, currency: &CurrencyCode, delta: Decimal) {
+        let before = self.ledger.position(party, currency);
+        self.ledger.adjust_position(party, currency, delta);
+        let after = before + delta;
+        self.net_total += after.max(Decimal::ZERO) - before.max(Decimal::ZERO);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::event_log::replay;
+    use chrono::Utc;
+    use rust_decimal_macros::dec;
+
+    fn ob(debtor: &str, creditor: &str, amount: Decimal) -> Obligation {
+        let usd = CurrencyCode::new("USD");
+        Obligation::new(PartyId::new(debtor), PartyId::new(creditor), amount, usd)
+    }
+
+    #[test]
+    fn test_running_net_matches_batch_netting() {
+        let ab = ob("A", "B", dec!(100));
+        let bc = ob("B", "C", dec!(80));
+        let ca = ob("C", "A", dec!(60));
+        let now = Utc::now();
+
+        let mut session = ClearingSession::new();
+        let events = vec![
+            ObligationEvent::Add(ab.clone()),
+            ObligationEvent::Add(bc.clone()),
+            ObligationEvent::Add(ca.clone()),
+            ObligationEvent::Amend(bc.id(), dec!(120), now),
+            ObligationEvent::Reverse(ca.id(), now),
+        ];
+        for event in events {
+            session.apply(event).unwrap();
+            let batch = NettingEngine::multilateral_net(&replay(session.events()));
+            assert_eq!(session.net_total(), batch.net_total());
+            assert_eq!(session.ledger().all_positions(), batch.ledger().all_positions());
+        }
+
+        // A→B 100, B→C 120
+        assert_eq!(session.net_total(), dec!(120));
+        assert_eq!(session.obligations().len(), 2);
+        let paid: Decimal = session.settlement_instructions().iter().map(|i| i.amount).sum();
+        assert_eq!(paid, session.result().net_total());
+
+        assert!(session
+            .apply(ObligationEvent::Amend(ab.id(), Decimal::ZERO, now))
+            .is_err());
+        assert_eq!(session.events().len(), 5);
+    }
+
+    #[test]
+    fn test_add_tracked_reports_changed_positions() {
+        let usd = CurrencyCode::new("USD");
+        let (a, b, c) = (PartyId::new("A"), PartyId::new("B"), PartyId::new("C"));
+        let mut session = ClearingSession::new();
+        session.add_tracked(Obligation::new(a.clone(), b.clone(), dec!(100), usd.clone()));
+        let before = session.result();
+
+        let deltas =
+            session.add_tracked(Obligation::new(b.clone(), c.clone(), dec!(40), usd.clone()));
+        let after = session.result();
+
+        assert_eq!(
+            deltas,
+            vec![(b.clone(), usd.clone(), dec!(-40)), (c.clone(), usd.clone(), dec!(40))]
+        );
+        for (party, currency, delta) in &deltas {
+            assert_eq!(
+                after.net_position(party, currency) - before.net_position(party, currency),
+                *delta
+            );
+        }
+        assert_eq!(after.net_position(&a, &usd), before.net_position(&a, &usd));
+        assert_eq!(session.net_total(), after.net_total());
+    }
+}
@@ -0,0 +1,60 @@
+//! Adversarial networks with no netting opportunity.
+//!
+//! The random generators in [`stress_test`] produce networks where cycles
+//! arise by chance. These networks are the opposite extreme: acyclic and
+//! arranged so that multilateral netting saves nothing, which exercises
+//! the engine on its worst case.
+//!
+//! [`stress_test`]: crate::simulation::stress_test
+
+use crate::core::currency::CurrencyCode;
+use crate::core::obligation::{Obligation, ObligationSet};
+use crate::core::party::PartyId;
+use rust_decimal::Decimal;
+
+/// A network in which netting cannot reduce settlement at all.
+///
+/// The first half of the parties (rounded up) only pay and the rest only
+/// receive: every payer owes every receiver, so the graph is a complete
+/// bipartite DAG with no party on both sides. Every net position then
+/// equals that party's gross flow, and net settlement equals gross.
+/// Amounts are deterministic and distinct per pair. Fewer than two
+/// parties yield an empty set.
+pub fn worst_case_network(parties: usize, currency: &CurrencyCode) -> ObligationSet {
+    let party = |i: usize| PartyId::new(format!("PARTY-{:03}", i));
+    let payers = parties.div_ceil(2);
+
+    let mut set = ObligationSet::new();
+    for debtor in 0..payers {
+        for creditor in payers..parties {
+            let amount = Decimal::from(1_000 * ((debtor + 1) * parties + creditor + 1));
+            set.add(Obligation::new(party(debtor), party(creditor), amount, currency.clone()));
+        }
+    }
+    set
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::cycle_detection::find_cycles;
+    use crate::graph::payment_graph::PaymentGraph;
+    use crate::optimization::netting::NettingEngine;
+
+    #[test]
+    fn test_worst_case_network_has_no_savings() {
+        let usd = CurrencyCode::new("USD");
+        let set = worst_case_network(9, &usd);
+        assert_eq!(set.len(), 5 * 4);
+
+        let graph = PaymentGraph::from_obligations(set.obligations().to_vec());
+        assert!(find_cycles(&graph, &usd).is_empty());
+
+        let result = NettingEngine::multilateral_net(&set);
+        assert!(result.is_valid());
+        assert!(result.savings_percent() < 0.01);
+        assert_eq!(result.net_total(), result.gross_total());
+
+        assert!(worst_case_network(1, &usd).is_empty());
+    }
+}
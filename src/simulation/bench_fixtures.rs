@@ -0,0 +1,84 @@
+//! Seeded networks for benchmarks.
+//!
+//! Benchmarks built on [`generate_random_network`] measure a different
+//! network on every run. These fixtures always produce the same network
+//! for a given size, so timings are comparable across runs and machines.
+//! [`brics_network`] is a small hand-written network shared by tests.
+//!
+//! [`generate_random_network`]: crate::simulation::stress_test::generate_random_network
+
+use crate::core::currency::CurrencyCode;
+use crate::core::obligation::{Obligation, ObligationSet};
+use crate::core::party::PartyId;
+use crate::simulation::stress_test::{generate_seeded_network, NetworkConfig};
+use rust_decimal_macros::dec;
+
+/// Seed shared by every fixture network.
+pub const FIXTURE_SEED: u64 = 20_240_301;
+
+/// The standard benchmark network with `size` parties.
+///
+/// Small networks (up to 10 parties) average 5 obligations per party,
+/// larger ones 10, in a single currency.
+///
+/// # Panics
+///
+/// Panics if `size` is below 2.
+pub fn standard_network(size: usize) -> ObligationSet {
+    let config = NetworkConfig {
+        party_count: size,
+        avg_obligations_per_party: if size <= 10 { 5 } else { 10 },
+        ..Default::default()
+    };
+    generate_seeded_network(&config, FIXTURE_SEED)
+}
+
+/// Eight USD obligations among five central banks.
+///
+/// No two banks owe each other, so bilateral offsetting saves nothing
+/// and every saving comes from the cycles multilateral netting removes.
+/// Brazil (`BR-TREASURY`) ends as a net creditor of 60 million.
+pub fn brics_network() -> ObligationSet {
+    let usd = CurrencyCode::new("USD");
+    let brazil = PartyId::new("BR-TREASURY");
+    let india = PartyId::new("IN-RBI");
+    let china = PartyId::new("CN-PBOC");
+    let russia = PartyId::new("RU-CBR");
+    let south_africa = PartyId::new("ZA-SARB");
+
+    let mut set = ObligationSet::new();
+    set.add(Obligation::new(brazil.clone(), india.clone(), dec!(100_000_000), usd.clone()));
+    set.add(Obligation::new(india.clone(), china.clone(), dec!(80_000_000), usd.clone()));
+    set.add(Obligation::new(china.clone(), russia.clone(), dec!(120_000_000), usd.clone()));
+    set.add(Obligation::new(russia.clone(), brazil.clone(), dec!(90_000_000), usd.clone()));
+    set.add(Obligation::new(south_africa.clone(), india.clone(), dec!(40_000_000), usd.clone()));
+    set.add(Obligation::new(china.clone(), brazil, dec!(70_000_000), usd.clone()));
+    set.add(Obligation::new(india, russia.clone(), dec!(30_000_000), usd.clone()));
+    set.add(Obligation::new(russia, south_africa, dec!(25_000_000), usd));
+    set
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal::Decimal;
+    use rust_decimal_macros::dec;
+
+    fn gross(set: &ObligationSet) -> Decimal {
+        set.obligations().iter().map(|ob| ob.amount()).sum()
+    }
+
+    #[test]
+    fn test_fixtures_are_stable() {
+        for size in [10, 100] {
+            let first = standard_network(size);
+            let second = standard_network(size);
+            assert_eq!(first.len(), second.len());
+            assert_eq!(gross(&first), gross(&second));
+        }
+        // Pinned so a change in generation is caught, not silently benchmarked
+        let small = standard_network(10);
+        assert_eq!(small.len(), 50);
+        assert_eq!(gross(&small), dec!(252635664.64));
+    }
+}
@@ -5,13 +5,19 @@
 //!
 //! # Status: Phase 2 — interface defined, implementation in progress
 
-use crate::core::currency::CurrencyCode;
+use crate::core::currency::{pair_map_serde, CurrencyCode, FxRateTable};
+use crate::core::obligation::ObligationSet;
+use crate::error::ClearingError;
+use crate::optimization::netting::NettingEngine;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use rust_decimal::prelude::FromPrimitive;
 use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
 /// Result of an FX shock scenario.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct FxShockResult {
     /// Description of the shock applied.
     pub scenario: String,
@@ -27,14 +33,121 @@ pub struct FxShockResult {
 ///
 /// Defines shock magnitudes to apply to exchange rates
 /// for stress testing settlement positions.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct FxShockConfig {
     /// Shocks to apply: currency pair -> percentage change (e.g., 0.10 = 10% depreciation).
+    #[serde(with = "pair_map_serde")]
     pub shocks: HashMap<(CurrencyCode, CurrencyCode), Decimal>,
 }
 
+/// Distribution of the base-currency net settlement under FX uncertainty.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NetConfidence {
+    /// Mean net settlement across all iterations.
+    pub mean: Decimal,
+    /// 5th percentile (nearest rank).
+    pub p5: Decimal,
+    /// 95th percentile (nearest rank).
+    pub p95: Decimal,
+    /// Number of simulated rate scenarios.
+    pub iterations: usize,
+}
+
+/// Monte Carlo confidence interval on the net settlement in `base`.
+///
+/// Netting is per currency, so positions are computed once; only their
+/// valuation in `base` is uncertain. Each iteration moves every currency
+/// with an entry in `vols` to `rate × exp(σ·z)` against `base`, where σ
+/// is its volatility over the horizon and z a standard normal draw, and
+/// sums the per-currency net settlement at those rates. Currencies are
+/// drawn in code order, so a given `seed` always yields the same result.
+/// Errors if a rate to `base` is missing or `iters` is zero.
+pub fn net_with_confidence(
+    obligations: &ObligationSet,
+    rates: &FxRateTable,
+    base: &CurrencyCode,
+    vols: &HashMap<CurrencyCode, f64>,
+    seed: u64,
+    iters: usize,
+) -> Result<NetConfidence, ClearingError> {
+    if iters == 0 {
+        return Err(ClearingError::InvalidConfig("iterations must be positive".into()));
+    }
+    let result = NettingEngine::multilateral_net(obligations);
+    let positions = result
+        .currency_breakdown()
+        .values()
+        .map(|b| Ok((b.net_total * rates.get_rate(&b.currency, base)?, vols.get(&b.currency))))
+        .collect::<Result<Vec<(Decimal, Option<&f64>)>, ClearingError>>()?;
+
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut samples: Vec<Decimal> = (0..iters)
+        .map(|_| {
+            positions
+                .iter()
+                .map(|(value, vol)| match vol {
+                    Some(vol) => *value * lognormal_factor(&mut rng, **vol),
+                    None => *value,
+                })
+                .sum()
+        })
+        .collect();
+    samples.sort();
+
+    let rank = |p: f64| samples[((p * iters as f64).ceil() as usize).clamp(1, iters) - 1];
+    let total: Decimal = samples.iter().sum();
+    Ok(NetConfidence {
+        mean: total / Decimal::from(iters),
+        p5: rank(0.05),
+        p95: rank(0.95),
+        iterations: iters,
+    })
+}
+
+/// `exp(σ·z)` for a standard normal z (Box–Muller), to 8 decimal places.
+fn lognormal_factor<R: Rng>(rng: &mut R, vol: f64) -> Decimal {
+    let u1: f64 = 1.0 - rng.gen::<f64>();
+    let u2: f64 = rng.gen();
+    let z = (-2.0 * u1.ln()).sqrt() * (2.0 * std::f64::consts::PI * u2).cos();
+    Decimal::from_f64((vol * z).exp())
+        .unwrap_or(Decimal::ONE)
+        .round_dp(8)
+}
+
 // TODO: Phase 2 implementation
 // - Apply FX shocks to obligation sets
 // - Recompute netting under stressed rates
-// - Monte Carlo simulation over rate distributions
 // - VaR-style exposure reporting
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::obligation::Obligation;
+    use crate::core::party::PartyId;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_net_with_confidence_is_deterministic() {
+        let usd = CurrencyCode::new("USD");
+        let brl = CurrencyCode::new("BRL");
+        let mut set = ObligationSet::new();
+        set.add(Obligation::new(PartyId::new("A"), PartyId::new("B"), dec!(1000), usd.clone()));
+        set.add(Obligation::new(PartyId::new("B"), PartyId::new("A"), dec!(5000), brl.clone()));
+        let mut rates = FxRateTable::new(usd.clone());
+        rates.set_rate(brl.clone(), usd.clone(), dec!(0.2)).unwrap();
+        let vols = HashMap::from([(brl.clone(), 0.15)]);
+
+        let ci = net_with_confidence(&set, &rates, &usd, &vols, 42, 1000).unwrap();
+
+        assert_eq!(ci, net_with_confidence(&set, &rates, &usd, &vols, 42, 1000).unwrap());
+        assert_eq!(ci.p5, dec!(1781.74845));
+        assert_eq!(ci.p95, dec!(2260.85156));
+        assert_eq!(ci.mean, dec!(2009.78342769));
+
+        // Without volatility every scenario values at the spot rate
+        let flat = net_with_confidence(&set, &rates, &usd, &HashMap::new(), 42, 10).unwrap();
+        assert_eq!((flat.p5, flat.mean, flat.p95), (dec!(2000), dec!(2000), dec!(2000)));
+
+        assert!(net_with_confidence(&set, &rates, &usd, &vols, 42, 0).is_err());
+    }
+}
@@ -0,0 +1,197 @@
+//! Experiment driver comparing netting algorithms on generated networks.
+//!
+//! Each configuration is generated deterministically from a seed, then
+//! settled gross, bilaterally, multilaterally per currency, and
+//! multilaterally across currencies in the rate table's base currency.
+//! The report tabulates the settlement value and number of transfers
+//! each algorithm needs.
+
+use crate::core::currency::FxRateTable;
+use crate::error::ClearingError;
+use crate::graph::payment_graph::PaymentGraph;
+use crate::optimization::netting::NettingEngine;
+use crate::simulation::stress_test::{try_generate_seeded_network, NetworkConfig};
+use rust_decimal::Decimal;
+use serde::Serialize;
+
+/// Settlement cost of one algorithm on one network.
+#[derive(Debug, Clone, Serialize)]
+pub struct AlgorithmStats {
+    /// Total value that must still be transferred.
+    pub settlement_total: Decimal,
+    /// Gross total minus settlement total.
+    pub savings: Decimal,
+    /// Number of transfers needed to settle.
+    pub transfers: usize,
+}
+
+/// One generated network and how each algorithm settles it.
+#[derive(Debug, Clone, Serialize)]
+pub struct ComparisonRow {
+    /// Index of the configuration in the input.
+    pub config_index: usize,
+    /// Seed the network was generated from.
+    pub seed: u64,
+    pub party_count: usize,
+    pub obligation_count: usize,
+    pub gross_total: Decimal,
+    /// Pairwise offsetting of mutual obligations per currency.
+    pub bilateral: AlgorithmStats,
+    /// Full multilateral netting per currency.
+    pub multilateral: AlgorithmStats,
+    /// Multilateral netting of every currency converted into the rate
+    /// table's base currency; amounts are in that currency.
+    pub cross_currency: AlgorithmStats,
+}
+
+/// Tabulated results of [`run_comparison`], one row per configuration.
+#[derive(Debug, Clone, Serialize)]
+pub struct ComparisonReport {
+    pub rows: Vec<ComparisonRow>,
+}
+
+/// Generate one network per configuration and compare netting algorithms.
+///
+/// Configuration `i` is generated with seed `seed + i`, so the whole
+/// report is reproducible from `seed`. Cross-currency netting converts
+/// through `rates` (see [`NettingEngine::multilateral_net_with_fx`]).
+/// Errors if a configuration is invalid or a required rate is missing.
+pub fn run_comparison(
+    configs: Vec<NetworkConfig>,
+    seed: u64,
+    rates: &FxRateTable,
+) -> Result<ComparisonReport, ClearingError> {
+    let rows = configs
+        .iter()
+        .enumerate()
+        .map(|(index, config)| {
+            let network_seed = seed.wrapping_add(index as u64);
+            let set = try_generate_seeded_network(config, network_seed)?;
+            let gross_total = set.gross_total();
+
+            let bilateral_graph = PaymentGraph::from_obligations(set.obligations().to_vec())
+                .bilateral_compress();
+            let bilateral_total = bilateral_graph.gross_total();
+
+            let result = NettingEngine::multilateral_net(&set);
+            let converted =
+                NettingEngine::multilateral_net_with_fx(&set, rates, &rates.base_currency)?;
+
+            Ok(ComparisonRow {
+                config_index: index,
+                seed: network_seed,
+                party_count: config.party_count,
+                obligation_count: set.len(),
+                gross_total,
+                bilateral: AlgorithmStats {
+                    settlement_total: bilateral_total,
+                    savings: gross_total - bilateral_total,
+                    transfers: bilateral_graph.obligation_count(),
+                },
+                multilateral: AlgorithmStats {
+                    settlement_total: result.net_total(),
+                    savings: result.savings(),
+                    transfers: result.settlement_instructions().len(),
+                },
+                cross_currency: AlgorithmStats {
+                    settlement_total: converted.net_total(),
+                    savings: converted.savings(),
+                    transfers: converted.settlement_instructions().len(),
+                },
+            })
+        })
+        .collect::<Result<_, ClearingError>>()?;
+    Ok(ComparisonReport { rows })
+}
+
+impl std::fmt::Display for ComparisonReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "{:>3} {:>8} {:>12} {:>18} {:>18} {:>6} {:>18} {:>6} {:>18} {:>6}",
+            "#",
+            "parties",
+            "obligations",
+            "gross",
+            "bilateral",
+            "txns",
+            "multilateral",
+            "txns",
+            "cross-currency",
+            "txns"
+        )?;
+        for row in &self.rows {
+            writeln!(
+                f,
+                "{:>3} {:>8} {:>12} {:>18} {:>18} {:>6} {:>18} {:>6} {:>18} {:>6}",
+                row.config_index,
+                row.party_count,
+                row.obligation_count,
+                row.gross_total,
+                row.bilateral.settlement_total,
+                row.bilateral.transfers,
+                row.multilateral.settlement_total,
+                row.multilateral.transfers,
+                row.cross_currency.settlement_total,
+                row.cross_currency.transfers,
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::currency::CurrencyCode;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_run_comparison_two_configs() {
+        let usd = CurrencyCode::new("USD");
+        let brl = CurrencyCode::new("BRL");
+        let mut rates = FxRateTable::new(usd.clone());
+        rates.set_rate(brl.clone(), usd.clone(), dec!(0.20)).unwrap();
+
+        let tiny = |party_count| NetworkConfig {
+            party_count,
+            currencies: vec![usd.clone(), brl.clone()],
+            avg_obligations_per_party: 2,
+            ..Default::default()
+        };
+        let report = run_comparison(vec![tiny(3), tiny(4)], 42, &rates).unwrap();
+        assert_eq!(report.rows.len(), 2);
+
+        for (row, config) in report.rows.iter().zip([tiny(3), tiny(4)]) {
+            // Multilateral never needs more value than bilateral, which
+            // never needs more than gross settlement
+            assert!(row.multilateral.settlement_total <= row.bilateral.settlement_total);
+            assert!(row.bilateral.settlement_total <= row.gross_total);
+            assert!(row.bilateral.transfers <= row.obligation_count);
+
+            // Netting across currencies never needs more than netting each
+            // currency alone, both valued in USD
+            let set = try_generate_seeded_network(&config, row.seed).unwrap();
+            let per_currency: Decimal = NettingEngine::multilateral_net(&set)
+                .currency_breakdown()
+                .values()
+                .map(|b| rates.convert(b.net_total, &b.currency, &usd).unwrap())
+                .sum();
+            assert!(row.cross_currency.settlement_total <= per_currency);
+            assert!(row.cross_currency.savings >= Decimal::ZERO);
+        }
+
+        let again = run_comparison(vec![tiny(3), tiny(4)], 42, &rates).unwrap();
+        assert_eq!(report.rows[1].gross_total, again.rows[1].gross_total);
+        assert_eq!(
+            report.rows[1].cross_currency.settlement_total,
+            again.rows[1].cross_currency.settlement_total
+        );
+
+        let unpriced = FxRateTable::new(usd.clone());
+        assert!(matches!(
+            run_comparison(vec![tiny(3)], 42, &unpriced),
+            Err(ClearingError::Fx(_))
+        ));
+    }
+}
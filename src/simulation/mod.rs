@@ -1,2 +1,5 @@
+pub mod adversarial;
+pub mod bench_fixtures;
 pub mod fx_volatility;
+pub mod harness;
 pub mod stress_test;
@@ -5,14 +5,19 @@
 //!
 //! # Status: Phase 2 — basic random generation implemented
 
-use crate::core::currency::CurrencyCode;
+use crate::core::currency::{CurrencyCode, FxError, FxRateTable};
 use crate::core::obligation::{Obligation, ObligationSet};
 use crate::core::party::PartyId;
-use rand::Rng;
+use crate::error::ClearingError;
+use crate::optimization::netting::{NettingEngine, NettingResult};
+use crate::simulation::fx_volatility::{FxShockConfig, FxShockResult};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
 use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
 
 /// Configuration for generating a random obligation network.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct NetworkConfig {
     /// Number of parties in the network.
     pub party_count: usize,
@@ -38,9 +43,70 @@ impl Default for NetworkConfig {
     }
 }
 
+impl NetworkConfig {
+    /// Check that a network can be generated from this configuration.
+    pub fn validate(&self) -> Result<(), ClearingError> {
+        if self.party_count < 2 {
+            return Err(ClearingError::InvalidConfig(format!(
+                "need at least 2 parties, got {}",
+                self.party_count
+            )));
+        }
+        if self.currencies.is_empty() {
+            return Err(ClearingError::InvalidConfig("no currencies configured".into()));
+        }
+        if self.min_amount >= self.max_amount {
+            return Err(ClearingError::InvalidConfig(format!(
+                "min_amount {} must be below max_amount {}",
+                self.min_amount, self.max_amount
+            )));
+        }
+        Ok(())
+    }
+}
+
 /// Generate a random obligation network for testing.
+///
+/// # Panics
+///
+/// Panics if `config` is invalid (see [`NetworkConfig::validate`]).
 pub fn generate_random_network(config: &NetworkConfig) -> ObligationSet {
-    let mut rng = rand::thread_rng();
+    expect_network(generate_network_with_rng(config, &mut rand::thread_rng()))
+}
+
+/// Generate a reproducible random obligation network.
+///
+/// The same `config` and `seed` always produce the same parties, amounts
+/// and currencies (obligation ids and timestamps still differ).
+///
+/// # Panics
+///
+/// Panics if `config` is invalid; see [`try_generate_seeded_network`].
+pub fn generate_seeded_network(config: &NetworkConfig, seed: u64) -> ObligationSet {
+    expect_network(try_generate_seeded_network(config, seed))
+}
+
+/// Generate a reproducible random obligation network, rejecting an
+/// invalid `config` instead of panicking.
+pub fn try_generate_seeded_network(
+    config: &NetworkConfig,
+    seed: u64,
+) -> Result<ObligationSet, ClearingError> {
+    generate_network_with_rng(config, &mut StdRng::seed_from_u64(seed))
+}
+
+fn expect_network(result: Result<ObligationSet, ClearingError>) -> ObligationSet {
+    match result {
+        Ok(set) => set,
+        Err(e) => panic!("{}", e),
+    }
+}
+
+fn generate_network_with_rng<R: Rng>(
+    config: &NetworkConfig,
+    rng: &mut R,
+) -> Result<ObligationSet, ClearingError> {
+    config.validate()?;
     let mut set = ObligationSet::new();
 
     let parties: Vec<PartyId> = (0..config.party_count)
@@ -67,16 +133,113 @@ pub fn generate_random_network(config: &NetworkConfig) -> ObligationSet {
             .round_dp(2);
 
         if amount > Decimal::ZERO {
-            set.add(Obligation::new(
+            set.add(Obligation::try_new(
                 parties[debtor_idx].clone(),
                 parties[creditor_idx].clone(),
                 amount,
                 config.currencies[currency_idx].clone(),
-            ));
+            )?);
+        }
+    }
+
+    Ok(set)
+}
+
+/// A self-contained, reproducible stress test.
+///
+/// Bundles everything needed to regenerate a network and its FX stress
+/// results, so a scenario can be serialized to JSON, shared, and rerun
+/// elsewhere with identical results.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StressScenario {
+    pub network: NetworkConfig,
+    pub seed: u64,
+    /// Rates for valuing net positions in their base currency. Without
+    /// rates, FX shocks are not evaluated.
+    #[serde(default)]
+    pub rates: Option<FxRateTable>,
+    #[serde(default)]
+    pub shocks: FxShockConfig,
+}
+
+/// Outcome of running a [`StressScenario`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StressReport {
+    pub obligation_count: usize,
+    pub gross_total: Decimal,
+    pub net_total: Decimal,
+    pub savings: Decimal,
+    /// One result per shocked pair, sorted by pair.
+    pub fx_shocks: Vec<FxShockResult>,
+}
+
+impl StressScenario {
+    /// Regenerate the network from the seed, net it, and apply each shock.
+    ///
+    /// A shock of `s` on (from, to) moves that rate to `rate × (1 − s)`.
+    /// Net totals are valued in the rate table's base currency: the
+    /// per-currency net settlement summed at baseline and at shocked
+    /// rates. Errors if the network config is invalid or a required rate
+    /// is missing.
+    pub fn run(&self) -> Result<StressReport, ClearingError> {
+        let set = try_generate_seeded_network(&self.network, self.seed)?;
+        let result = NettingEngine::multilateral_net(&set);
+
+        let mut fx_shocks = Vec::new();
+        if let Some(rates) = &self.rates {
+            let baseline_net = net_in_base(&result, rates)?;
+            let mut pairs: Vec<(&(CurrencyCode, CurrencyCode), &Decimal)> =
+                self.shocks.shocks.iter().collect();
+            pairs.sort();
+            for ((from, to), shock) in pairs {
+                let mut shocked = rates.clone();
+                let rate = rates.get_rate(from, to)?;
+                shocked.set_rate(from.clone(), to.clone(), rate * (Decimal::ONE - shock))?;
+                let shocked_net = net_in_base(&result, &shocked)?;
+                fx_shocks.push(FxShockResult {
+                    scenario: format!("{}/{} {}%", from, to, -shock * Decimal::from(100)),
+                    baseline_net,
+                    shocked_net,
+                    impact: shocked_net - baseline_net,
+                });
+            }
+        }
+
+        Ok(StressReport {
+            obligation_count: set.len(),
+            gross_total: result.gross_total(),
+            net_total: result.net_total(),
+            savings: result.savings(),
+            fx_shocks,
+        })
+    }
+}
+
+impl std::fmt::Display for StressReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "=== Stress Report ===")?;
+        writeln!(f, "Obligations:    {}", self.obligation_count)?;
+        writeln!(f, "Gross Total:    {}", self.gross_total)?;
+        writeln!(f, "Net Total:      {}", self.net_total)?;
+        writeln!(f, "Savings:        {}", self.savings)?;
+        for shock in &self.fx_shocks {
+            writeln!(f, "\n--- {} ---", shock.scenario)?;
+            writeln!(f, "  Baseline net: {}", shock.baseline_net)?;
+            writeln!(f, "  Shocked net:  {}", shock.shocked_net)?;
+            writeln!(f, "  Impact:       {}", shock.impact)?;
         }
+        Ok(())
     }
+}
 
-    set
+/// Per-currency net settlement summed in the table's base currency.
+fn net_in_base(result: &NettingResult, rates: &FxRateTable) -> Result<Decimal, FxError> {
+    let mut breakdown: Vec<_> = result.currency_breakdown().values().collect();
+    breakdown.sort_by(|a, b| a.currency.cmp(&b.currency));
+    breakdown
+        .into_iter()
+        .map(|b| rates.convert(b.net_total, &b.currency, &rates.base_currency))
+        .sum()
 }
 
 #[cfg(test)]
@@ -113,4 +276,66 @@ mod tests {
         // In a random network, netting should generally save something
         assert!(result.net_total() <= result.gross_total());
     }
+
+    #[test]
+    fn test_seeded_network_is_reproducible() {
+        let config = NetworkConfig::default();
+        let summary = |set: &ObligationSet| -> Vec<String> {
+            set.obligations()
+                .iter()
+                .map(|o| format!("{}>{}:{}", o.debtor(), o.creditor(), o.amount()))
+                .collect()
+        };
+        let a = generate_seeded_network(&config, 7);
+        let b = generate_seeded_network(&config, 7);
+        assert_eq!(summary(&a), summary(&b));
+        assert_ne!(summary(&a), summary(&generate_seeded_network(&config, 8)));
+    }
+
+    #[test]
+    fn test_invalid_scenario_returns_invalid_config() {
+        let scenario = StressScenario {
+            network: NetworkConfig {
+                party_count: 1,
+                ..Default::default()
+            },
+            seed: 7,
+            rates: None,
+            shocks: FxShockConfig::default(),
+        };
+        assert!(matches!(scenario.run(), Err(ClearingError::InvalidConfig(_))));
+    }
+
+    #[test]
+    fn test_scenario_round_trips_to_identical_report() {
+        use rust_decimal_macros::dec;
+
+        let usd = CurrencyCode::new("USD");
+        let brl = CurrencyCode::new("BRL");
+        let mut rates = FxRateTable::new(usd.clone());
+        rates.set_rate(brl.clone(), usd.clone(), dec!(0.2)).unwrap();
+        let mut shocks = FxShockConfig::default();
+        shocks.shocks.insert((brl.clone(), usd.clone()), dec!(0.10));
+
+        let scenario = StressScenario {
+            network: NetworkConfig {
+                party_count: 6,
+                currencies: vec![usd, brl],
+                ..Default::default()
+            },
+            seed: 2024,
+            rates: Some(rates),
+            shocks,
+        };
+
+        let json = serde_json::to_string(&scenario).unwrap();
+        let reloaded: StressScenario = serde_json::from_str(&json).unwrap();
+
+        let report = scenario.run().unwrap();
+        assert_eq!(report, reloaded.run().unwrap());
+        assert_eq!(report.fx_shocks.len(), 1);
+        assert_eq!(report.fx_shocks[0].scenario, "BRL/USD -10.00%");
+        // Weaker BRL shrinks the USD value of BRL settlement
+        assert!(report.fx_shocks[0].impact <= Decimal::ZERO);
+    }
 }
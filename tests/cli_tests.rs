@@ -0,0 +1,146 @@
+use std::fs;
+use std::path::PathBuf;
+use std::process::{Command, Output};
+
+/// Write `content` to a uniquely named file in the system temp directory.
+fn write_temp(name: &str, content: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!(
+        "clearing-engine-{}-{}.json",
+        name,
+        std::process::id()
+    ));
+    fs::write(&path, content).unwrap();
+    path
+}
+
+fn run(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_clearing-engine"))
+        .args(args)
+        .output()
+        .expect("failed to run clearing-engine binary")
+}
+
+/// A malformed amount is rejected with the index of the offending obligation.
+#[test]
+fn net_rejects_malformed_amount_with_index() {
+    let path = write_temp(
+        "malformed-amount",
+        r#"{
+  "obligations": [
+    { "from": "A", "to": "B", "amount": "100", "currency": "USD" },
+    { "from": "B", "to": "C", "amount": "1e5", "currency": "USD" }
+  ]
+}"#,
+    );
+
+    let output = run(&["net", "--input", path.to_str().unwrap()]);
+    fs::remove_file(&path).ok();
+
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("obligations[1]"), "stderr was: {}", stderr);
+    assert!(stderr.contains("1e5"), "stderr was: {}", stderr);
+}
+
+/// A party owing itself is reported cleanly instead of panicking.
+#[test]
+fn net_rejects_self_obligation() {
+    let path = write_temp(
+        "self-obligation",
+        r#"{
+  "obligations": [
+    { "from": "A", "to": "A", "amount": "100", "currency": "USD" }
+  ]
+}"#,
+    );
+
+    let output = run(&["net", "--input", path.to_str().unwrap()]);
+    fs::remove_file(&path).ok();
+
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("obligations[0]: debtor and creditor are both A"), "{}", stderr);
+    assert!(!stderr.contains("panicked"), "{}", stderr);
+}
+
+/// `net --format csv` prints the summary block followed by position rows.
+#[test]
+fn net_outputs_csv() {
+    let output = run(&[
+        "net",
+        "--input",
+        concat!(env!("CARGO_MANIFEST_DIR"), "/examples/sample_obligations.json"),
+        "--format",
+        "csv",
+    ]);
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.starts_with("gross_total,net_total,savings,savings_percent,valid\n"));
+    assert!(stdout.contains("party,currency,net_position,status\n"));
+    assert!(stdout.contains("BR-TREASURY,USD,60000000,CREDITOR\n"));
+}
+
+/// `net --min-savings` exits with code 2 when the run saves too little.
+#[test]
+fn net_min_savings_exit_code() {
+    let path = write_temp(
+        "low-savings",
+        r#"{"obligations": [
+    { "from": "A", "to": "B", "amount": "100" },
+    { "from": "B", "to": "A", "amount": "1" }
+]}"#,
+    );
+    let input = path.to_str().unwrap();
+
+    let skipped = run(&["net", "--input", input, "--min-savings", "5"]);
+    let accepted = run(&["net", "--input", input, "--min-savings", "1"]);
+    fs::remove_file(&path).ok();
+
+    assert_eq!(skipped.status.code(), Some(2));
+    assert!(skipped.stdout.is_empty());
+    assert!(String::from_utf8_lossy(&skipped.stderr).contains("skipping"));
+    assert!(accepted.status.success());
+}
+
+/// `stress` with a fixed seed prints the same report on every run.
+#[test]
+fn stress_is_deterministic_for_seed() {
+    let args = [
+        "stress",
+        "--parties",
+        "8",
+        "--obligations",
+        "24",
+        "--currencies",
+        "USD,BRL",
+        "--seed",
+        "7",
+        "--rate",
+        "BRL/USD=0.2",
+        "--shock",
+        "BRL/USD=0.1",
+    ];
+    let first = run(&args);
+    let second = run(&args);
+
+    assert!(first.status.success(), "stderr: {}", String::from_utf8_lossy(&first.stderr));
+    assert_eq!(first.stdout, second.stdout);
+    let stdout = String::from_utf8_lossy(&first.stdout);
+    assert!(stdout.contains("=== Stress Report ==="));
+    assert!(stdout.contains("--- BRL/USD -10.0% ---"), "stdout was: {}", stdout);
+
+    let other_seed = run(&["stress", "--parties", "8", "--obligations", "24", "--seed", "8"]);
+    assert_ne!(first.stdout, other_seed.stdout);
+}
+
+/// An invalid network config is reported as an error, not a panic.
+#[test]
+fn stress_rejects_invalid_network() {
+    let output = run(&["stress", "--parties", "1", "--obligations", "5"]);
+
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("invalid configuration"), "stderr was: {}", stderr);
+    assert!(!stderr.contains("panicked"), "stderr was: {}", stderr);
+}
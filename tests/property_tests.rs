@@ -1,10 +1,10 @@
-use clearing_engine::core::currency::CurrencyCode;
+use clearing_engine::core::currency::{CurrencyCode, FxRateTable};
 use clearing_engine::core::obligation::{Obligation, ObligationSet};
 use clearing_engine::core::party::PartyId;
 use clearing_engine::graph::cycle_detection::find_cycles;
 use clearing_engine::graph::payment_graph::PaymentGraph;
 use clearing_engine::optimization::liquidity::LiquidityAnalysis;
-use clearing_engine::optimization::netting::NettingEngine;
+use clearing_engine::optimization::netting::{NettingEngine, MAX_ROUNDING_RESIDUAL};
 use proptest::prelude::*;
 use rust_decimal::Decimal;
 
@@ -97,7 +97,7 @@ proptest! {
         let result = NettingEngine::multilateral_net(&set);
         let pct = result.savings_percent();
         prop_assert!(
-            pct >= 0.0 && pct <= 100.0,
+            (0.0..=100.0).contains(&pct),
             "Savings percent {} must be in [0, 100]",
             pct
         );
@@ -236,4 +236,36 @@ proptest! {
             a_to_b, b_to_a, expected_net
         );
     }
+
+    // ===================================================================
+    // INVARIANT 10: Converted netting stays within one minor unit.
+    //
+    // Netting every obligation into a single settlement currency, with
+    // cross rates triangulated through the base, must not leave any
+    // currency imbalanced by more than one minor unit.
+    // ===================================================================
+    #[test]
+    fn converted_residual_within_minor_unit(
+        set in arb_obligation_set(),
+        brl_rate in 1u32..100_000u32,
+        inr_rate in 1u32..100_000u32,
+        settlement in arb_currency(),
+    ) {
+        let usd = CurrencyCode::new("USD");
+        let mut rates = FxRateTable::new(usd.clone());
+        rates
+            .set_rate(CurrencyCode::new("BRL"), usd.clone(), Decimal::new(brl_rate.into(), 5))
+            .unwrap();
+        rates
+            .set_rate(CurrencyCode::new("INR"), usd, Decimal::new(inr_rate.into(), 6))
+            .unwrap();
+
+        let result = NettingEngine::multilateral_net_with_fx(&set, &rates, &settlement).unwrap();
+        prop_assert!(
+            result.max_rounding_residual() <= MAX_ROUNDING_RESIDUAL,
+            "Conversion into {} drifted by {}",
+            settlement,
+            result.max_rounding_residual()
+        );
+    }
 }